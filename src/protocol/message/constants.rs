@@ -10,22 +10,31 @@ pub const MAGIC_LEN: usize = 4;
 pub const HEADER_LEN: usize = 24;
 /// Maximum message length (2 MiB).
 pub const MAX_MESSAGE_LEN: usize = 2 * 1024 * 1024;
+/// Maximum block size (2 MB), which also bounds the size of a single transaction.
+pub const MAX_BLOCK_SIZE: usize = 2_000_000;
 
 /// The current network protocol version number.
 pub const PROTOCOL_VERSION: u32 = 170_100;
-/// The current network version identifier.
+/// The network identifiers, see [`Network`](crate::protocol::network::Network).
 pub const MAGIC_TESTNET: [u8; MAGIC_LEN] = [0xfa, 0x1a, 0xf9, 0xbf];
 pub const MAGIC_MAINNET: [u8; MAGIC_LEN] = [0x24, 0xe9, 0x27, 0x64];
-
-#[cfg(test)]
-pub const MAGIC: [u8; MAGIC_LEN] = MAGIC_TESTNET;
-#[cfg(all(not(test), not(feature = "crawler")))]
-pub const MAGIC: [u8; MAGIC_LEN] = MAGIC_MAINNET;
-#[cfg(feature = "crawler")]
-pub const MAGIC: [u8; MAGIC_LEN] = MAGIC_MAINNET;
+pub const MAGIC_REGTEST: [u8; MAGIC_LEN] = [0xaa, 0xe8, 0x3f, 0x5f];
 
 pub const COMMAND_LEN: usize = 12;
 
+// Per-field limits, only enforced when decoding strictly (see
+// [`DecodeOptions::strict`](crate::protocol::payload::codec::DecodeOptions::strict)).
+/// Maximum number of entries in an inventory vector.
+pub const MAX_INV_SZ: usize = 50_000;
+/// Maximum number of addresses in an `addr` or `addrv2` message.
+pub const MAX_ADDR_TO_SEND: usize = 1_000;
+/// Maximum number of headers in a `headers` message.
+pub const MAX_HEADERS_RESULTS: usize = 160;
+/// Maximum length of a user agent.
+pub const MAX_SUBVERSION_LENGTH: usize = 256;
+/// Maximum length of the reason in a `reject` message.
+pub const MAX_REJECT_MESSAGE_LENGTH: usize = 111;
+
 // Message command bytes.
 pub const VERSION_COMMAND: [u8; COMMAND_LEN] = *b"version\0\0\0\0\0";
 pub const VERACK_COMMAND: [u8; COMMAND_LEN] = *b"verack\0\0\0\0\0\0";
@@ -46,3 +55,5 @@ pub const REJECT_COMMAND: [u8; COMMAND_LEN] = *b"reject\0\0\0\0\0\0";
 pub const FILTERLOAD_COMMAND: [u8; COMMAND_LEN] = *b"filterload\0\0";
 pub const FILTERADD_COMMAND: [u8; COMMAND_LEN] = *b"filteradd\0\0\0";
 pub const FILTERCLEAR_COMMAND: [u8; COMMAND_LEN] = *b"filterclear\0";
+pub const ADDRV2_COMMAND: [u8; COMMAND_LEN] = *b"addrv2\0\0\0\0\0\0";
+pub const SENDADDRV2_COMMAND: [u8; COMMAND_LEN] = *b"sendaddrv2\0\0";
@@ -0,0 +1,190 @@
+//! The registry of known message kinds and their metadata.
+
+use std::{fmt, io, str::FromStr};
+
+use crate::protocol::message::constants::*;
+
+/// The kind of a [`Message`](super::Message), one per variant (excluding
+/// [`Unknown`](super::Message::Unknown)).
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum MessageKind {
+    Version,
+    Verack,
+    Ping,
+    Pong,
+    GetAddr,
+    Addr,
+    GetHeaders,
+    Headers,
+    GetBlocks,
+    Block,
+    GetData,
+    Inv,
+    NotFound,
+    MemPool,
+    Tx,
+    Reject,
+    FilterLoad,
+    FilterAdd,
+    FilterClear,
+    AddrV2,
+    SendAddrV2,
+}
+
+/// The metadata of a [`MessageKind`].
+struct KindInfo {
+    command: [u8; COMMAND_LEN],
+    name: &'static str,
+    has_payload: bool,
+    responses: &'static [MessageKind],
+}
+
+impl MessageKind {
+    /// Every known message kind.
+    pub const ALL: [MessageKind; 21] = [
+        Self::Version,
+        Self::Verack,
+        Self::Ping,
+        Self::Pong,
+        Self::GetAddr,
+        Self::Addr,
+        Self::GetHeaders,
+        Self::Headers,
+        Self::GetBlocks,
+        Self::Block,
+        Self::GetData,
+        Self::Inv,
+        Self::NotFound,
+        Self::MemPool,
+        Self::Tx,
+        Self::Reject,
+        Self::FilterLoad,
+        Self::FilterAdd,
+        Self::FilterClear,
+        Self::AddrV2,
+        Self::SendAddrV2,
+    ];
+
+    /// The registry itself, the single source of the mapping between kinds and commands.
+    fn info(self) -> KindInfo {
+        use MessageKind::*;
+
+        let (command, name, has_payload, responses): (_, _, _, &'static [MessageKind]) = match self
+        {
+            Version => (VERSION_COMMAND, "Version", true, &[Verack]),
+            Verack => (VERACK_COMMAND, "Verack", false, &[]),
+            Ping => (PING_COMMAND, "Ping", true, &[Pong]),
+            Pong => (PONG_COMMAND, "Pong", true, &[]),
+            GetAddr => (GETADDR_COMMAND, "GetAddr", false, &[Addr, AddrV2]),
+            Addr => (ADDR_COMMAND, "Addr", true, &[]),
+            GetHeaders => (GETHEADERS_COMMAND, "GetHeaders", true, &[Headers]),
+            Headers => (HEADERS_COMMAND, "Headers", true, &[]),
+            GetBlocks => (GETBLOCKS_COMMAND, "GetBlocks", true, &[Inv]),
+            Block => (BLOCK_COMMAND, "Block", true, &[]),
+            GetData => (GETDATA_COMMAND, "GetData", true, &[Block, Tx, NotFound]),
+            Inv => (INV_COMMAND, "Inv", true, &[]),
+            NotFound => (NOTFOUND_COMMAND, "NotFound", true, &[]),
+            MemPool => (MEMPOOL_COMMAND, "MemPool", false, &[Inv]),
+            Tx => (TX_COMMAND, "Tx", true, &[]),
+            Reject => (REJECT_COMMAND, "Reject", true, &[]),
+            FilterLoad => (FILTERLOAD_COMMAND, "FilterLoad", true, &[]),
+            FilterAdd => (FILTERADD_COMMAND, "FilterAdd", true, &[]),
+            FilterClear => (FILTERCLEAR_COMMAND, "FilterClear", false, &[]),
+            AddrV2 => (ADDRV2_COMMAND, "AddrV2", true, &[]),
+            SendAddrV2 => (SENDADDRV2_COMMAND, "SendAddrV2", false, &[]),
+        };
+
+        KindInfo {
+            command,
+            name,
+            has_payload,
+            responses,
+        }
+    }
+
+    /// Returns the kind of messages with the command, if known.
+    pub fn from_command(command: [u8; COMMAND_LEN]) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.command() == command)
+    }
+
+    /// Returns the command sent in the header of messages of this kind.
+    pub fn command(self) -> [u8; COMMAND_LEN] {
+        self.info().command
+    }
+
+    /// Returns the name of this kind, as used by [`Display`](fmt::Display).
+    pub fn name(self) -> &'static str {
+        self.info().name
+    }
+
+    /// Returns `true` if messages of this kind carry a payload.
+    pub fn has_payload(self) -> bool {
+        self.info().has_payload
+    }
+
+    /// Returns `true` if messages of this kind call for a response.
+    pub fn is_request(self) -> bool {
+        !self.expected_responses().is_empty()
+    }
+
+    /// Returns the kinds of message a peer may respond with to a message of this kind.
+    pub fn expected_responses(self) -> &'static [MessageKind] {
+        self.info().responses
+    }
+
+    /// Returns the maximum body length the protocol allows for messages of this kind.
+    pub fn max_body_length(self) -> usize {
+        match self {
+            Self::Ping | Self::Pong => 8,
+            Self::Block | Self::Tx => MAX_BLOCK_SIZE,
+            kind if !kind.has_payload() => 0,
+            _ => MAX_MESSAGE_LEN,
+        }
+    }
+}
+
+impl fmt::Display for MessageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for MessageKind {
+    type Err = io::Error;
+
+    /// Parses a kind from its name or its command, case-insensitively (e.g. `GetHeaders` or
+    /// `getheaders`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown message kind {s:?}"),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn registry_is_consistent() {
+        for kind in MessageKind::ALL {
+            assert_eq!(MessageKind::from_command(kind.command()), Some(kind));
+            assert_eq!(kind.to_string().parse::<MessageKind>().unwrap(), kind);
+
+            // Names double as commands, modulo case and padding.
+            let command = String::from_utf8_lossy(&kind.command()).replace('\0', "");
+            assert_eq!(command.parse::<MessageKind>().unwrap(), kind);
+
+            for response in kind.expected_responses() {
+                assert!(!response.is_request());
+            }
+        }
+    }
+}
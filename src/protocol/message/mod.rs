@@ -2,24 +2,33 @@
 
 pub mod constants;
 
+pub mod kind;
+pub use kind::MessageKind;
+
+pub mod view;
+pub use view::{LazyMessage, MessageRef};
+
 use std::io;
 
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
 use crate::protocol::{
     message::constants::*,
+    network::Network,
     payload::{
         block::{Block, Headers, LocatorHashes},
-        codec::Codec,
-        Addr, FilterAdd, FilterLoad, Inv, Nonce, Reject, Tx, Version,
+        codec::{Codec, DecodeOptions},
+        serde_helpers::as_hex,
+        Addr, AddrV2, FilterAdd, FilterLoad, Inv, Nonce, ProtocolError, Reject, Tx, Version,
     },
 };
 
 /// The header of a network message.
 #[derive(Debug, Default, Clone)]
 pub struct MessageHeader {
-    /// The network protocol version.
+    /// The magic bytes identifying the network.
     pub magic: [u8; MAGIC_LEN],
     /// The message command, identifies the type of message being sent.
     pub command: [u8; COMMAND_LEN],
@@ -39,9 +48,9 @@ impl Codec for MessageHeader {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
         if bytes.remaining() < HEADER_LEN {
-            return Err(io::ErrorKind::InvalidData.into());
+            return Err(ProtocolError::unexpected_eof(HEADER_LEN, bytes.remaining()).into());
         }
 
         let mut magic = [0u8; MAGIC_LEN];
@@ -60,22 +69,35 @@ impl Codec for MessageHeader {
 }
 
 impl MessageHeader {
-    /// Returns a `MessageHeader` constructed from the message body.
-    pub fn new(command: [u8; COMMAND_LEN], body: &[u8]) -> Self {
+    /// Returns a `MessageHeader` for the given network, constructed from the message body.
+    pub fn new(network: Network, command: [u8; COMMAND_LEN], body: &[u8]) -> Self {
         MessageHeader {
-            magic: MAGIC,
+            magic: network.magic(),
             command,
             body_length: body.len() as u32,
             checksum: checksum(body),
         }
     }
+
+    /// Returns the maximum body length the protocol allows for a message with this header's
+    /// command.
+    ///
+    /// Unknown commands are only bounded by [`MAX_MESSAGE_LEN`].
+    pub fn max_body_length(&self) -> usize {
+        MessageKind::from_command(self.command)
+            .map_or(MAX_MESSAGE_LEN, MessageKind::max_body_length)
+    }
 }
 
 /// A network message.
 ///
 /// All the message types and their payloads are documented by the [Bitcoin protocol
 /// documentation](https://en.bitcoin.it/wiki/Protocol_documentation#Message_types).
-#[derive(Debug, PartialEq, Clone)]
+///
+/// Messages serialize (e.g. to JSON) as their command and payload, with byte strings and hashes
+/// in hex and timestamps in RFC 3339, e.g. `{"command":"ping","payload":12345}`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", content = "payload", rename_all = "lowercase")]
 pub enum Message {
     Version(Version),
     Verack,
@@ -96,116 +118,202 @@ pub enum Message {
     FilterLoad(FilterLoad),
     FilterAdd(FilterAdd),
     FilterClear,
+    AddrV2(AddrV2),
+    SendAddrV2,
+    /// A message with a command we don't model, kept as-is so it can be inspected and re-encoded
+    /// byte-for-byte.
+    Unknown {
+        /// The raw command bytes from the message header.
+        #[serde(with = "as_hex")]
+        command: [u8; COMMAND_LEN],
+        /// The undecoded message body.
+        #[serde(with = "as_hex")]
+        payload: Bytes,
+    },
 }
 
 macro_rules! encode_with_header_prefix {
-    ($command:expr, $buffer:expr) => {{
-        let header = MessageHeader::new($command, &[]);
+    ($network:expr, $command:expr, $buffer:expr) => {{
+        let header = MessageHeader::new($network, $command, &[]);
         header.encode($buffer)?;
     }};
 
-    ($command:expr, $buffer:expr, $payload:expr) => {{
+    ($network:expr, $command:expr, $buffer:expr, $payload:expr) => {{
         $payload.encode($buffer)?;
         let serialized_payload = $buffer.split_to($buffer.len()).freeze();
-        let header = MessageHeader::new($command, &serialized_payload);
+        let header = MessageHeader::new($network, $command, &serialized_payload);
         header.encode($buffer)?;
         $buffer.put_slice(&serialized_payload);
     }};
 }
 
 impl Message {
-    /// Encodes a message into the supplied buffer and returns its header.
-    pub fn encode(&self, buffer: &mut BytesMut) -> io::Result<()> {
+    /// Returns the command sent in this message's header.
+    pub fn command(&self) -> [u8; COMMAND_LEN] {
         match self {
-            Self::Version(version) => {
-                encode_with_header_prefix!(VERSION_COMMAND, buffer, version);
-            }
-            Self::Verack => {
-                encode_with_header_prefix!(VERACK_COMMAND, buffer);
-            }
-            Self::Ping(nonce) => {
-                encode_with_header_prefix!(PING_COMMAND, buffer, nonce);
-            }
-            Self::Pong(nonce) => {
-                encode_with_header_prefix!(PONG_COMMAND, buffer, nonce);
-            }
-            Self::GetAddr => {
-                encode_with_header_prefix!(GETADDR_COMMAND, buffer);
-            }
-            Self::Addr(addr) => {
-                encode_with_header_prefix!(ADDR_COMMAND, buffer, addr);
-            }
-            Self::GetHeaders(locator_hashes) => {
-                encode_with_header_prefix!(GETHEADERS_COMMAND, buffer, locator_hashes);
-            }
-            Self::Headers(headers) => {
-                encode_with_header_prefix!(HEADERS_COMMAND, buffer, headers);
-            }
-            Self::GetBlocks(locator_hashes) => {
-                encode_with_header_prefix!(GETBLOCKS_COMMAND, buffer, locator_hashes);
-            }
-            Self::Block(block) => {
-                encode_with_header_prefix!(BLOCK_COMMAND, buffer, block);
-            }
-            Self::GetData(inv) => {
-                encode_with_header_prefix!(GETDATA_COMMAND, buffer, inv);
-            }
-            Self::Inv(inv) => {
-                encode_with_header_prefix!(INV_COMMAND, buffer, inv);
+            Self::Unknown { command, .. } => *command,
+            message => message.kind().unwrap().command(),
+        }
+    }
+
+    /// Returns this message's kind, `None` for [`Message::Unknown`].
+    pub fn kind(&self) -> Option<MessageKind> {
+        let kind = match self {
+            Self::Version(_) => MessageKind::Version,
+            Self::Verack => MessageKind::Verack,
+            Self::Ping(_) => MessageKind::Ping,
+            Self::Pong(_) => MessageKind::Pong,
+            Self::GetAddr => MessageKind::GetAddr,
+            Self::Addr(_) => MessageKind::Addr,
+            Self::GetHeaders(_) => MessageKind::GetHeaders,
+            Self::Headers(_) => MessageKind::Headers,
+            Self::GetBlocks(_) => MessageKind::GetBlocks,
+            Self::Block(_) => MessageKind::Block,
+            Self::GetData(_) => MessageKind::GetData,
+            Self::Inv(_) => MessageKind::Inv,
+            Self::NotFound(_) => MessageKind::NotFound,
+            Self::MemPool => MessageKind::MemPool,
+            Self::Tx(_) => MessageKind::Tx,
+            Self::Reject(_) => MessageKind::Reject,
+            Self::FilterLoad(_) => MessageKind::FilterLoad,
+            Self::FilterAdd(_) => MessageKind::FilterAdd,
+            Self::FilterClear => MessageKind::FilterClear,
+            Self::AddrV2(_) => MessageKind::AddrV2,
+            Self::SendAddrV2 => MessageKind::SendAddrV2,
+            Self::Unknown { .. } => return None,
+        };
+
+        Some(kind)
+    }
+
+    /// Returns `true` if this message is a response to `request`.
+    ///
+    /// The message's kind must be one of the request kind's
+    /// [`expected_responses`](MessageKind::expected_responses). Beyond that, a [`Pong`](Self::Pong)
+    /// must carry the [`Ping`](Self::Ping)'s nonce, and the objects sent in reply to a
+    /// [`GetData`](Self::GetData) must have been part of its inventory.
+    pub fn is_response_to(&self, request: &Message) -> bool {
+        match (request.kind(), self.kind()) {
+            (Some(request_kind), Some(kind))
+                if request_kind.expected_responses().contains(&kind) => {}
+            _ => return false,
+        }
+
+        match (request, self) {
+            (Self::Ping(nonce), Self::Pong(response_nonce)) => nonce == response_nonce,
+            (Self::GetData(inv), Self::Block(block)) => inv.inventory.contains(&block.inv_hash()),
+            (Self::GetData(inv), Self::Tx(tx)) => {
+                inv.inventory.contains(&tx.inv_hash()) || inv.inventory.contains(&tx.wtx_inv_hash())
             }
-            Self::NotFound(inv) => {
-                encode_with_header_prefix!(NOTFOUND_COMMAND, buffer, inv);
+            (Self::GetData(inv), Self::NotFound(not_found)) => not_found
+                .inventory
+                .iter()
+                .any(|inv_hash| inv.inventory.contains(inv_hash)),
+            _ => true,
+        }
+    }
+
+    /// Encodes a message for the given network into the supplied buffer, prefixed by its header.
+    pub fn encode(&self, network: Network, buffer: &mut BytesMut) -> io::Result<()> {
+        let command = self.command();
+
+        match self {
+            Self::Verack | Self::GetAddr | Self::MemPool | Self::FilterClear | Self::SendAddrV2 => {
+                encode_with_header_prefix!(network, command, buffer);
             }
-            Self::MemPool => {
-                encode_with_header_prefix!(MEMPOOL_COMMAND, buffer);
+            Self::Version(version) => encode_with_header_prefix!(network, command, buffer, version),
+            Self::Ping(nonce) | Self::Pong(nonce) => {
+                encode_with_header_prefix!(network, command, buffer, nonce);
             }
-            Self::Tx(tx) => {
-                encode_with_header_prefix!(TX_COMMAND, buffer, tx);
+            Self::Addr(addr) => encode_with_header_prefix!(network, command, buffer, addr),
+            Self::GetHeaders(locator_hashes) | Self::GetBlocks(locator_hashes) => {
+                encode_with_header_prefix!(network, command, buffer, locator_hashes);
             }
-            Self::Reject(reject) => {
-                encode_with_header_prefix!(REJECT_COMMAND, buffer, reject);
+            Self::Headers(headers) => encode_with_header_prefix!(network, command, buffer, headers),
+            Self::Block(block) => encode_with_header_prefix!(network, command, buffer, block),
+            Self::GetData(inv) | Self::Inv(inv) | Self::NotFound(inv) => {
+                encode_with_header_prefix!(network, command, buffer, inv);
             }
+            Self::Tx(tx) => encode_with_header_prefix!(network, command, buffer, tx),
+            Self::Reject(reject) => encode_with_header_prefix!(network, command, buffer, reject),
             Self::FilterLoad(filter_load) => {
-                encode_with_header_prefix!(FILTERLOAD_COMMAND, buffer, filter_load);
-            }
-            Self::FilterAdd(filter) => {
-                encode_with_header_prefix!(FILTERADD_COMMAND, buffer, filter);
+                encode_with_header_prefix!(network, command, buffer, filter_load);
             }
-            Self::FilterClear => {
-                encode_with_header_prefix!(FILTERCLEAR_COMMAND, buffer);
+            Self::FilterAdd(filter) => encode_with_header_prefix!(network, command, buffer, filter),
+            Self::AddrV2(addr) => encode_with_header_prefix!(network, command, buffer, addr),
+            Self::Unknown { payload, .. } => {
+                let header = MessageHeader::new(network, command, payload);
+                header.encode(buffer)?;
+                buffer.put_slice(payload);
             }
         }
 
         Ok(())
     }
 
-    /// Decodes the bytes into a message.
+    /// Decodes the bytes into a message, permissively (see [`DecodeOptions`]).
+    ///
+    /// Errors wrap a [`ProtocolError`] locating the failure within the message body.
     pub fn decode<B: Buf>(command: [u8; 12], bytes: &mut B) -> io::Result<Self> {
-        let message = match command {
-            VERSION_COMMAND => Self::Version(Version::decode(bytes)?),
-            VERACK_COMMAND => Self::Verack,
-            PING_COMMAND => Self::Ping(Nonce::decode(bytes)?),
-            PONG_COMMAND => Self::Pong(Nonce::decode(bytes)?),
-            GETADDR_COMMAND => Self::GetAddr,
-            ADDR_COMMAND => Self::Addr(Addr::decode(bytes)?),
-            GETHEADERS_COMMAND => Self::GetHeaders(LocatorHashes::decode(bytes)?),
-            HEADERS_COMMAND => Self::Headers(Headers::decode(bytes)?),
-            GETBLOCKS_COMMAND => Self::GetBlocks(LocatorHashes::decode(bytes)?),
-            BLOCK_COMMAND => Self::Block(Box::new(Block::decode(bytes)?)),
-            GETDATA_COMMAND => Self::GetData(Inv::decode(bytes)?),
-            INV_COMMAND => Self::Inv(Inv::decode(bytes)?),
-            NOTFOUND_COMMAND => Self::NotFound(Inv::decode(bytes)?),
-            MEMPOOL_COMMAND => Self::MemPool,
-            TX_COMMAND => Self::Tx(Tx::decode(bytes)?),
-            REJECT_COMMAND => Self::Reject(Reject::decode(bytes)?),
-            cmd => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    format!("Unknown command string: {cmd:?}"),
-                ))
+        Self::decode_with(command, bytes, DecodeOptions::default())
+    }
+
+    /// Decodes the bytes into a message with the given options, e.g. [`DecodeOptions::strict`] to
+    /// reject non-canonical encodings and fields exceeding their maximums.
+    ///
+    /// Errors wrap a [`ProtocolError`] locating the failure within the message body.
+    pub fn decode_with<B: Buf>(
+        command: [u8; 12],
+        bytes: &mut B,
+        options: DecodeOptions,
+    ) -> io::Result<Self> {
+        let body_length = bytes.remaining();
+
+        Self::decode_payload(command, bytes, options)
+            .map_err(|err| ProtocolError::locate(err, command, body_length))
+    }
+
+    fn decode_payload<B: Buf>(
+        command: [u8; 12],
+        bytes: &mut B,
+        options: DecodeOptions,
+    ) -> io::Result<Self> {
+        let kind = match MessageKind::from_command(command) {
+            Some(kind) => kind,
+            None => {
+                return Ok(Self::Unknown {
+                    command,
+                    payload: bytes.copy_to_bytes(bytes.remaining()),
+                })
             }
         };
 
+        let message = match kind {
+            MessageKind::Version => Self::Version(Version::decode_with(bytes, options)?),
+            MessageKind::Verack => Self::Verack,
+            MessageKind::Ping => Self::Ping(Nonce::decode_with(bytes, options)?),
+            MessageKind::Pong => Self::Pong(Nonce::decode_with(bytes, options)?),
+            MessageKind::GetAddr => Self::GetAddr,
+            MessageKind::Addr => Self::Addr(Addr::decode_with(bytes, options)?),
+            MessageKind::GetHeaders => {
+                Self::GetHeaders(LocatorHashes::decode_with(bytes, options)?)
+            }
+            MessageKind::Headers => Self::Headers(Headers::decode_with(bytes, options)?),
+            MessageKind::GetBlocks => Self::GetBlocks(LocatorHashes::decode_with(bytes, options)?),
+            MessageKind::Block => Self::Block(Box::new(Block::decode_with(bytes, options)?)),
+            MessageKind::GetData => Self::GetData(Inv::decode_with(bytes, options)?),
+            MessageKind::Inv => Self::Inv(Inv::decode_with(bytes, options)?),
+            MessageKind::NotFound => Self::NotFound(Inv::decode_with(bytes, options)?),
+            MessageKind::MemPool => Self::MemPool,
+            MessageKind::Tx => Self::Tx(Tx::decode_with(bytes, options)?),
+            MessageKind::Reject => Self::Reject(Reject::decode_with(bytes, options)?),
+            MessageKind::FilterLoad => Self::FilterLoad(FilterLoad::decode_with(bytes, options)?),
+            MessageKind::FilterAdd => Self::FilterAdd(FilterAdd::decode_with(bytes, options)?),
+            MessageKind::FilterClear => Self::FilterClear,
+            MessageKind::AddrV2 => Self::AddrV2(AddrV2::decode_with(bytes, options)?),
+            MessageKind::SendAddrV2 => Self::SendAddrV2,
+        };
+
         Ok(message)
     }
 }
@@ -213,30 +321,19 @@ impl Message {
 impl std::fmt::Display for Message {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Message::Version(_) => f.write_str("Version"),
-            Message::Verack => f.write_str("Verack"),
             Message::Ping(nonce) => f.write_fmt(format_args!("Ping({nonce:?})")),
             Message::Pong(nonce) => f.write_fmt(format_args!("Pong({nonce:?})")),
-            Message::GetAddr => f.write_str("GetAddr"),
-            Message::Addr(_) => f.write_str("Addr"),
-            Message::GetHeaders(_) => f.write_str("GetHeaders"),
-            Message::Headers(_) => f.write_str("Headers"),
-            Message::GetBlocks(_) => f.write_str("GetBlocks"),
-            Message::Block(_) => f.write_str("Block"),
-            Message::GetData(_) => f.write_str("GetData"),
-            Message::Inv(_) => f.write_str("Inv"),
-            Message::NotFound(_) => f.write_str("NotFound"),
-            Message::MemPool => f.write_str("MemPool"),
-            Message::Tx(_) => f.write_str("Tx"),
             Message::Reject(reject) => f.write_fmt(format_args!("Reject({:?})", reject.ccode)),
-            Message::FilterLoad(_) => f.write_str("FilterLoad"),
-            Message::FilterAdd(_) => f.write_str("FilterAdd"),
-            Message::FilterClear => f.write_str("FilterClear"),
+            Message::Unknown { command, .. } => f.write_fmt(format_args!(
+                "Unknown({})",
+                String::from_utf8_lossy(command).trim_end_matches('\0')
+            )),
+            message => f.write_str(message.kind().unwrap().name()),
         }
     }
 }
-
-fn checksum(bytes: &[u8]) -> u32 {
+/// Computes the checksum of a message body, the first 4 bytes of its double Sha256 hash.
+pub fn checksum(bytes: &[u8]) -> u32 {
     let sha2 = Sha256::digest(bytes);
     let sha2d = Sha256::digest(sha2);
 
@@ -245,3 +342,321 @@ fn checksum(bytes: &[u8]) -> u32 {
 
     u32::from_le_bytes(checksum)
 }
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::protocol::payload::{
+        addr::{HostAddr, NetworkAddr, NetworkAddrV2},
+        error::{FieldSegment, ProtocolErrorKind},
+        reject::CCode,
+        Hash, Services, VarStr,
+    };
+
+    fn round_trip(message: Message) -> Message {
+        let mut buffer = BytesMut::new();
+        message.encode(Network::default(), &mut buffer).unwrap();
+
+        let header = MessageHeader::decode(&mut buffer).unwrap();
+        assert_eq!(header.body_length as usize, buffer.len());
+
+        Message::decode(header.command, &mut buffer).unwrap()
+    }
+
+    #[test]
+    #[ignore]
+    fn filter_load_round_trip() {
+        let message = Message::FilterLoad(FilterLoad {
+            filter: vec![0xab; 128],
+            hash_fn_count: 11,
+            tweak: 0xdead_beef,
+            flags: 1,
+        });
+
+        assert_eq!(round_trip(message.clone()), message);
+    }
+
+    #[test]
+    #[ignore]
+    fn filter_add_round_trip() {
+        let message = Message::FilterAdd(FilterAdd {
+            data: vec![0xcd; 32],
+        });
+
+        assert_eq!(round_trip(message.clone()), message);
+    }
+
+    #[test]
+    #[ignore]
+    fn filter_clear_round_trip() {
+        assert_eq!(round_trip(Message::FilterClear), Message::FilterClear);
+    }
+
+    #[test]
+    #[ignore]
+    fn addr_v2_round_trip() {
+        let mut addrs = vec![
+            NetworkAddrV2::new("127.0.0.1:8233".parse().unwrap()),
+            NetworkAddrV2::new("[::1]:18233".parse().unwrap()),
+        ];
+        addrs[0].last_seen = time::OffsetDateTime::from_unix_timestamp(1_600_000_000).unwrap();
+        addrs[1].last_seen = addrs[0].last_seen;
+        addrs.push(NetworkAddrV2 {
+            addr: HostAddr::TorV3([0x42; 32]),
+            port: 8233,
+            ..addrs[0].clone()
+        });
+        addrs.push(NetworkAddrV2 {
+            addr: HostAddr::Unknown {
+                network_id: 0x2a,
+                bytes: vec![0xee; 7],
+            },
+            services: Services::from_bits(1 << 40),
+            port: 0,
+            ..addrs[0].clone()
+        });
+        let message = Message::AddrV2(AddrV2::new(addrs));
+
+        assert_eq!(round_trip(message.clone()), message);
+        json_round_trip(&message);
+        assert_eq!(round_trip(Message::SendAddrV2), Message::SendAddrV2);
+        assert!(message.is_response_to(&Message::GetAddr));
+    }
+
+    #[test]
+    #[ignore]
+    fn unknown_round_trip() {
+        let message = Message::Unknown {
+            command: *b"feefilter\0\0\0",
+            payload: Bytes::from_static(&[0xe8, 0x03, 0, 0, 0, 0, 0, 0]),
+        };
+
+        assert_eq!(round_trip(message.clone()), message);
+        assert_eq!(message.to_string(), "Unknown(feefilter)");
+    }
+
+    #[test]
+    #[ignore]
+    fn unknown_is_re_encoded_byte_for_byte() {
+        let mut original = BytesMut::new();
+        let payload = [0x01, 0x02, 0x03];
+        MessageHeader::new(Network::default(), *b"sendcmpct\0\0\0", &payload)
+            .encode(&mut original)
+            .unwrap();
+        original.put_slice(&payload);
+
+        let mut bytes = original.clone();
+        let header = MessageHeader::decode(&mut bytes).unwrap();
+        let message = Message::decode(header.command, &mut bytes).unwrap();
+
+        let mut buffer = BytesMut::new();
+        message.encode(Network::default(), &mut buffer).unwrap();
+        assert_eq!(buffer, original);
+    }
+
+    #[test]
+    #[ignore]
+    fn truncated_payload_error_is_located() {
+        let addr = "127.0.0.1:8080".parse().unwrap();
+        let mut buffer = BytesMut::new();
+        Message::Version(Version::new(addr, addr))
+            .encode(Network::default(), &mut buffer)
+            .unwrap();
+
+        let header = MessageHeader::decode(&mut buffer).unwrap();
+        // Cut the body off just before the user agent.
+        let mut body = buffer.split_to(80);
+
+        let err = Message::decode(header.command, &mut body).unwrap_err();
+        let protocol_error = ProtocolError::from_io_error(&err).unwrap();
+        assert_eq!(protocol_error.command, Some(VERSION_COMMAND));
+        assert_eq!(protocol_error.path, vec![FieldSegment::Field("user_agent")]);
+        assert_eq!(protocol_error.offset, Some(80));
+        assert_eq!(
+            protocol_error.kind,
+            ProtocolErrorKind::UnexpectedEof {
+                expected: 1,
+                actual: 0
+            }
+        );
+    }
+
+    fn decode_frame(bytes: &[u8], options: DecodeOptions) -> io::Result<Message> {
+        let mut bytes = bytes;
+        let header = MessageHeader::decode(&mut bytes).unwrap();
+        assert_eq!(header.checksum, checksum(bytes));
+
+        Message::decode_with(header.command, &mut bytes, options)
+    }
+
+    #[test]
+    #[ignore]
+    fn strict_decoding_rejects_malleable_encodings() {
+        use crate::tools::fuzzing::encode_with_non_canonical_compact_size;
+
+        let block = Block::testnet_1();
+        let message = Message::Inv(Inv::new(vec![block.inv_hash(); 2]));
+
+        let encodings = encode_with_non_canonical_compact_size(&message);
+        assert_eq!(encodings.len(), 3);
+        for bytes in encodings {
+            assert_eq!(
+                decode_frame(&bytes, DecodeOptions::default()).unwrap(),
+                message
+            );
+
+            let err = decode_frame(&bytes, DecodeOptions::strict()).unwrap_err();
+            let protocol_error = ProtocolError::from_io_error(&err).unwrap();
+            assert_eq!(protocol_error.path, vec![FieldSegment::Field("inventory")]);
+            assert_matches!(protocol_error.kind, ProtocolErrorKind::InvalidValue { .. });
+        }
+        assert!(encode_with_non_canonical_compact_size(&Message::Verack).is_empty());
+
+        // Per-field maximums.
+        let addr = "127.0.0.1:8233".parse().unwrap();
+        let mut version = Version::new(addr, addr);
+        version.timestamp = time::OffsetDateTime::from_unix_timestamp(1_600_000_000).unwrap();
+        version.user_agent = VarStr("a".repeat(MAX_SUBVERSION_LENGTH + 1));
+        let oversized = [
+            Message::Inv(Inv::new(vec![block.inv_hash(); MAX_INV_SZ + 1])),
+            Message::Headers(Headers::new(vec![
+                block.header.clone();
+                MAX_HEADERS_RESULTS + 1
+            ])),
+            Message::Version(version),
+        ];
+        for message in oversized {
+            let mut bytes = BytesMut::new();
+            message.encode(Network::default(), &mut bytes).unwrap();
+
+            assert_eq!(
+                decode_frame(&bytes, DecodeOptions::default()).unwrap(),
+                message
+            );
+            let err = decode_frame(&bytes, DecodeOptions::strict()).unwrap_err();
+            assert_matches!(
+                ProtocolError::from_io_error(&err).unwrap().kind,
+                ProtocolErrorKind::TooLarge { .. }
+            );
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn command_matches_encoded_header() {
+        let messages = [
+            Message::Verack,
+            Message::Ping(Nonce::default()),
+            Message::GetAddr,
+            Message::Inv(Inv::empty()),
+            Message::FilterClear,
+            Message::Unknown {
+                command: *b"feefilter\0\0\0",
+                payload: Bytes::new(),
+            },
+        ];
+
+        for message in messages {
+            let mut buffer = BytesMut::new();
+            message.encode(Network::default(), &mut buffer).unwrap();
+
+            let header = MessageHeader::decode(&mut buffer).unwrap();
+            assert_eq!(header.command, message.command());
+            assert_eq!(message.kind(), MessageKind::from_command(header.command));
+        }
+    }
+
+    fn json_round_trip(message: &Message) -> serde_json::Value {
+        let json = serde_json::to_value(message).unwrap();
+        assert_eq!(
+            &serde_json::from_value::<Message>(json.clone()).unwrap(),
+            message
+        );
+
+        json
+    }
+
+    #[test]
+    #[ignore]
+    fn json_round_trip_all_blocks() {
+        use crate::vectors::*;
+
+        for bytes in [
+            &BLOCK_TESTNET_GENESIS_BYTES[..],
+            &BLOCK_TESTNET_0_280_000_BYTES[..],
+            &BLOCK_TESTNET_1_028_500_BYTES[..],
+            &BLOCK_TESTNET_1_599_200_BYTES[..],
+            &BLOCK_TESTNET_1_599_201_BYTES[..],
+        ] {
+            let block = Block::decode(&mut &bytes[..]).unwrap();
+            json_round_trip(&Message::Block(Box::new(block)));
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn json_representation() {
+        let addr = "127.0.0.1:8233".parse().unwrap();
+        let mut version = Version::new(addr, addr);
+        version.timestamp = time::OffsetDateTime::from_unix_timestamp(1_600_000_000).unwrap();
+
+        let json = json_round_trip(&Message::Version(version));
+        assert_eq!(json["command"], "version");
+        assert_eq!(json["payload"]["timestamp"], "2020-09-13T12:26:40Z");
+        assert_eq!(json["payload"]["addr_recv"]["addr"], "127.0.0.1:8233");
+
+        // Hashes are in the byte order zcashd displays them in.
+        let genesis = Block::testnet_genesis();
+        let json = json_round_trip(&Message::Inv(Inv::new(vec![genesis.inv_hash()])));
+        assert_eq!(
+            json["payload"]["inventory"][0]["hash"],
+            "05a60a92d99d85997cce3b87616c089f6124d7342af37106edc76126334a2c38"
+        );
+
+        let json = json_round_trip(&Message::Verack);
+        assert_eq!(json, serde_json::json!({ "command": "verack" }));
+
+        let json = json_round_trip(&Message::Unknown {
+            command: *b"feefilter\0\0\0",
+            payload: Bytes::from_static(&[0xe8, 0x03]),
+        });
+        assert_eq!(json["payload"]["payload"], "e803");
+
+        json_round_trip(&Message::Reject(Reject {
+            message: VarStr("tx".to_owned()),
+            ccode: CCode::Invalid,
+            reason: VarStr("bad-txns".to_owned()),
+            data: vec![0xaa; 32],
+        }));
+        json_round_trip(&Message::Addr(Addr::new(vec![NetworkAddr::new(addr)])));
+    }
+
+    #[test]
+    #[ignore]
+    fn responses_are_correlated_with_requests() {
+        let nonce = Nonce::default();
+        let ping = Message::Ping(nonce);
+        assert!(Message::Pong(nonce).is_response_to(&ping));
+        assert!(!Message::Pong(Nonce::default()).is_response_to(&ping));
+        assert!(!Message::Ping(nonce).is_response_to(&ping));
+
+        let get_headers = Message::GetHeaders(LocatorHashes::new(vec![], Hash::zeroed()));
+        assert!(Message::Headers(Headers::empty()).is_response_to(&get_headers));
+        assert!(!Message::Inv(Inv::empty()).is_response_to(&get_headers));
+
+        let block = Block::testnet_1();
+        let get_data = Message::GetData(Inv::new(vec![block.inv_hash()]));
+        assert!(Message::Block(Box::new(block.clone())).is_response_to(&get_data));
+        assert!(Message::NotFound(Inv::new(vec![block.inv_hash()])).is_response_to(&get_data));
+        assert!(!Message::Block(Box::new(Block::testnet_2())).is_response_to(&get_data));
+
+        let tx = block.txs[0].clone();
+        let get_data = Message::GetData(Inv::new(vec![tx.wtx_inv_hash()]));
+        assert!(Message::Tx(tx).is_response_to(&get_data));
+
+        // Messages which aren't requests have no responses.
+        assert!(!Message::GetData(Inv::empty()).is_response_to(&Message::Inv(Inv::empty())));
+    }
+}
@@ -0,0 +1,369 @@
+//! Lazily decoded views over encoded messages.
+//!
+//! Decoding a [`Message`] copies its whole payload into owned types, which is wasteful when only
+//! a few fields are inspected (e.g. the hash of a received block). The types in this module keep
+//! the encoded body around instead and decode fields on demand, with the [`DecodeOptions`] set on
+//! the view.
+
+use std::{
+    convert::TryInto,
+    io::{self, Error, ErrorKind},
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use sha2::{Digest, Sha256};
+
+use crate::protocol::{
+    message::{checksum, constants::*, Message, MessageHeader},
+    network::Network,
+    payload::{
+        block::Header,
+        codec::{Codec, DecodeOptions},
+        Hash, Nonce, ProtocolError, Tx, VarInt,
+    },
+};
+
+/// The length of a block header up to (excluding) the Equihash solution.
+const BLOCK_HEADER_PREFIX_LEN: usize = 4 + 32 + 32 + 32 + 4 + 4 + 32;
+
+/// A borrowed view of an encoded message: its header and undecoded body.
+#[derive(Debug, Clone)]
+pub struct MessageRef<'a> {
+    header: MessageHeader,
+    body: &'a [u8],
+    options: DecodeOptions,
+}
+
+impl<'a> MessageRef<'a> {
+    /// Creates a view of a message from its header and body, decoded permissively.
+    pub fn new(header: MessageHeader, body: &'a [u8]) -> Self {
+        Self {
+            header,
+            body,
+            options: DecodeOptions::default(),
+        }
+    }
+
+    /// Sets the options the message and its fields are decoded with.
+    pub fn with_decode_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Creates a view of a message from a single encoded frame, header included.
+    ///
+    /// The frame's length must match the body length given in its header.
+    pub fn from_frame(frame: &'a [u8]) -> io::Result<Self> {
+        let mut bytes = frame;
+        let header = MessageHeader::decode(&mut bytes)?;
+
+        let body_length = header.body_length as usize;
+        if bytes.len() < body_length {
+            return Err(ProtocolError::unexpected_eof(body_length, bytes.len()).into());
+        }
+        if bytes.len() > body_length {
+            return Err(ProtocolError::invalid_value(
+                format!("a body of {body_length} bytes"),
+                format!("{} bytes", bytes.len()),
+                bytes.len(),
+            )
+            .into());
+        }
+
+        Ok(Self::new(header, bytes))
+    }
+
+    /// Returns the message's header.
+    pub fn header(&self) -> &MessageHeader {
+        &self.header
+    }
+
+    /// Returns the message's command.
+    pub fn command(&self) -> [u8; COMMAND_LEN] {
+        self.header.command
+    }
+
+    /// Returns the message's encoded body.
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+
+    /// Returns `true` if the header's checksum matches the body.
+    pub fn has_valid_checksum(&self) -> bool {
+        self.header.checksum == checksum(self.body)
+    }
+
+    /// Decodes the whole message.
+    pub fn decode(&self) -> io::Result<Message> {
+        Message::decode_with(self.header.command, &mut &self.body[..], self.options)
+    }
+
+    /// Decodes the [`Nonce`] of a [`Ping`](Message::Ping) or [`Pong`](Message::Pong).
+    pub fn nonce(&self) -> io::Result<Nonce> {
+        self.expect_command(&[PING_COMMAND, PONG_COMMAND])?;
+
+        self.decode_field(Nonce::decode_with)
+    }
+
+    /// Decodes the header of a [`Block`](Message::Block), skipping its transactions.
+    pub fn block_header(&self) -> io::Result<Header> {
+        self.expect_command(&[BLOCK_COMMAND])?;
+
+        self.decode_field(Header::decode_without_tx_count)
+    }
+
+    /// Computes the hash of a [`Block`](Message::Block) straight from its encoded header.
+    pub fn block_hash(&self) -> io::Result<Hash> {
+        self.expect_command(&[BLOCK_COMMAND])?;
+
+        let header_len = self.block_header_len()?;
+        Ok(double_sha256(&self.body[..header_len]))
+    }
+
+    /// Decodes the number of transactions in a [`Block`](Message::Block), skipping its header.
+    pub fn tx_count(&self) -> io::Result<usize> {
+        self.expect_command(&[BLOCK_COMMAND])?;
+
+        let header_len = self.block_header_len()?;
+        self.decode_field(|bytes, options| {
+            bytes.advance(header_len);
+            VarInt::decode_with(bytes, options)
+        })
+        .map(|tx_count| *tx_count)
+    }
+
+    /// Computes the ID of a [`Tx`](Message::Tx), straight from its encoded body before V5.
+    ///
+    /// V5 transactions are decoded, as their [ZIP 244](https://zips.z.cash/zip-0244) ID commits
+    /// to digests of their fields rather than to their encoding.
+    pub fn tx_hash(&self) -> io::Result<Hash> {
+        self.expect_command(&[TX_COMMAND])?;
+
+        // The version with the overwintered flag set.
+        const V5_HEADER: [u8; 4] = (5u32 | 1 << 31).to_le_bytes();
+        if self.body.starts_with(&V5_HEADER) {
+            return self.decode_field(Tx::decode_with)?.txid();
+        }
+
+        Ok(double_sha256(self.body))
+    }
+
+    /// Returns the length of the encoded block header, excluding the transaction count.
+    fn block_header_len(&self) -> io::Result<usize> {
+        if self.body.len() < BLOCK_HEADER_PREFIX_LEN {
+            return Err(self.locate(
+                ProtocolError::unexpected_eof(BLOCK_HEADER_PREFIX_LEN, self.body.len()).into(),
+            ));
+        }
+
+        let mut bytes = &self.body[BLOCK_HEADER_PREFIX_LEN..];
+        let solution_size =
+            *VarInt::decode_with(&mut bytes, self.options).map_err(|err| self.locate(err))?;
+        if bytes.remaining() < solution_size {
+            return Err(
+                self.locate(ProtocolError::unexpected_eof(solution_size, bytes.remaining()).into())
+            );
+        }
+
+        Ok(self.body.len() - bytes.remaining() + solution_size)
+    }
+
+    fn decode_field<T>(
+        &self,
+        decode: impl FnOnce(&mut &'a [u8], DecodeOptions) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut bytes = self.body;
+        decode(&mut bytes, self.options).map_err(|err| self.locate(err))
+    }
+
+    fn locate(&self, err: io::Error) -> io::Error {
+        ProtocolError::locate(err, self.header.command, self.body.len())
+    }
+
+    fn expect_command(&self, commands: &[[u8; COMMAND_LEN]]) -> io::Result<()> {
+        if commands.contains(&self.header.command) {
+            return Ok(());
+        }
+
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "the field isn't present in {:?} messages",
+                String::from_utf8_lossy(&self.header.command).trim_end_matches('\0')
+            ),
+        ))
+    }
+}
+
+/// An owned message whose body is kept encoded until needed, see [`MessageRef`].
+///
+/// Cloning is cheap, the body is reference counted.
+#[derive(Debug, Clone)]
+pub struct LazyMessage {
+    header: MessageHeader,
+    body: Bytes,
+    options: DecodeOptions,
+}
+
+impl LazyMessage {
+    /// Creates a lazy message from its header and body, decoded permissively.
+    pub fn new(header: MessageHeader, body: Bytes) -> Self {
+        Self {
+            header,
+            body,
+            options: DecodeOptions::default(),
+        }
+    }
+
+    /// Sets the options the message and its fields are decoded with.
+    pub fn with_decode_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Encodes `message` for the given network, for APIs which expect a [`LazyMessage`].
+    pub fn from_message(network: Network, message: &Message) -> io::Result<Self> {
+        let mut buffer = BytesMut::new();
+        message.encode(network, &mut buffer)?;
+
+        let header = MessageHeader::decode(&mut buffer)?;
+        Ok(Self::new(header, buffer.freeze()))
+    }
+
+    /// Returns a view of the message, to decode its fields.
+    pub fn view(&self) -> MessageRef<'_> {
+        MessageRef::new(self.header.clone(), &self.body).with_decode_options(self.options)
+    }
+
+    /// Returns the message's header.
+    pub fn header(&self) -> &MessageHeader {
+        &self.header
+    }
+
+    /// Returns the message's command.
+    pub fn command(&self) -> [u8; COMMAND_LEN] {
+        self.header.command
+    }
+
+    /// Returns the message's encoded body.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Decodes the whole message.
+    pub fn decode(&self) -> io::Result<Message> {
+        self.view().decode()
+    }
+}
+
+fn double_sha256(bytes: &[u8]) -> Hash {
+    let hash = Sha256::digest(Sha256::digest(bytes));
+    Hash::new(hash.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::payload::{block::Block, tx::TxV5};
+
+    fn lazy(message: &Message) -> LazyMessage {
+        LazyMessage::from_message(Network::default(), message).unwrap()
+    }
+
+    #[test]
+    #[ignore]
+    fn block_fields_match_decoded_block() {
+        let block = Block::testnet_2();
+        let lazy = lazy(&Message::Block(Box::new(block.clone())));
+        let view = lazy.view();
+
+        assert_eq!(view.command(), BLOCK_COMMAND);
+        assert!(view.has_valid_checksum());
+        assert_eq!(view.block_header().unwrap(), block.header);
+        assert_eq!(view.block_hash().unwrap(), block.double_sha256().unwrap());
+        assert_eq!(view.tx_count().unwrap(), block.txs.len());
+        assert_eq!(view.decode().unwrap(), Message::Block(Box::new(block)));
+    }
+
+    #[test]
+    #[ignore]
+    fn tx_hash_matches_decoded_tx() {
+        for tx in [
+            Block::testnet_2().txs.remove(0),
+            TxV5::builder().build().unwrap().into(),
+        ] {
+            let lazy = lazy(&Message::Tx(tx.clone()));
+
+            assert_eq!(lazy.view().tx_hash().unwrap(), tx.txid().unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn from_frame_round_trip() {
+        let nonce = Nonce::default();
+        let mut buffer = BytesMut::new();
+        Message::Pong(nonce)
+            .encode(Network::default(), &mut buffer)
+            .unwrap();
+
+        let view = MessageRef::from_frame(&buffer).unwrap();
+        assert_eq!(view.nonce().unwrap(), nonce);
+
+        // Truncated frames are rejected.
+        assert!(MessageRef::from_frame(&buffer[..buffer.len() - 1]).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn field_of_other_command_is_rejected() {
+        let lazy = lazy(&Message::Ping(Nonce::default()));
+
+        let err = lazy.view().block_hash().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    #[ignore]
+    fn truncated_block_is_located() {
+        let lazy = lazy(&Message::Block(Box::new(Block::testnet_2())));
+        let view = MessageRef::new(lazy.header().clone(), &lazy.body()[..200]);
+
+        let err = view.block_hash().unwrap_err();
+        let protocol_error = ProtocolError::from_io_error(&err).unwrap();
+        assert_eq!(protocol_error.command, Some(BLOCK_COMMAND));
+        assert_eq!(protocol_error.offset, Some(BLOCK_HEADER_PREFIX_LEN + 3));
+    }
+
+    #[test]
+    #[ignore]
+    fn decode_options_apply_to_fields() {
+        let block = Block::testnet_2();
+        let message = Message::Block(Box::new(block.clone()));
+        let lazy = lazy(&message);
+
+        // Re-encode the Equihash solution size, a canonical `0xfd` `CompactSize`, in 5 bytes.
+        let (prefix, rest) = lazy.body().split_at(BLOCK_HEADER_PREFIX_LEN);
+        assert_eq!(rest[0], 0xfd);
+        let solution_size = u16::from_le_bytes([rest[1], rest[2]]) as u32;
+        let mut body = prefix.to_vec();
+        body.push(0xfe);
+        body.extend_from_slice(&solution_size.to_le_bytes());
+        body.extend_from_slice(&rest[3..]);
+
+        let view = MessageRef::new(lazy.header().clone(), &body);
+        assert_eq!(view.block_header().unwrap(), block.header);
+        assert_eq!(view.tx_count().unwrap(), block.txs.len());
+        assert_eq!(view.decode().unwrap(), message);
+
+        let view = view.with_decode_options(DecodeOptions::strict());
+        assert!(view.block_header().is_err());
+        assert!(view.block_hash().is_err());
+        assert!(view.tx_count().is_err());
+        assert!(view.decode().is_err());
+
+        let lazy = LazyMessage::new(lazy.header().clone(), body.into())
+            .with_decode_options(DecodeOptions::strict());
+        assert!(lazy.decode().is_err());
+    }
+}
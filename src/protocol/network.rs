@@ -0,0 +1,284 @@
+//! Network selection types.
+
+use std::{fmt, io, str::FromStr};
+
+use crate::protocol::message::constants::{MAGIC_LEN, MAGIC_MAINNET, MAGIC_REGTEST, MAGIC_TESTNET};
+
+/// The Zcash network a node participates in, identified on the wire by its magic bytes.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Network {
+    /// The production network.
+    Mainnet,
+    /// The public test network, which the test suite runs its nodes on.
+    #[default]
+    Testnet,
+    /// A local regression test network.
+    Regtest,
+    /// Any other network, identified only by its magic bytes.
+    Custom([u8; MAGIC_LEN]),
+}
+
+impl Network {
+    /// Returns the magic bytes which prefix every message header on this network.
+    pub fn magic(&self) -> [u8; MAGIC_LEN] {
+        match self {
+            Self::Mainnet => MAGIC_MAINNET,
+            Self::Testnet => MAGIC_TESTNET,
+            Self::Regtest => MAGIC_REGTEST,
+            Self::Custom(magic) => *magic,
+        }
+    }
+
+    /// Returns the network matching the magic bytes, falling back to [`Network::Custom`] for
+    /// unknown values.
+    pub fn from_magic(magic: [u8; MAGIC_LEN]) -> Self {
+        match magic {
+            MAGIC_MAINNET => Self::Mainnet,
+            MAGIC_TESTNET => Self::Testnet,
+            MAGIC_REGTEST => Self::Regtest,
+            magic => Self::Custom(magic),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mainnet => f.write_str("mainnet"),
+            Self::Testnet => f.write_str("testnet"),
+            Self::Regtest => f.write_str("regtest"),
+            Self::Custom(magic) => f.write_str(&hex::encode(magic)),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = io::Error;
+
+    /// Parses `mainnet`, `testnet`, `regtest` or a custom magic given as 8 hex digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mainnet" => Ok(Self::Mainnet),
+            "testnet" => Ok(Self::Testnet),
+            "regtest" => Ok(Self::Regtest),
+            other => {
+                let mut magic = [0u8; MAGIC_LEN];
+                hex::decode_to_slice(other, &mut magic).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Unknown network {s:?}, expected mainnet, testnet, regtest or {MAGIC_LEN} hex-encoded magic bytes"),
+                    )
+                })?;
+
+                Ok(Self::from_magic(magic))
+            }
+        }
+    }
+}
+
+/// A network upgrade, which changes the consensus rules from its activation height onwards
+/// ([spec](https://zips.z.cash/protocol/protocol.pdf#networks)).
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum NetworkUpgrade {
+    /// The rules in effect from the genesis block.
+    Sprout,
+    Overwinter,
+    Sapling,
+    Blossom,
+    Heartwood,
+    Canopy,
+    Nu5,
+    Nu6,
+}
+
+impl NetworkUpgrade {
+    /// Every network upgrade, in activation order.
+    pub const ALL: [Self; 8] = [
+        Self::Sprout,
+        Self::Overwinter,
+        Self::Sapling,
+        Self::Blossom,
+        Self::Heartwood,
+        Self::Canopy,
+        Self::Nu5,
+        Self::Nu6,
+    ];
+
+    /// Returns the consensus branch ID, which transactions commit to in their signatures and,
+    /// from V5 on, in their encoding.
+    pub const fn branch_id(self) -> u32 {
+        match self {
+            Self::Sprout => 0,
+            Self::Overwinter => 0x5ba8_1b19,
+            Self::Sapling => 0x76b8_09bb,
+            Self::Blossom => 0x2bb4_0e60,
+            Self::Heartwood => 0xf5b9_230b,
+            Self::Canopy => 0xe9ff_75a6,
+            Self::Nu5 => 0xc2d6_d0b4,
+            Self::Nu6 => 0xc8e7_1055,
+        }
+    }
+
+    /// Returns the upgrade with the given consensus branch ID.
+    pub fn from_branch_id(branch_id: u32) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|upgrade| upgrade.branch_id() == branch_id)
+    }
+
+    /// Returns the height the upgrade activates at on `network` by default, `None` if it never
+    /// does, see [`ActivationHeights::new`].
+    pub fn activation_height(self, network: Network) -> Option<u32> {
+        ActivationHeights::new(network).activation_height(self)
+    }
+
+    /// Returns `true` if the upgrade is active at `height` on `network` by default.
+    pub fn is_active(self, network: Network, height: u32) -> bool {
+        ActivationHeights::new(network).is_active(self, height)
+    }
+
+    /// Returns the latest upgrade active at `height` on `network` by default.
+    pub fn current(network: Network, height: u32) -> Self {
+        ActivationHeights::new(network).current(height)
+    }
+}
+
+/// The heights network upgrades activate at on a network.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ActivationHeights {
+    network: Network,
+    heights: [Option<u32>; NetworkUpgrade::ALL.len()],
+}
+
+impl ActivationHeights {
+    /// Returns the activation heights `network` nodes use by default.
+    ///
+    /// Regtest nodes only activate the upgrades configured with `-nuparams`, none by default (nor
+    /// in the nodes set up by this crate), see [`ActivationHeights::with_upgrade`]. Custom
+    /// networks aren't known to activate any.
+    pub fn new(network: Network) -> Self {
+        let heights = match network {
+            Network::Mainnet => [
+                0, 347_500, 419_200, 653_600, 903_000, 1_046_400, 1_687_104, 2_726_400,
+            ]
+            .map(Some),
+            Network::Testnet => [
+                0, 207_500, 280_000, 584_000, 903_800, 1_028_500, 1_842_420, 2_976_000,
+            ]
+            .map(Some),
+            Network::Regtest | Network::Custom(_) => {
+                let mut heights = [None; NetworkUpgrade::ALL.len()];
+                heights[NetworkUpgrade::Sprout as usize] = Some(0);
+                heights
+            }
+        };
+
+        Self { network, heights }
+    }
+
+    /// Activates `upgrade` at `height`, as `-nuparams=<branch ID>:<height>` does on a regtest
+    /// node.
+    pub fn with_upgrade(mut self, upgrade: NetworkUpgrade, height: u32) -> Self {
+        self.heights[upgrade as usize] = Some(height);
+        self
+    }
+
+    /// Returns the network the heights are for.
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Returns the height `upgrade` activates at, `None` if it never does.
+    pub fn activation_height(&self, upgrade: NetworkUpgrade) -> Option<u32> {
+        self.heights[upgrade as usize]
+    }
+
+    /// Returns `true` if `upgrade` is active at `height`.
+    pub fn is_active(&self, upgrade: NetworkUpgrade, height: u32) -> bool {
+        self.activation_height(upgrade)
+            .is_some_and(|activation_height| height >= activation_height)
+    }
+
+    /// Returns the latest upgrade active at `height`.
+    pub fn current(&self, height: u32) -> NetworkUpgrade {
+        NetworkUpgrade::ALL
+            .into_iter()
+            .rev()
+            .find(|upgrade| self.is_active(*upgrade, height))
+            .unwrap_or(NetworkUpgrade::Sprout)
+    }
+}
+
+impl From<Network> for ActivationHeights {
+    fn from(network: Network) -> Self {
+        Self::new(network)
+    }
+}
+
+impl fmt::Display for NetworkUpgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nu5 => f.write_str("NU5"),
+            Self::Nu6 => f.write_str("NU6"),
+            upgrade => fmt::Debug::fmt(upgrade, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn magic_round_trip() {
+        for network in [
+            Network::Mainnet,
+            Network::Testnet,
+            Network::Regtest,
+            Network::Custom([0xde, 0xad, 0xbe, 0xef]),
+        ] {
+            assert_eq!(Network::from_magic(network.magic()), network);
+            assert_eq!(network.to_string().parse::<Network>().unwrap(), network);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn network_upgrades_by_height() {
+        for network in [Network::Mainnet, Network::Testnet] {
+            for upgrade in NetworkUpgrade::ALL {
+                let height = upgrade.activation_height(network).unwrap();
+                assert_eq!(NetworkUpgrade::current(network, height), upgrade);
+                assert_eq!(
+                    NetworkUpgrade::from_branch_id(upgrade.branch_id()),
+                    Some(upgrade)
+                );
+
+                if upgrade != NetworkUpgrade::Sprout {
+                    assert!(NetworkUpgrade::current(network, height - 1) < upgrade);
+                }
+            }
+        }
+
+        assert_eq!(
+            NetworkUpgrade::current(Network::Testnet, 1_599_200),
+            NetworkUpgrade::Canopy
+        );
+        assert_eq!(
+            NetworkUpgrade::current(Network::Regtest, u32::MAX),
+            NetworkUpgrade::Sprout
+        );
+
+        // Regtest upgrades are only active once configured, like `-nuparams` does.
+        let heights = ActivationHeights::new(Network::Regtest)
+            .with_upgrade(NetworkUpgrade::Overwinter, 1)
+            .with_upgrade(NetworkUpgrade::Sapling, 1)
+            .with_upgrade(NetworkUpgrade::Nu5, 10);
+        assert_eq!(heights.current(0), NetworkUpgrade::Sprout);
+        assert_eq!(heights.current(1), NetworkUpgrade::Sapling);
+        assert_eq!(heights.current(9), NetworkUpgrade::Sapling);
+        assert_eq!(heights.current(10), NetworkUpgrade::Nu5);
+        assert!(!heights.is_active(NetworkUpgrade::Blossom, u32::MAX));
+    }
+}
@@ -1,18 +1,33 @@
 //! Network address types.
+//!
+//! Besides the original `addr` format, this covers the `addrv2` format of
+//! [ZIP 155](https://zips.z.cash/zip-0155), which can also carry Tor, I2P and CJDNS addresses.
 
 use std::{
     convert::TryInto,
     io,
-    net::{IpAddr::*, Ipv6Addr, SocketAddr},
+    net::{IpAddr, IpAddr::*, Ipv4Addr, Ipv6Addr, SocketAddr},
 };
 
 use bytes::{Buf, BufMut};
+use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
-use crate::protocol::payload::{codec::Codec, read_n_bytes, read_short_timestamp};
+use crate::protocol::{
+    message::constants::MAX_ADDR_TO_SEND,
+    payload::{
+        codec::{decode_bounded, Codec, DecodeOptions},
+        read_compact_size, read_n_bytes, read_short_timestamp,
+        serde_helpers::{as_hex, as_rfc3339},
+        write_compact_size, DecodeContext, ProtocolError, Services,
+    },
+};
+
+/// The maximum length of an address in an `addrv2` message.
+pub const MAX_ADDRV2_ADDR_LEN: usize = 512;
 
 /// A list of network addresses, used for peering.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Addr {
     pub addrs: Vec<NetworkAddr>,
 }
@@ -39,19 +54,22 @@ impl Codec for Addr {
         self.addrs.encode(buffer)
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        Ok(Self::new(Vec::decode(bytes)?))
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        Ok(Self::new(
+            decode_bounded(bytes, MAX_ADDR_TO_SEND, options).field("addrs")?,
+        ))
     }
 }
 
 /// A network address.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NetworkAddr {
     /// The last time this address was seen.
     /// Note: Present only when version is >= 31402
+    #[serde(with = "as_rfc3339")]
     pub last_seen: Option<OffsetDateTime>,
     /// The services supported by this address.
-    pub services: u64,
+    pub services: Services,
     /// The socket address.
     pub addr: SocketAddr,
 }
@@ -59,17 +77,17 @@ pub struct NetworkAddr {
 impl NetworkAddr {
     /// Creates a new NetworkAddr with the given socket address,
     /// `last_seen=OffsetDateTime::now_utc()`,
-    /// and `services=1` (only `NODE_NETWORK` is enabled).
+    /// and `services=NODE_NETWORK`.
     pub fn new(addr: SocketAddr) -> Self {
         Self {
             last_seen: Some(OffsetDateTime::now_utc()),
-            services: 1,
+            services: Services::NODE_NETWORK,
             addr,
         }
     }
 
     pub fn encode_without_timestamp<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
-        buffer.put_u64_le(self.services);
+        self.services.encode(buffer)?;
 
         let (ip, port) = match self.addr {
             SocketAddr::V4(v4) => (v4.ip().to_ipv6_mapped(), v4.port()),
@@ -82,11 +100,14 @@ impl NetworkAddr {
         Ok(())
     }
 
-    pub(super) fn decode_without_timestamp<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let services = u64::from_le_bytes(read_n_bytes(bytes)?);
+    pub(super) fn decode_without_timestamp<B: Buf>(
+        bytes: &mut B,
+        options: DecodeOptions,
+    ) -> io::Result<Self> {
+        let services = Services::decode_with(bytes, options).field("services")?;
 
         if bytes.remaining() < 16 {
-            return Err(io::ErrorKind::InvalidData.into());
+            return Err(ProtocolError::unexpected_eof(16, bytes.remaining()).into()).field("ip");
         }
 
         let mut octets = [0u8; 16];
@@ -98,7 +119,7 @@ impl NetworkAddr {
             None => V6(v6_addr),
         };
 
-        let port = u16::from_be_bytes(read_n_bytes(bytes)?);
+        let port = u16::from_be_bytes(read_n_bytes(bytes).field("port")?);
 
         Ok(Self {
             last_seen: None,
@@ -123,9 +144,9 @@ impl Codec for NetworkAddr {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let timestamp = read_short_timestamp(bytes)?;
-        let without_timestamp = Self::decode_without_timestamp(bytes)?;
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let timestamp = read_short_timestamp(bytes).field("last_seen")?;
+        let without_timestamp = Self::decode_without_timestamp(bytes, options)?;
 
         Ok(Self {
             last_seen: Some(timestamp),
@@ -133,3 +154,305 @@ impl Codec for NetworkAddr {
         })
     }
 }
+
+/// A list of network addresses in the `addrv2` format of ZIP 155, only sent to peers which
+/// signalled support with `sendaddrv2`.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct AddrV2 {
+    pub addrs: Vec<NetworkAddrV2>,
+}
+
+impl AddrV2 {
+    /// Returns an `AddrV2` with no addresses.
+    pub fn empty() -> Self {
+        Self { addrs: Vec::new() }
+    }
+
+    /// Returns an `AddrV2` with the given addresses.
+    pub fn new(addrs: Vec<NetworkAddrV2>) -> Self {
+        Self { addrs }
+    }
+
+    /// Returns an iterator over the list of network addresses.
+    pub fn iter(&self) -> std::slice::Iter<NetworkAddrV2> {
+        self.addrs.iter()
+    }
+}
+
+impl Codec for AddrV2 {
+    fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
+        self.addrs.encode(buffer)
+    }
+
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        Ok(Self::new(
+            decode_bounded(bytes, MAX_ADDR_TO_SEND, options).field("addrs")?,
+        ))
+    }
+}
+
+/// A network address in the `addrv2` format.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkAddrV2 {
+    /// The last time this address was seen.
+    #[serde(with = "as_rfc3339")]
+    pub last_seen: OffsetDateTime,
+    /// The services supported by this address.
+    pub services: Services,
+    /// The host address, tagged with its network.
+    pub addr: HostAddr,
+    /// The port, `0` for networks without ports.
+    pub port: u16,
+}
+
+impl NetworkAddrV2 {
+    /// Creates a new NetworkAddrV2 with the given socket address,
+    /// `last_seen=OffsetDateTime::now_utc()`,
+    /// and `services=NODE_NETWORK`.
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            last_seen: OffsetDateTime::now_utc(),
+            services: Services::NODE_NETWORK,
+            addr: addr.ip().into(),
+            port: addr.port(),
+        }
+    }
+
+    /// Returns the socket address, if the host is an IP address.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        self.addr.ip().map(|ip| SocketAddr::new(ip, self.port))
+    }
+
+    /// Converts the address to the original `addr` format, if the host is an IP address.
+    pub fn to_network_addr(&self) -> Option<NetworkAddr> {
+        Some(NetworkAddr {
+            last_seen: Some(self.last_seen),
+            services: self.services,
+            addr: self.socket_addr()?,
+        })
+    }
+}
+
+impl Codec for NetworkAddrV2 {
+    fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
+        let timestamp: u32 = self.last_seen.unix_timestamp().try_into().unwrap();
+        buffer.put_u32_le(timestamp);
+        write_compact_size(buffer, self.services.bits());
+        self.addr.encode(buffer)?;
+        buffer.put_u16(self.port);
+
+        Ok(())
+    }
+
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let last_seen = read_short_timestamp(bytes).field("last_seen")?;
+        let services = Services::from_bits(read_compact_size(bytes, options).field("services")?);
+        let addr = HostAddr::decode_with(bytes, options).field("addr")?;
+        let port = u16::from_be_bytes(read_n_bytes(bytes).field("port")?);
+
+        Ok(Self {
+            last_seen,
+            services,
+            addr,
+            port,
+        })
+    }
+}
+
+/// A host address tagged with the network it belongs to, as listed in ZIP 155.
+///
+/// Addresses of known networks must have the network's address length, addresses of unknown
+/// networks are kept as-is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostAddr {
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    /// A Tor v2 onion service, deprecated by Tor and not relayed by `zcashd`.
+    TorV2(#[serde(with = "as_hex")] [u8; 10]),
+    /// A Tor v3 onion service's public key.
+    TorV3(#[serde(with = "as_hex")] [u8; 32]),
+    /// An I2P destination's hash.
+    I2p(#[serde(with = "as_hex")] [u8; 32]),
+    /// A CJDNS address, within `fc00::/8`.
+    Cjdns(Ipv6Addr),
+    Unknown {
+        network_id: u8,
+        #[serde(with = "as_hex")]
+        bytes: Vec<u8>,
+    },
+}
+
+impl HostAddr {
+    /// Returns the network ID the address is tagged with.
+    pub fn network_id(&self) -> u8 {
+        match self {
+            Self::Ipv4(_) => 0x01,
+            Self::Ipv6(_) => 0x02,
+            Self::TorV2(_) => 0x03,
+            Self::TorV3(_) => 0x04,
+            Self::I2p(_) => 0x05,
+            Self::Cjdns(_) => 0x06,
+            Self::Unknown { network_id, .. } => *network_id,
+        }
+    }
+
+    /// Returns the address bytes, as encoded after the network ID and length.
+    pub fn bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Ipv4(ip) => ip.octets().to_vec(),
+            Self::Ipv6(ip) | Self::Cjdns(ip) => ip.octets().to_vec(),
+            Self::TorV2(bytes) => bytes.to_vec(),
+            Self::TorV3(bytes) | Self::I2p(bytes) => bytes.to_vec(),
+            Self::Unknown { bytes, .. } => bytes.clone(),
+        }
+    }
+
+    /// Returns the IP address, `None` for networks which don't use IP.
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self {
+            Self::Ipv4(ip) => Some(V4(*ip)),
+            Self::Ipv6(ip) => Some(V6(*ip)),
+            _ => None,
+        }
+    }
+}
+
+impl From<IpAddr> for HostAddr {
+    fn from(ip: IpAddr) -> Self {
+        match ip {
+            V4(ip) => Self::Ipv4(ip),
+            V6(ip) => Self::Ipv6(ip),
+        }
+    }
+}
+
+impl Codec for HostAddr {
+    fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
+        let bytes = self.bytes();
+
+        buffer.put_u8(self.network_id());
+        write_compact_size(buffer, bytes.len() as u64);
+        buffer.put_slice(&bytes);
+
+        Ok(())
+    }
+
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let network_id = u8::from_le_bytes(read_n_bytes(bytes).field("network_id")?);
+
+        let remaining = bytes.remaining();
+        let len = read_compact_size(bytes, options).field("len")?;
+        if len > MAX_ADDRV2_ADDR_LEN as u64 {
+            return Err(ProtocolError::too_large(MAX_ADDRV2_ADDR_LEN, len, remaining).into())
+                .field("len");
+        }
+
+        let expected_len = match network_id {
+            0x01 => Some(4),
+            0x02 | 0x06 => Some(16),
+            0x03 => Some(10),
+            0x04 | 0x05 => Some(32),
+            _ => None,
+        };
+        if let Some(expected_len) = expected_len {
+            if len != expected_len {
+                return Err(ProtocolError::invalid_value(
+                    format!("an address of {expected_len} bytes"),
+                    len,
+                    remaining,
+                )
+                .into())
+                .field("len");
+            }
+        }
+
+        let len = len as usize;
+        if bytes.remaining() < len {
+            return Err(ProtocolError::unexpected_eof(len, bytes.remaining()).into())
+                .field("bytes");
+        }
+        let mut addr = vec![0u8; len];
+        bytes.copy_to_slice(&mut addr);
+
+        let host = match network_id {
+            0x01 => Self::Ipv4(Ipv4Addr::from(<[u8; 4]>::try_from(addr).unwrap())),
+            0x02 => Self::Ipv6(Ipv6Addr::from(<[u8; 16]>::try_from(addr).unwrap())),
+            0x03 => Self::TorV2(addr.try_into().unwrap()),
+            0x04 => Self::TorV3(addr.try_into().unwrap()),
+            0x05 => Self::I2p(addr.try_into().unwrap()),
+            0x06 => Self::Cjdns(Ipv6Addr::from(<[u8; 16]>::try_from(addr).unwrap())),
+            network_id => Self::Unknown {
+                network_id,
+                bytes: addr,
+            },
+        };
+
+        Ok(host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::protocol::payload::error::ProtocolErrorKind;
+
+    fn decode_host(bytes: &[u8]) -> io::Result<HostAddr> {
+        HostAddr::decode(&mut &bytes[..])
+    }
+
+    #[test]
+    #[ignore]
+    fn host_addr_lengths_are_checked() {
+        assert_eq!(
+            decode_host(&[0x01, 0x04, 10, 0, 0, 1]).unwrap(),
+            HostAddr::Ipv4(Ipv4Addr::new(10, 0, 0, 1))
+        );
+        assert_eq!(
+            decode_host(&[0x07, 0x01, 0xff]).unwrap(),
+            HostAddr::Unknown {
+                network_id: 0x07,
+                bytes: vec![0xff]
+            }
+        );
+
+        // An IPv4 address must be 4 bytes long.
+        let err = decode_host(&[0x01, 0x05, 10, 0, 0, 1, 0]).unwrap_err();
+        assert_matches!(
+            ProtocolError::from_io_error(&err).unwrap().kind,
+            ProtocolErrorKind::InvalidValue { .. }
+        );
+
+        // Unknown networks are still bounded.
+        let mut bytes = vec![0x07, 0xfd];
+        bytes.extend_from_slice(&513u16.to_le_bytes());
+        bytes.resize(bytes.len() + 513, 0);
+        let err = decode_host(&bytes).unwrap_err();
+        assert_eq!(
+            ProtocolError::from_io_error(&err).unwrap().kind,
+            ProtocolErrorKind::TooLarge {
+                max: MAX_ADDRV2_ADDR_LEN,
+                actual: 513
+            }
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn addr_v2_converts_to_addr() {
+        let addr = NetworkAddrV2::new("[2001:db8::1]:8233".parse().unwrap());
+        assert_eq!(addr.addr.network_id(), 0x02);
+        assert_eq!(
+            addr.to_network_addr().unwrap().addr,
+            addr.socket_addr().unwrap()
+        );
+
+        let onion = NetworkAddrV2 {
+            addr: HostAddr::I2p([0; 32]),
+            ..addr
+        };
+        assert_eq!(onion.to_network_addr(), None);
+    }
+}
@@ -3,16 +3,25 @@
 use std::{convert::TryInto, io};
 
 use bytes::{Buf, BufMut};
+use serde::{Deserialize, Serialize};
 use sha2::Digest;
 
-use crate::protocol::payload::{
-    codec::Codec,
-    inv::{InvHash, ObjectKind},
-    read_n_bytes, Hash, ProtocolVersion, Tx, VarInt,
+use crate::protocol::{
+    message::constants::MAX_HEADERS_RESULTS,
+    network::{ActivationHeights, Network},
+    payload::{
+        codec::{decode_bounded, Codec, DecodeOptions},
+        equihash::EquihashParams,
+        inv::{InvHash, ObjectKind},
+        read_n_bytes,
+        script::{self, Instruction},
+        serde_helpers::{as_hex, as_rfc3339},
+        DecodeContext, Hash, ProtocolError, ProtocolVersion, Tx, VarInt,
+    },
 };
 
 /// The locator hash object, used to communicate chain state.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct LocatorHashes {
     /// The protocol version.
     pub version: ProtocolVersion,
@@ -50,10 +59,11 @@ impl Codec for LocatorHashes {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let version = ProtocolVersion::decode(bytes)?;
-        let block_locator_hashes = Vec::decode(bytes)?;
-        let hash_stop = Hash::decode(bytes)?;
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let version = ProtocolVersion::decode_with(bytes, options).field("version")?;
+        let block_locator_hashes =
+            Vec::decode_with(bytes, options).field("block_locator_hashes")?;
+        let hash_stop = Hash::decode_with(bytes, options).field("hash_stop")?;
 
         Ok(Self {
             version,
@@ -64,7 +74,7 @@ impl Codec for LocatorHashes {
 }
 
 /// A block, composed of its header and transactions.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Block {
     /// The block's header.
     pub header: Header,
@@ -161,10 +171,140 @@ impl Block {
         ]
     }
 
+    /// Creates the regtest genesis block.
+    ///
+    /// It shares its coinbase transaction with the testnet genesis block, only the header fields
+    /// affecting proof-of-work differ.
+    pub fn regtest_genesis() -> Self {
+        let mut block = Self::testnet_genesis();
+
+        let mut nonce = [0; 32];
+        nonce[0] = 9;
+        let solution =
+            hex::decode("01936b7db1eb4ac39f151b8704642d0a8bda13ec547d54cd5e43ba142fc6d8877cab07b3")
+                .unwrap();
+
+        block.header.timestamp = 1_296_688_602;
+        block.header.bits = 0x200f_0f0f;
+        block.header.nonce = nonce;
+        block.header.solution_size = VarInt::new(solution.len());
+        block.header.solution = solution;
+
+        block
+    }
+
     /// Convenience function which creates the [`InvHash`] for this block.
     pub fn inv_hash(&self) -> InvHash {
         InvHash::new(ObjectKind::Block, self.double_sha256().unwrap())
     }
+
+    /// Computes the merkle root of the block's transaction IDs.
+    ///
+    /// Each level of the tree pairs up hashes and double Sha256 hashes their concatenation, the
+    /// last hash is paired with itself if a level has an odd number of them. The IDs of V5
+    /// transactions are their [ZIP 244](https://zips.z.cash/zip-0244) digests, see [`Tx::txid`].
+    ///
+    /// Errors with [`io::ErrorKind::InvalidData`] if the block has no transactions, as it has no
+    /// merkle root without a coinbase.
+    pub fn compute_merkle_root(&self) -> io::Result<Hash> {
+        let mut level = self
+            .txs
+            .iter()
+            .map(|tx| tx.txid().map(|txid| txid.0))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        if level.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the block has no transactions to compute a merkle root from",
+            ));
+        }
+
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| {
+                    let mut bytes = [0u8; 64];
+                    bytes[..32].copy_from_slice(&pair[0]);
+                    bytes[32..].copy_from_slice(pair.last().unwrap());
+
+                    sha2::Sha256::digest(sha2::Sha256::digest(bytes)).into()
+                })
+                .collect();
+        }
+
+        Ok(Hash::new(level[0]))
+    }
+
+    /// Checks the merkle root in the header matches the block's transactions, see
+    /// [`Block::compute_merkle_root`].
+    pub fn validate_merkle_root(&self) -> io::Result<()> {
+        let merkle_root = self.compute_merkle_root()?;
+
+        if merkle_root != self.header.merkle_root {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "merkle root mismatch: header has {}, transactions hash to {merkle_root}",
+                    self.header.merkle_root
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks every transaction is valid under the network upgrade active at the block's height,
+    /// see [`Tx::validate_network_upgrade`]. Heights are read from the coinbase, blocks without a
+    /// parent are taken to be genesis blocks.
+    pub fn validate_network_upgrade(
+        &self,
+        heights: impl Into<ActivationHeights>,
+    ) -> io::Result<()> {
+        let heights = heights.into();
+        let height = if self.header.prev_block == Hash::zeroed() {
+            0
+        } else {
+            self.coinbase_height()?
+        };
+
+        for tx in &self.txs {
+            tx.validate_network_upgrade(heights, height)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the height encoded at the start of the coinbase script, as required by
+    /// [BIP 34](https://github.com/bitcoin/bips/blob/master/bip-0034.mediawiki).
+    ///
+    /// Genesis blocks predate the rule, the number their script starts with isn't their height.
+    pub fn coinbase_height(&self) -> io::Result<u32> {
+        let script = self
+            .txs
+            .first()
+            .and_then(|tx| tx.tx_in().first())
+            .map(|input| input.script())
+            .ok_or_else(|| invalid_coinbase("the block has no coinbase input"))?;
+
+        match script::instructions(script).next() {
+            Some(Ok(Instruction::Push(bytes))) if bytes.len() <= 4 => {
+                // Script numbers are little-endian, with the top bit as the sign.
+                if bytes.last().is_some_and(|byte| byte & 0x80 != 0) {
+                    return Err(invalid_coinbase("the height is negative"));
+                }
+
+                Ok(bytes
+                    .iter()
+                    .rev()
+                    .fold(0, |height, byte| height << 8 | u32::from(*byte)))
+            }
+            Some(Ok(Instruction::Op(opcode @ script::OP_1..=script::OP_16))) => {
+                Ok(u32::from(opcode - script::OP_1) + 1)
+            }
+            _ => Err(invalid_coinbase("the script doesn't start with a height")),
+        }
+    }
 }
 
 impl Codec for Block {
@@ -173,15 +313,22 @@ impl Codec for Block {
         self.txs.encode(buffer)
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let header = Header::decode_without_tx_count(bytes)?;
-        let txs = Vec::decode(bytes)?;
-        Ok(Self { header, txs })
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let header = Header::decode_without_tx_count(bytes, options).field("header")?;
+        // The transactions are validated at the block's height, rather than on their own.
+        let txs = Vec::decode_with(bytes, options.without_activation_heights()).field("txs")?;
+        let block = Self { header, txs };
+
+        if let Some(heights) = options.activation_heights() {
+            block.validate_network_upgrade(heights)?;
+        }
+
+        Ok(block)
     }
 }
 
 /// A list of block headers.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Headers {
     pub headers: Vec<Header>,
 }
@@ -205,15 +352,18 @@ impl Codec for Headers {
         self.headers.encode(buffer)
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let headers = Vec::decode(bytes)?;
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let headers = decode_bounded(bytes, MAX_HEADERS_RESULTS, options).field("headers")?;
         Ok(Self::new(headers))
     }
 }
 
+/// The size of the largest Equihash solution, that of the mainnet and testnet parameters.
+const MAX_SOLUTION_SIZE: usize = 1344;
+
 /// A block header, see the [Zcash protocol
 /// spec](https://zips.z.cash/protocol/protocol.pdf#blockheader) for details.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Header {
     /// The block version number.
     pub version: ProtocolVersion,
@@ -224,16 +374,20 @@ pub struct Header {
     /// Field usage varies depending on version, see spec.
     pub light_client_root: Hash,
     /// The block timestamp.
+    #[serde(with = "as_rfc3339")]
     pub timestamp: u32,
     /// An encoded version of the target threshold.
     pub bits: u32,
     /// The nonce used in the version messages, `Nonce(u64)`, is NOT the same as the nonce the
     /// block was generated with as it uses a `u32`.
+    #[serde(with = "as_hex")]
     pub nonce: [u8; 32],
-    /// The size of the Equihash solution in bytes (always `1344`).
+    /// The size of the Equihash solution in bytes (`1344` on mainnet and testnet, `36` on
+    /// regtest).
     pub solution_size: VarInt,
     /// The Equihash solution.
-    pub solution: [u8; 1344],
+    #[serde(with = "as_hex")]
+    pub solution: Vec<u8>,
 }
 
 impl Codec for Header {
@@ -243,19 +397,18 @@ impl Codec for Header {
         VarInt(0).encode(buffer)
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self>
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self>
     where
         Self: Sized,
     {
-        let result = Self::decode_without_tx_count(bytes);
+        let result = Self::decode_without_tx_count(bytes, options);
 
         // tx_count must be zero
-        let tx_count = *VarInt::decode(bytes)?;
+        let remaining = bytes.remaining();
+        let tx_count = *VarInt::decode_with(bytes, options).field("tx_count")?;
         if tx_count != 0 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Message::Header.tx_count = {tx_count}, expected 0"),
-            ));
+            return Err(ProtocolError::invalid_value(0, tx_count, remaining).into())
+                .field("tx_count");
         }
 
         result
@@ -277,9 +430,40 @@ impl Header {
         Ok(hash)
     }
 
-    /// Encodes [Header] without the VarInt `tx_count=0`. This is useful for [Block] encoding which requires
-    /// `tx_count=N`, as well as Hash calculation as it excludes `tx_count`.
-    fn encode_without_tx_count<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
+    /// Returns the proof-of-work target encoded in `bits`.
+    pub fn target(&self) -> io::Result<Target> {
+        Target::from_bits(self.bits)
+    }
+
+    /// Checks the Equihash solution is valid for the header.
+    pub fn verify_solution(&self, params: EquihashParams) -> io::Result<()> {
+        let mut input = Vec::new();
+        self.encode_equihash_input(&mut input)?;
+
+        params.verify(&input, &self.solution)
+    }
+
+    /// Checks the header carries valid proof-of-work for the network: a valid Equihash solution
+    /// and a hash under the target encoded in `bits`.
+    ///
+    /// The target itself isn't checked against the network's difficulty adjustment.
+    pub fn validate_pow(&self, network: Network) -> io::Result<()> {
+        self.verify_solution(EquihashParams::for_network(network))?;
+
+        let hash = self.double_sha256()?;
+        if !self.target()?.is_met_by(&hash) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("block hash {hash} is above the target {:#010x}", self.bits),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the fields of the header which the Equihash solution commits to, all of them up to
+    /// and including the nonce.
+    pub(crate) fn encode_equihash_input<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         self.version.encode(buffer)?;
         self.prev_block.encode(buffer)?;
         self.merkle_root.encode(buffer)?;
@@ -289,6 +473,14 @@ impl Header {
         buffer.put_u32_le(self.bits);
         buffer.put_slice(&self.nonce);
 
+        Ok(())
+    }
+
+    /// Encodes [Header] without the VarInt `tx_count=0`. This is useful for [Block] encoding which requires
+    /// `tx_count=N`, as well as Hash calculation as it excludes `tx_count`.
+    fn encode_without_tx_count<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
+        self.encode_equihash_input(buffer)?;
+
         self.solution_size.encode(buffer)?;
         buffer.put_slice(&self.solution);
 
@@ -299,19 +491,36 @@ impl Header {
     /// requires the value to determine the number of transactions which follow in the body. [Header] on the
     /// otherhand requires that this value be 0. This gets asserted in Header::encode, making it unsuiteable
     /// for use by [Block].
-    fn decode_without_tx_count<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let version = ProtocolVersion::decode(bytes)?;
-        let prev_block = Hash::decode(bytes)?;
-        let merkle_root = Hash::decode(bytes)?;
-        let light_client_root = Hash::decode(bytes)?;
-
-        let timestamp = u32::from_le_bytes(read_n_bytes(bytes)?);
-
-        let bits = u32::from_le_bytes(read_n_bytes(bytes)?);
-        let nonce = read_n_bytes(bytes)?;
-
-        let solution_size = VarInt::decode(bytes)?;
-        let solution = read_n_bytes(bytes)?;
+    pub(crate) fn decode_without_tx_count<B: Buf>(
+        bytes: &mut B,
+        options: DecodeOptions,
+    ) -> io::Result<Self> {
+        let version = ProtocolVersion::decode_with(bytes, options).field("version")?;
+        let prev_block = Hash::decode_with(bytes, options).field("prev_block")?;
+        let merkle_root = Hash::decode_with(bytes, options).field("merkle_root")?;
+        let light_client_root = Hash::decode_with(bytes, options).field("light_client_root")?;
+
+        let timestamp = u32::from_le_bytes(read_n_bytes(bytes).field("timestamp")?);
+
+        let bits = u32::from_le_bytes(read_n_bytes(bytes).field("bits")?);
+        let nonce = read_n_bytes(bytes).field("nonce")?;
+
+        let remaining = bytes.remaining();
+        let solution_size = VarInt::decode_with(bytes, options).field("solution_size")?;
+        if *solution_size > MAX_SOLUTION_SIZE {
+            return Err(ProtocolError::too_large(
+                MAX_SOLUTION_SIZE,
+                *solution_size as u64,
+                remaining,
+            )
+            .into())
+            .field("solution_size");
+        }
+        if bytes.remaining() < *solution_size {
+            return Err(ProtocolError::unexpected_eof(*solution_size, bytes.remaining()).into())
+                .field("solution");
+        }
+        let solution = bytes.copy_to_bytes(*solution_size).to_vec();
 
         Ok(Self {
             version,
@@ -327,6 +536,80 @@ impl Header {
     }
 }
 
+/// A proof-of-work target, the largest block hash which meets it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Target([u8; 32]);
+
+impl Target {
+    /// Decodes the target from the compact representation used in [`Header::bits`], a 3 byte
+    /// mantissa and a 1 byte base-256 exponent.
+    ///
+    /// Negative and overflowing targets are rejected.
+    pub fn from_bits(bits: u32) -> io::Result<Self> {
+        let exponent = (bits >> 24) as usize;
+        let mantissa = bits & 0x007f_ffff;
+
+        let invalid = |reason| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid compact target {bits:#010x}: {reason}"),
+            )
+        };
+
+        if mantissa != 0 && bits & 0x0080_0000 != 0 {
+            return Err(invalid("negative"));
+        }
+
+        // The mantissa's bytes, least significant first, land at `exponent - 3` onwards.
+        let mut target = [0u8; 32];
+        for (i, byte) in mantissa.to_le_bytes()[..3].iter().enumerate() {
+            match (exponent + i).checked_sub(3) {
+                Some(position) if position < 32 => target[position] = *byte,
+                Some(_) if *byte != 0 => return Err(invalid("overflows 256 bits")),
+                _ => {}
+            }
+        }
+
+        Ok(Self(target))
+    }
+
+    /// Encodes the target in the compact representation used in [`Header::bits`], truncating it
+    /// to its 3 most significant bytes.
+    pub fn to_bits(&self) -> u32 {
+        let Some(top) = self.0.iter().rposition(|byte| *byte != 0) else {
+            return 0;
+        };
+
+        let mut exponent = top + 1;
+        let mut mantissa = (0..3).fold(0u32, |mantissa, i| {
+            let byte = (top + i)
+                .checked_sub(2)
+                .map_or(0, |position| self.0[position]);
+            mantissa | u32::from(byte) << (8 * i)
+        });
+
+        // The mantissa's top bit is its sign.
+        if mantissa & 0x0080_0000 != 0 {
+            mantissa >>= 8;
+            exponent += 1;
+        }
+
+        (exponent as u32) << 24 | mantissa
+    }
+
+    /// Returns `true` if the hash, read as a little-endian 256-bit number, is at most the target.
+    pub fn is_met_by(&self, hash: &Hash) -> bool {
+        hash.0.iter().rev().cmp(self.0.iter().rev()) != std::cmp::Ordering::Greater
+    }
+}
+
+fn invalid_coinbase(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid coinbase height: {message}"),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
@@ -654,4 +937,194 @@ mod tests {
 
         assert_eq!(expected, hash);
     }
+
+    #[test]
+    #[ignore]
+    fn vector_blocks_have_valid_merkle_roots() {
+        for bytes in [
+            &BLOCK_TESTNET_GENESIS_BYTES[..],
+            &BLOCK_TESTNET_0_000_001_BYTES[..],
+            // Three transactions, the last hash is duplicated.
+            &BLOCK_TESTNET_0_207_500_BYTES[..],
+            &BLOCK_TESTNET_0_280_000_BYTES[..],
+            &BLOCK_TESTNET_0_584_000_BYTES[..],
+            &BLOCK_TESTNET_0_903_800_BYTES[..],
+            &BLOCK_TESTNET_1_028_500_BYTES[..],
+            &BLOCK_TESTNET_1_599_199_BYTES[..],
+            // V5 transactions.
+            &BLOCK_TESTNET_1_599_200_BYTES[..],
+            &BLOCK_TESTNET_1_599_201_BYTES[..],
+        ] {
+            let block = Block::decode(&mut Cursor::new(bytes)).unwrap();
+            block.validate_merkle_root().unwrap();
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn invalid_merkle_root_is_detected() {
+        let mut block =
+            Block::decode(&mut Cursor::new(&BLOCK_TESTNET_0_207_500_BYTES[..])).unwrap();
+        let merkle_root = block.header.merkle_root;
+
+        // Dropping a transaction changes the root.
+        block.txs.pop();
+        assert!(block.validate_merkle_root().is_err());
+
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        block.validate_merkle_root().unwrap();
+        assert_ne!(block.header.merkle_root, merkle_root);
+
+        // Without a coinbase there's no root to compute.
+        block.txs.clear();
+        assert_eq!(
+            block.compute_merkle_root().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert!(block.validate_merkle_root().is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn vector_headers_have_valid_pow() {
+        for block in Block::initial_testnet_blocks() {
+            block.header.validate_pow(Network::Testnet).unwrap();
+        }
+
+        let header = Block::decode(&mut Cursor::new(&BLOCK_TESTNET_1_599_201_BYTES[..]))
+            .unwrap()
+            .header;
+        header.validate_pow(Network::Testnet).unwrap();
+
+        // The regtest parameters expect a shorter solution.
+        assert!(header.verify_solution(EquihashParams::REGTEST).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn vector_blocks_match_network_upgrades() {
+        for block in Block::initial_testnet_blocks() {
+            block.validate_network_upgrade(Network::Testnet).unwrap();
+        }
+
+        // The first blocks of each upgrade.
+        for bytes in [
+            &BLOCK_TESTNET_0_207_500_BYTES[..],
+            &BLOCK_TESTNET_0_280_000_BYTES[..],
+            &BLOCK_TESTNET_0_584_000_BYTES[..],
+            &BLOCK_TESTNET_0_903_800_BYTES[..],
+            &BLOCK_TESTNET_1_028_500_BYTES[..],
+        ] {
+            let block = Block::decode(&mut Cursor::new(bytes)).unwrap();
+            block.validate_network_upgrade(Network::Testnet).unwrap();
+
+            // A block earlier in the chain can't hold the same transactions.
+            let mut earlier = block.clone();
+            earlier.header.prev_block = Hash::zeroed();
+            assert!(earlier.validate_network_upgrade(Network::Testnet).is_err());
+        }
+
+        // NU5 first activated at this height, the testnet has since been rolled back and
+        // activated it later.
+        let block = Block::decode(&mut Cursor::new(&BLOCK_TESTNET_1_599_200_BYTES[..])).unwrap();
+        assert!(block.validate_network_upgrade(Network::Testnet).is_err());
+
+        // Decoding for a network checks the same.
+        let options = DecodeOptions::default().with_network(Network::Testnet);
+        let bytes = &BLOCK_TESTNET_1_599_200_BYTES[..];
+        let err = Block::decode_with(&mut Cursor::new(bytes), options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let bytes = &BLOCK_TESTNET_1_028_500_BYTES[..];
+        Block::decode_with(&mut Cursor::new(bytes), options).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn invalid_pow_is_detected() {
+        let header = Block::testnet_1().header;
+
+        let mut tampered = header.clone();
+        tampered.solution[100] ^= 1;
+        assert!(tampered.validate_pow(Network::Testnet).is_err());
+
+        // The solution commits to the nonce.
+        let mut tampered = header.clone();
+        tampered.nonce[0] ^= 1;
+        assert!(tampered.verify_solution(EquihashParams::MAINNET).is_err());
+
+        // Swapping the halves of the solution breaks the index ordering.
+        let params = EquihashParams::MAINNET;
+        let mut indices = params.indices_from_solution(&header.solution);
+        indices.rotate_left(params.index_count() / 2);
+        let mut tampered = header;
+        tampered.solution = params.solution_from_indices(&indices);
+        assert!(tampered.verify_solution(params).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn compact_targets() {
+        // The testnet proof-of-work limit, 0x07ffff followed by 29 zero bytes.
+        let target = Target::from_bits(0x2007_ffff).unwrap();
+        let mut expected = [0u8; 32];
+        expected[29..].copy_from_slice(&[0xff, 0xff, 0x07]);
+        assert_eq!(target, Target(expected));
+        assert_eq!(target.to_bits(), 0x2007_ffff);
+
+        assert!(target.is_met_by(&Hash::new([0; 32])));
+        assert!(target.is_met_by(&Hash::new(expected)));
+        expected[0] = 1;
+        assert!(!target.is_met_by(&Hash::new(expected)));
+
+        for bits in [0x200f_0f0f, 0x1d00_ffff, 0x0312_3456, 0x0101_0000, 0] {
+            assert_eq!(Target::from_bits(bits).unwrap().to_bits(), bits);
+        }
+        // Small exponents shift bytes out of the mantissa.
+        assert_eq!(
+            Target::from_bits(0x0212_3456).unwrap().to_bits(),
+            0x0212_3400
+        );
+
+        // Negative and overflowing targets.
+        assert!(Target::from_bits(0x0480_0001).is_err());
+        assert!(Target::from_bits(0x2101_0000).is_err());
+        assert!(Target::from_bits(0x2300_0001).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn coinbase_heights() {
+        for (height, block) in Block::initial_testnet_blocks()
+            .into_iter()
+            .enumerate()
+            .skip(1)
+        {
+            assert_eq!(block.coinbase_height().unwrap(), height as u32);
+        }
+
+        for (bytes, height) in [
+            (&BLOCK_TESTNET_0_207_500_BYTES[..], 207_500),
+            (&BLOCK_TESTNET_1_599_200_BYTES[..], 1_599_200),
+        ] {
+            let block = Block::decode(&mut Cursor::new(bytes)).unwrap();
+            assert_eq!(block.coinbase_height().unwrap(), height);
+        }
+
+        let mut block = Block::testnet_1();
+        block.txs.clear();
+        assert!(block.coinbase_height().is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn regtest_genesis_block_hash() {
+        let genesis = Block::regtest_genesis();
+        genesis.header.validate_pow(Network::Regtest).unwrap();
+        genesis.validate_merkle_root().unwrap();
+
+        assert_eq!(
+            genesis.double_sha256().unwrap().to_string(),
+            "029f11d80ef9765602235e1bc9727e3eb6ba20839319f761fee920d63401e327"
+        );
+    }
 }
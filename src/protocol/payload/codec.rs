@@ -4,15 +4,97 @@ use std::io;
 
 use bytes::{Buf, BufMut};
 
-use super::VarInt;
+use super::{DecodeContext, ProtocolError, VarInt};
+use crate::protocol::network::{ActivationHeights, Network};
+
+/// Options threaded through decoding.
+///
+/// Decoding is permissive and context-free by default, so that messages from misbehaving peers
+/// can be inspected.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    strict: bool,
+    activation_heights: Option<ActivationHeights>,
+}
+
+impl DecodeOptions {
+    /// Strict decoding, where non-canonical `CompactSize` encodings are rejected and counts and
+    /// lengths are held to their per-field maximums (e.g.
+    /// [`MAX_INV_SZ`](crate::protocol::message::constants::MAX_INV_SZ)), as `zcashd` does.
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if decoding strictly, see [`DecodeOptions::strict`].
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Decodes for `network`, with its default [`ActivationHeights`], see
+    /// [`DecodeOptions::with_activation_heights`].
+    pub fn with_network(self, network: Network) -> Self {
+        self.with_activation_heights(network.into())
+    }
+
+    /// Checks transactions are valid under the network upgrade active at their height: a
+    /// block's transactions at the height in its coinbase, a standalone transaction at the height
+    /// its version and consensus branch ID claim (see
+    /// [`Tx::validate_network_upgrade`](super::Tx::validate_network_upgrade)).
+    pub fn with_activation_heights(mut self, activation_heights: ActivationHeights) -> Self {
+        self.activation_heights = Some(activation_heights);
+        self
+    }
+
+    /// Returns the activation heights transactions are checked against, if any, see
+    /// [`DecodeOptions::with_activation_heights`].
+    pub fn activation_heights(&self) -> Option<ActivationHeights> {
+        self.activation_heights
+    }
+
+    /// Returns these options without activation heights, for payloads validated by their
+    /// container.
+    pub(super) fn without_activation_heights(mut self) -> Self {
+        self.activation_heights = None;
+        self
+    }
+}
+
+/// Decodes a list of at most `max` elements, the maximum is only enforced when decoding strictly.
+pub(super) fn decode_bounded<T: Codec, B: Buf>(
+    bytes: &mut B,
+    max: usize,
+    options: DecodeOptions,
+) -> io::Result<Vec<T>> {
+    let remaining = bytes.remaining();
+    let length = *VarInt::decode_with(bytes, options)?;
+
+    if options.is_strict() && length > max {
+        return Err(ProtocolError::too_large(max, length as u64, remaining).into());
+    }
+
+    (0..length)
+        .map(|i| T::decode_with(bytes, options).index(i))
+        .collect()
+}
 
 /// A trait for unifying encoding and decoding.
 pub trait Codec {
     /// Encodes the payload into the supplied buffer.
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()>;
 
-    /// Decodes the bytes and returns the payload.
+    /// Decodes the bytes and returns the payload, permissively (see [`DecodeOptions`]).
     fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::decode_with(bytes, DecodeOptions::default())
+    }
+
+    /// Decodes the bytes with the given options and returns the payload.
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self>
     where
         Self: Sized;
 }
@@ -27,11 +109,10 @@ impl<T: Codec> Codec for Vec<T> {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self>
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self>
     where
         Self: Sized,
     {
-        let length = *VarInt::decode(bytes)?;
-        (0..length).map(|_| T::decode(bytes)).collect()
+        decode_bounded(bytes, usize::MAX, options)
     }
 }
@@ -0,0 +1,284 @@
+//! Equihash proof-of-work verification ([spec](https://zips.z.cash/protocol/protocol.pdf#equihash)).
+
+use std::io;
+
+use crate::protocol::network::Network;
+
+/// The parameters of an Equihash instance.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EquihashParams {
+    /// The bit length of the hashes.
+    pub n: u32,
+    /// The number of collision rounds, a solution has `2^k` indices.
+    pub k: u32,
+}
+
+impl EquihashParams {
+    /// The parameters used on mainnet and testnet.
+    pub const MAINNET: Self = Self { n: 200, k: 9 };
+    /// The parameters used on regtest.
+    pub const REGTEST: Self = Self { n: 48, k: 5 };
+
+    /// Returns the parameters used on the network.
+    pub fn for_network(network: Network) -> Self {
+        match network {
+            Network::Regtest => Self::REGTEST,
+            _ => Self::MAINNET,
+        }
+    }
+
+    /// Returns the known parameters whose solutions are `len` bytes long.
+    pub fn from_solution_len(len: usize) -> Option<Self> {
+        [Self::MAINNET, Self::REGTEST]
+            .into_iter()
+            .find(|params| params.solution_len() == len)
+    }
+
+    /// Returns the byte length of an encoded solution.
+    pub fn solution_len(&self) -> usize {
+        (1 << self.k) * self.index_bits() / 8
+    }
+
+    /// Returns the number of indices in a solution.
+    pub fn index_count(&self) -> usize {
+        1 << self.k
+    }
+
+    /// Returns the number of bits which must collide in each round.
+    pub(crate) fn collision_bits(&self) -> usize {
+        (self.n / (self.k + 1)) as usize
+    }
+
+    /// Returns the bit length of an encoded index.
+    fn index_bits(&self) -> usize {
+        self.collision_bits() + 1
+    }
+
+    /// Returns the number of `n`-bit hashes taken from each BLAKE2b output.
+    fn hashes_per_output(&self) -> usize {
+        (512 / self.n) as usize
+    }
+
+    /// Returns a BLAKE2b state personalized for these parameters and fed with `input`.
+    pub(crate) fn hasher(&self, input: &[u8]) -> blake2b_simd::State {
+        let mut personalization = [0u8; 16];
+        personalization[..8].copy_from_slice(b"ZcashPoW");
+        personalization[8..12].copy_from_slice(&self.n.to_le_bytes());
+        personalization[12..].copy_from_slice(&self.k.to_le_bytes());
+
+        let mut state = blake2b_simd::Params::new()
+            .hash_length(self.hashes_per_output() * self.n as usize / 8)
+            .personal(&personalization)
+            .to_state();
+        state.update(input);
+
+        state
+    }
+
+    /// Returns the `n`-bit hash of the index, from a `hasher` fed with the input.
+    pub(crate) fn hash_index(&self, hasher: &blake2b_simd::State, index: u32) -> Vec<u8> {
+        let hash_len = self.n as usize / 8;
+        let per_output = self.hashes_per_output() as u32;
+
+        let mut state = hasher.clone();
+        state.update(&(index / per_output).to_le_bytes());
+        let output = state.finalize();
+
+        let start = (index % per_output) as usize * hash_len;
+        output.as_bytes()[start..start + hash_len].to_vec()
+    }
+
+    /// Unpacks the big-endian, bit-packed indices of an encoded solution.
+    pub fn indices_from_solution(&self, solution: &[u8]) -> Vec<u32> {
+        let index_bits = self.index_bits();
+
+        (0..solution.len() * 8 / index_bits)
+            .map(|i| {
+                (0..index_bits).fold(0u32, |index, bit| {
+                    let position = i * index_bits + bit;
+                    let set = solution[position / 8] >> (7 - position % 8) & 1;
+                    index << 1 | u32::from(set)
+                })
+            })
+            .collect()
+    }
+
+    /// Packs indices into an encoded solution, the inverse of
+    /// [`indices_from_solution`](Self::indices_from_solution).
+    pub fn solution_from_indices(&self, indices: &[u32]) -> Vec<u8> {
+        let index_bits = self.index_bits();
+        let mut solution = vec![0u8; indices.len() * index_bits / 8];
+
+        for (i, index) in indices.iter().enumerate() {
+            for bit in 0..index_bits {
+                if index >> (index_bits - 1 - bit) & 1 == 1 {
+                    let position = i * index_bits + bit;
+                    solution[position / 8] |= 1 << (7 - position % 8);
+                }
+            }
+        }
+
+        solution
+    }
+
+    /// Checks `solution` is a valid Equihash solution for `input`, the encoded block header up to
+    /// and including its nonce.
+    pub fn verify(&self, input: &[u8], solution: &[u8]) -> io::Result<()> {
+        if solution.len() != self.solution_len() {
+            return Err(invalid(format!(
+                "expected a {} byte solution for Equihash ({}, {}), got {} bytes",
+                self.solution_len(),
+                self.n,
+                self.k,
+                solution.len()
+            )));
+        }
+
+        let hasher = self.hasher(input);
+        let mut nodes: Vec<(Vec<u8>, Vec<u32>)> = self
+            .indices_from_solution(solution)
+            .into_iter()
+            .map(|index| (self.hash_index(&hasher, index), vec![index]))
+            .collect();
+
+        for round in 1..=self.k as usize {
+            nodes = nodes
+                .chunks(2)
+                .map(|pair| {
+                    let (left, right) = (&pair[0], &pair[1]);
+
+                    if left.1[0] >= right.1[0] {
+                        return Err(invalid(format!("indices out of order in round {round}")));
+                    }
+                    if left.1.iter().any(|index| right.1.contains(index)) {
+                        return Err(invalid(format!("duplicate indices in round {round}")));
+                    }
+
+                    let hash: Vec<u8> = left.0.iter().zip(&right.0).map(|(l, r)| l ^ r).collect();
+                    if !has_leading_zeros(&hash, round * self.collision_bits()) {
+                        return Err(invalid(format!("no collision in round {round}")));
+                    }
+
+                    Ok((hash, [&left.1[..], &right.1[..]].concat()))
+                })
+                .collect::<io::Result<_>>()?;
+        }
+
+        if nodes[0].0.iter().any(|byte| *byte != 0) {
+            return Err(invalid("the hashes don't XOR to zero".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Finds the valid solutions for `input` with Wagner's algorithm, only practical for small
+    /// parameters such as [`EquihashParams::REGTEST`].
+    pub fn solve(&self, input: &[u8]) -> Vec<Vec<u8>> {
+        let collision_bits = self.collision_bits();
+        let hasher = self.hasher(input);
+
+        let mut nodes: Vec<(Vec<u8>, Vec<u32>)> = (0..1u32 << (collision_bits + 1))
+            .map(|index| (self.hash_index(&hasher, index), vec![index]))
+            .collect();
+
+        for round in 1..=self.k as usize {
+            // The last round needs the remaining two collision lengths to match.
+            let start = (round - 1) * collision_bits;
+            let len = if round == self.k as usize {
+                2 * collision_bits
+            } else {
+                collision_bits
+            };
+
+            nodes.sort_by_key(|(hash, _)| bit_window(hash, start, len));
+
+            let mut merged = Vec::new();
+            for group in
+                nodes.chunk_by(|a, b| bit_window(&a.0, start, len) == bit_window(&b.0, start, len))
+            {
+                for (i, left) in group.iter().enumerate() {
+                    for right in &group[i + 1..] {
+                        if left.1.iter().any(|index| right.1.contains(index)) {
+                            continue;
+                        }
+
+                        let (left, right) = if left.1[0] < right.1[0] {
+                            (left, right)
+                        } else {
+                            (right, left)
+                        };
+                        let hash = left.0.iter().zip(&right.0).map(|(l, r)| l ^ r).collect();
+                        merged.push((hash, [&left.1[..], &right.1[..]].concat()));
+                    }
+                }
+            }
+
+            nodes = merged;
+        }
+
+        nodes
+            .into_iter()
+            .map(|(_, indices)| self.solution_from_indices(&indices))
+            .filter(|solution| self.verify(input, solution).is_ok())
+            .collect()
+    }
+}
+
+/// Returns the `len` bits of `hash` starting at bit `start`, as a big-endian number.
+fn bit_window(hash: &[u8], start: usize, len: usize) -> u64 {
+    (start..start + len).fold(0, |window, position| {
+        window << 1 | u64::from(hash[position / 8] >> (7 - position % 8) & 1)
+    })
+}
+
+/// Returns `true` if the first `bits` bits of `hash` are zero.
+pub(crate) fn has_leading_zeros(hash: &[u8], bits: usize) -> bool {
+    let (bytes, rest) = (bits / 8, bits % 8);
+
+    hash[..bytes].iter().all(|byte| *byte == 0) && (rest == 0 || hash[bytes] >> (8 - rest) == 0)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid Equihash solution: {message}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn solution_packing_round_trip() {
+        for params in [EquihashParams::MAINNET, EquihashParams::REGTEST] {
+            let max = (1u32 << (params.collision_bits() + 1)) - 1;
+            let indices: Vec<u32> = (0..params.index_count() as u32)
+                .map(|i| i.wrapping_mul(2_654_435_761) & max)
+                .collect();
+
+            let solution = params.solution_from_indices(&indices);
+            assert_eq!(solution.len(), params.solution_len());
+            assert_eq!(params.indices_from_solution(&solution), indices);
+            assert_eq!(
+                EquihashParams::from_solution_len(solution.len()),
+                Some(params)
+            );
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn regtest_solutions_verify() {
+        let params = EquihashParams::REGTEST;
+        let solutions: Vec<_> = (0u8..4)
+            .flat_map(|nonce| params.solve(&[nonce; 140]))
+            .collect();
+
+        assert!(!solutions.is_empty());
+        for solution in solutions {
+            assert_eq!(solution.len(), params.solution_len());
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Structured errors for payload decoding.
+
+use std::{fmt, io};
+
+use crate::protocol::message::constants::COMMAND_LEN;
+
+/// A segment of the path to the field which failed to decode.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FieldSegment {
+    /// A named field of a struct.
+    Field(&'static str),
+    /// An element of a list.
+    Index(usize),
+}
+
+/// What went wrong decoding a field.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ProtocolErrorKind {
+    /// The payload ended before the field could be read.
+    UnexpectedEof {
+        /// The number of bytes the field needs.
+        expected: usize,
+        /// The number of bytes left in the payload.
+        actual: usize,
+    },
+    /// A length or count exceeds the protocol's maximum.
+    TooLarge {
+        /// The maximum the protocol allows.
+        max: usize,
+        /// The decoded value.
+        actual: u64,
+    },
+    /// The field holds a value the protocol doesn't allow.
+    InvalidValue {
+        /// A description of the allowed values.
+        expected: String,
+        /// The decoded value.
+        actual: String,
+    },
+}
+
+impl fmt::Display for ProtocolErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof { expected, actual } => {
+                write!(f, "expected {expected} more bytes, got {actual}")
+            }
+            Self::TooLarge { max, actual } => write!(f, "{actual} exceeds the maximum of {max}"),
+            Self::InvalidValue { expected, actual } => {
+                write!(f, "expected {expected}, got {actual}")
+            }
+        }
+    }
+}
+
+/// An [`Error`](std::error::Error) type describing where and why a payload failed to decode.
+///
+/// [`Codec::decode`](super::codec::Codec::decode) surfaces it wrapped in an [`io::Error`] of kind
+/// [`io::ErrorKind::InvalidData`], use [`ProtocolError::from_io_error`] to get it back. The field
+/// path is filled in as the error propagates up through the payload's decoders, the command and
+/// byte offset once it reaches [`Message::decode`](crate::protocol::message::Message::decode).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ProtocolError {
+    /// The command of the message being decoded, if known.
+    pub command: Option<[u8; COMMAND_LEN]>,
+    /// The path to the field which failed to decode, outermost first.
+    pub path: Vec<FieldSegment>,
+    /// The offset of the field from the start of the message body, if known.
+    pub offset: Option<usize>,
+    /// What went wrong.
+    pub kind: ProtocolErrorKind,
+    // The number of bytes left in the payload at the start of the field, used to compute the
+    // offset.
+    remaining: usize,
+}
+
+impl ProtocolError {
+    /// Creates an error for a field which needs `expected` bytes, where `remaining` is the number
+    /// of bytes left at the start of the field.
+    pub fn unexpected_eof(expected: usize, remaining: usize) -> Self {
+        Self::new(
+            ProtocolErrorKind::UnexpectedEof {
+                expected,
+                actual: remaining,
+            },
+            remaining,
+        )
+    }
+
+    /// Creates an error for a length or count field exceeding `max`, where `remaining` is the
+    /// number of bytes left at the start of the field.
+    pub fn too_large(max: usize, actual: u64, remaining: usize) -> Self {
+        Self::new(ProtocolErrorKind::TooLarge { max, actual }, remaining)
+    }
+
+    /// Creates an error for a field holding a disallowed value, where `remaining` is the number of
+    /// bytes left at the start of the field.
+    pub fn invalid_value(
+        expected: impl fmt::Display,
+        actual: impl fmt::Display,
+        remaining: usize,
+    ) -> Self {
+        Self::new(
+            ProtocolErrorKind::InvalidValue {
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            },
+            remaining,
+        )
+    }
+
+    fn new(kind: ProtocolErrorKind, remaining: usize) -> Self {
+        Self {
+            command: None,
+            path: Vec::new(),
+            offset: None,
+            kind,
+            remaining,
+        }
+    }
+
+    /// Returns the [`ProtocolError`] wrapped by `err`, if any.
+    pub fn from_io_error(err: &io::Error) -> Option<&Self> {
+        err.get_ref().and_then(|inner| inner.downcast_ref())
+    }
+
+    /// Applies `f` to the [`ProtocolError`] wrapped by `err`, if any.
+    fn map_io_error(mut err: io::Error, f: impl FnOnce(&mut Self)) -> io::Error {
+        if let Some(protocol_error) = err.get_mut().and_then(|inner| inner.downcast_mut::<Self>()) {
+            f(protocol_error);
+        }
+
+        err
+    }
+
+    /// Records the message `command` and the offset of the failed field within a body of
+    /// `body_length` bytes on the [`ProtocolError`] wrapped by `err`, if any.
+    pub(crate) fn locate(
+        err: io::Error,
+        command: [u8; COMMAND_LEN],
+        body_length: usize,
+    ) -> io::Error {
+        Self::map_io_error(err, |protocol_error| {
+            protocol_error.command = Some(command);
+            protocol_error.offset = Some(body_length.saturating_sub(protocol_error.remaining));
+        })
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to decode")?;
+
+        if let Some(command) = self.command {
+            let command = String::from_utf8_lossy(&command);
+            write!(f, " {:?} payload", command.trim_end_matches('\0'))?;
+        }
+
+        if !self.path.is_empty() {
+            f.write_str(" at ")?;
+            for (i, segment) in self.path.iter().enumerate() {
+                match segment {
+                    FieldSegment::Field(name) if i == 0 => f.write_str(name)?,
+                    FieldSegment::Field(name) => write!(f, ".{name}")?,
+                    FieldSegment::Index(index) => write!(f, "[{index}]")?,
+                }
+            }
+        }
+
+        if let Some(offset) = self.offset {
+            write!(f, " (byte {offset})")?;
+        }
+
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<ProtocolError> for io::Error {
+    fn from(original: ProtocolError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, original)
+    }
+}
+
+/// Prepends the location of a field to the [`ProtocolError`] of a failed decode, if it has one.
+pub trait DecodeContext<T> {
+    /// Marks the error as coming from the struct field `name`.
+    fn field(self, name: &'static str) -> io::Result<T>;
+
+    /// Marks the error as coming from the list element at `index`.
+    fn index(self, index: usize) -> io::Result<T>;
+}
+
+impl<T> DecodeContext<T> for io::Result<T> {
+    fn field(self, name: &'static str) -> io::Result<T> {
+        self.map_err(|err| {
+            ProtocolError::map_io_error(err, |protocol_error| {
+                protocol_error.path.insert(0, FieldSegment::Field(name))
+            })
+        })
+    }
+
+    fn index(self, index: usize) -> io::Result<T> {
+        self.map_err(|err| {
+            ProtocolError::map_io_error(err, |protocol_error| {
+                protocol_error.path.insert(0, FieldSegment::Index(index))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::message::constants::TX_COMMAND;
+
+    #[test]
+    #[ignore]
+    fn context_builds_path_and_offset() {
+        let result: io::Result<()> = Err(ProtocolError::unexpected_eof(4, 2).into());
+        let err = ProtocolError::locate(
+            result.field("script").index(1).field("tx_in").unwrap_err(),
+            TX_COMMAND,
+            10,
+        );
+
+        let protocol_error = ProtocolError::from_io_error(&err).unwrap();
+        assert_eq!(
+            protocol_error.path,
+            vec![
+                FieldSegment::Field("tx_in"),
+                FieldSegment::Index(1),
+                FieldSegment::Field("script")
+            ]
+        );
+        assert_eq!(protocol_error.offset, Some(8));
+        assert_eq!(
+            protocol_error.to_string(),
+            "failed to decode \"tx\" payload at tx_in[1].script (byte 8): expected 4 more bytes, got 2"
+        );
+    }
+}
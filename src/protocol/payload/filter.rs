@@ -1,22 +1,30 @@
 //! Bloom filtering types, see [BIP 37](https://github.com/bitcoin/bips/blob/master/bip-0037.mediawiki).
 
-use std::io::{self, Cursor, ErrorKind, Read};
+use std::io::{self, Cursor, Read};
 
 use bytes::{Buf, BufMut};
+use serde::{Deserialize, Serialize};
 
-use crate::protocol::payload::{codec::Codec, read_n_bytes};
+use crate::protocol::payload::{
+    codec::{Codec, DecodeOptions},
+    read_n_bytes,
+    serde_helpers::as_hex,
+    DecodeContext, ProtocolError,
+};
 
 /// A modification to an existing filter.
-#[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[derive(Debug, PartialEq, Eq, Default, Clone, Serialize, Deserialize)]
 pub struct FilterAdd {
     /// The data element to add to the current filter.
+    #[serde(with = "as_hex")]
     pub data: Vec<u8>,
 }
 
 /// A new filter on the connection.
-#[derive(Debug, PartialEq, Eq, Default, Clone)]
+#[derive(Debug, PartialEq, Eq, Default, Clone, Serialize, Deserialize)]
 pub struct FilterLoad {
     /// The filter itself.
+    #[serde(with = "as_hex")]
     pub filter: Vec<u8>,
     /// The number of hash functions to use in this filter.
     pub hash_fn_count: u32,
@@ -33,23 +41,19 @@ impl Codec for FilterAdd {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self>
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self>
     where
         Self: Sized,
     {
+        let remaining = bytes.remaining();
+        if remaining > 520 {
+            return Err(ProtocolError::too_large(520, remaining as u64, remaining).into())
+                .field("data");
+        }
+
         let mut data = Vec::new();
         bytes.reader().read_to_end(&mut data)?;
 
-        if data.len() > 520 {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!(
-                    "Maximum FilterAdd data length is 520, but got {}",
-                    data.len()
-                ),
-            ));
-        }
-
         Ok(Self { data })
     }
 }
@@ -64,7 +68,7 @@ impl Codec for FilterLoad {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self>
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self>
     where
         Self: Sized,
     {
@@ -75,21 +79,19 @@ impl Codec for FilterLoad {
 
         const NON_FILTER_BYTES: usize = 4 + 4 + 1;
         if bytes_read < NON_FILTER_BYTES {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!(
-                    "Minimum FilterLoad bytes required is {NON_FILTER_BYTES} but only got {bytes_read}"
-                ),
-            ));
+            return Err(ProtocolError::unexpected_eof(NON_FILTER_BYTES, bytes_read).into());
         }
         let filter_bytes = bytes_read - NON_FILTER_BYTES;
         // maximum filter size is 36k bytes
         const MAX_FILTER_BYTES: usize = 36_000;
         if filter_bytes > MAX_FILTER_BYTES {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!("Maximum filter bytes is {MAX_FILTER_BYTES} but got {filter_bytes}"),
-            ));
+            return Err(ProtocolError::too_large(
+                MAX_FILTER_BYTES,
+                filter_bytes as u64,
+                bytes_read,
+            )
+            .into())
+            .field("filter");
         }
 
         let mut cursor = Cursor::new(&buffer[..]);
@@ -97,9 +99,9 @@ impl Codec for FilterLoad {
         let mut filter = vec![0; filter_bytes];
         cursor.read_exact(&mut filter)?;
 
-        let hash_fn_count = u32::from_le_bytes(read_n_bytes(&mut cursor)?);
-        let tweak = u32::from_le_bytes(read_n_bytes(&mut cursor)?);
-        let flags = u8::from_le_bytes(read_n_bytes(&mut cursor)?);
+        let hash_fn_count = u32::from_le_bytes(read_n_bytes(&mut cursor).field("hash_fn_count")?);
+        let tweak = u32::from_le_bytes(read_n_bytes(&mut cursor).field("tweak")?);
+        let flags = u8::from_le_bytes(read_n_bytes(&mut cursor).field("flags")?);
 
         Ok(Self {
             filter,
@@ -139,4 +141,79 @@ mod tests {
         let decoded = FilterAdd::decode(&mut cursor).unwrap();
         assert_eq!(decoded, original);
     }
+
+    #[test]
+    #[ignore]
+    fn filter_load_max_size_is_accepted() {
+        let original = FilterLoad {
+            filter: vec![0xff; 36_000],
+            hash_fn_count: 50,
+            tweak: 1,
+            flags: 2,
+        };
+
+        let mut buffer = Vec::new();
+        original.encode(&mut buffer).unwrap();
+
+        let mut cursor = Cursor::new(&buffer[..]);
+        let decoded = FilterLoad::decode(&mut cursor).unwrap();
+        assert_eq!(decoded, original);
+    }
+
+    #[test]
+    #[ignore]
+    fn filter_load_oversized_filter_is_rejected() {
+        let original = FilterLoad {
+            filter: vec![0xff; 36_001],
+            ..Default::default()
+        };
+
+        let mut buffer = Vec::new();
+        original.encode(&mut buffer).unwrap();
+
+        let mut cursor = Cursor::new(&buffer[..]);
+        let err = FilterLoad::decode(&mut cursor).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[ignore]
+    fn filter_load_truncated_is_rejected() {
+        // Missing the trailing `flags` byte.
+        let buffer = [0u8; 8];
+
+        let mut cursor = Cursor::new(&buffer[..]);
+        let err = FilterLoad::decode(&mut cursor).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[ignore]
+    fn filter_add_max_size_is_accepted() {
+        let original = FilterAdd {
+            data: vec![0xff; 520],
+        };
+
+        let mut buffer = Vec::new();
+        original.encode(&mut buffer).unwrap();
+
+        let mut cursor = Cursor::new(&buffer[..]);
+        let decoded = FilterAdd::decode(&mut cursor).unwrap();
+        assert_eq!(decoded, original);
+    }
+
+    #[test]
+    #[ignore]
+    fn filter_add_oversized_data_is_rejected() {
+        let original = FilterAdd {
+            data: vec![0xff; 521],
+        };
+
+        let mut buffer = Vec::new();
+        original.encode(&mut buffer).unwrap();
+
+        let mut cursor = Cursor::new(&buffer[..]);
+        let err = FilterAdd::decode(&mut cursor).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
 }
@@ -3,11 +3,18 @@
 use std::io;
 
 use bytes::{Buf, BufMut};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
 
-use crate::protocol::payload::{codec::Codec, read_n_bytes, Hash};
+use crate::protocol::{
+    message::constants::MAX_INV_SZ,
+    payload::{
+        codec::{decode_bounded, Codec, DecodeOptions},
+        read_n_bytes, DecodeContext, Hash, ProtocolError,
+    },
+};
 
 /// An inventory vector.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Inv {
     pub inventory: Vec<InvHash>,
 }
@@ -29,26 +36,93 @@ impl Codec for Inv {
         self.inventory.encode(buffer)
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
         Ok(Self {
-            inventory: Vec::decode(bytes)?,
+            inventory: decode_bounded(bytes, MAX_INV_SZ, options).field("inventory")?,
         })
     }
 }
 
 /// An inventory hash.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
 pub struct InvHash {
     /// The object type linked to this inventory.
     kind: ObjectKind,
     /// The hash of the object.
     hash: Hash,
+    /// The authorizing data commitment, only present for [`ObjectKind::WTx`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth_digest: Option<Hash>,
+}
+
+/// Requires the auth digest of [`ObjectKind::WTx`] entries and rejects it for other kinds, which
+/// would otherwise encode to a malformed inventory.
+impl<'de> Deserialize<'de> for InvHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Fields {
+            kind: ObjectKind,
+            hash: Hash,
+            #[serde(default)]
+            auth_digest: Option<Hash>,
+        }
+
+        let Fields {
+            kind,
+            hash,
+            auth_digest,
+        } = Fields::deserialize(deserializer)?;
+
+        match (kind, auth_digest) {
+            (ObjectKind::WTx, Some(auth_digest)) => Ok(Self::wtx(hash, auth_digest)),
+            (ObjectKind::WTx, None) => Err(D::Error::missing_field("auth_digest")),
+            (kind, None) => Ok(Self::new(kind, hash)),
+            (kind, Some(_)) => Err(D::Error::custom(format!(
+                "unexpected auth_digest for a {kind:?} entry"
+            ))),
+        }
+    }
 }
 
 impl InvHash {
     /// Returns a new `InvHash` instance.
+    ///
+    /// The auth digest of a [`ObjectKind::WTx`] defaults to all `0xff`, use [`InvHash::wtx`] to
+    /// set it.
     pub fn new(kind: ObjectKind, hash: Hash) -> Self {
-        Self { kind, hash }
+        match kind {
+            ObjectKind::WTx => Self::wtx(hash, Hash::new([0xff; 32])),
+            _ => Self {
+                kind,
+                hash,
+                auth_digest: None,
+            },
+        }
+    }
+
+    /// Returns a new [`ObjectKind::WTx`] `InvHash` for the transaction with the given ID and
+    /// auth digest ([ZIP 239](https://zips.z.cash/zip-0239)).
+    pub fn wtx(txid: Hash, auth_digest: Hash) -> Self {
+        Self {
+            kind: ObjectKind::WTx,
+            hash: txid,
+            auth_digest: Some(auth_digest),
+        }
+    }
+
+    /// Returns the object kind.
+    pub fn kind(&self) -> ObjectKind {
+        self.kind
+    }
+
+    /// Returns the hash of the object, the txid for [`ObjectKind::WTx`].
+    pub fn hash(&self) -> Hash {
+        self.hash
+    }
+
+    /// Returns the auth digest of a [`ObjectKind::WTx`].
+    pub fn auth_digest(&self) -> Option<Hash> {
+        self.auth_digest
     }
 }
 
@@ -57,19 +131,32 @@ impl Codec for InvHash {
         self.kind.encode(buffer)?;
         self.hash.encode(buffer)?;
 
+        if let Some(auth_digest) = &self.auth_digest {
+            auth_digest.encode(buffer)?;
+        }
+
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let kind = ObjectKind::decode(bytes)?;
-        let hash = Hash::decode(bytes)?;
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let kind = ObjectKind::decode_with(bytes, options).field("kind")?;
+        let hash = Hash::decode_with(bytes, options).field("hash")?;
+
+        let auth_digest = match kind {
+            ObjectKind::WTx => Some(Hash::decode_with(bytes, options).field("auth_digest")?),
+            _ => None,
+        };
 
-        Ok(Self { kind, hash })
+        Ok(Self {
+            kind,
+            hash,
+            auth_digest,
+        })
     }
 }
 
 /// The inventory object kind.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum ObjectKind {
     /// Any data of this kind may be ignored.
     Error,
@@ -79,6 +166,8 @@ pub enum ObjectKind {
     Block,
     /// The hash is that of a block header.
     FilteredBlock,
+    /// The hash is the ID of a transaction, followed by its auth digest.
+    WTx,
 }
 
 impl Codec for ObjectKind {
@@ -88,6 +177,7 @@ impl Codec for ObjectKind {
             Self::Tx => 1,
             Self::Block => 2,
             Self::FilteredBlock => 3,
+            Self::WTx => 5,
         };
 
         buffer.put_u32_le(value);
@@ -95,7 +185,8 @@ impl Codec for ObjectKind {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
+        let remaining = bytes.remaining();
         let value = u32::from_le_bytes(read_n_bytes(bytes)?);
 
         let kind = match value {
@@ -103,14 +194,76 @@ impl Codec for ObjectKind {
             1 => Self::Tx,
             2 => Self::Block,
             3 => Self::FilteredBlock,
+            5 => Self::WTx,
             _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "ObjectKind is not known",
-                ))
+                return Err(ProtocolError::invalid_value(
+                    "a known ObjectKind (0..=3 or 5)",
+                    value,
+                    remaining,
+                )
+                .into())
             }
         };
 
         Ok(kind)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn wtx_inv_hash_round_trip() {
+        let inv = Inv::new(vec![
+            InvHash::new(ObjectKind::Tx, Hash::new([1; 32])),
+            InvHash::wtx(Hash::new([2; 32]), Hash::new([3; 32])),
+            InvHash::new(ObjectKind::Block, Hash::new([4; 32])),
+        ]);
+
+        let mut buffer = Vec::new();
+        inv.encode(&mut buffer).unwrap();
+        // The count, two 36 byte entries and a 68 byte WTX entry.
+        assert_eq!(buffer.len(), 1 + 36 * 2 + 68);
+        assert_eq!(buffer[1 + 36..1 + 40], 5u32.to_le_bytes());
+
+        assert_eq!(Inv::decode(&mut Cursor::new(&buffer)).unwrap(), inv);
+    }
+
+    #[test]
+    #[ignore]
+    fn auth_digest_is_deserialized_for_wtx_only() {
+        let hash = Hash::new([1; 32]);
+        for inv_hash in [
+            InvHash::new(ObjectKind::Tx, hash),
+            InvHash::wtx(hash, Hash::new([2; 32])),
+        ] {
+            let json = serde_json::to_value(inv_hash).unwrap();
+            assert_eq!(serde_json::from_value::<InvHash>(json).unwrap(), inv_hash);
+        }
+
+        let hash = serde_json::to_value(hash).unwrap();
+        for json in [
+            serde_json::json!({ "kind": "WTx", "hash": hash }),
+            serde_json::json!({ "kind": "Tx", "hash": hash, "auth_digest": hash }),
+            serde_json::json!({ "kind": "Block", "hash": hash, "auth_digest": hash }),
+        ] {
+            assert!(serde_json::from_value::<InvHash>(json).is_err());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn truncated_wtx_inv_hash_is_rejected() {
+        let mut buffer = Vec::new();
+        InvHash::wtx(Hash::new([2; 32]), Hash::new([3; 32]))
+            .encode(&mut buffer)
+            .unwrap();
+        buffer.truncate(36);
+
+        assert!(InvHash::decode(&mut Cursor::new(&buffer)).is_err());
+    }
+}
@@ -1,40 +1,63 @@
 //! Network message payload types.
 
-use std::io;
+use std::{fmt, io};
 
 use bytes::{Buf, BufMut};
 use rand::{thread_rng, Rng};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use time::OffsetDateTime;
 
 pub mod addr;
-pub use addr::Addr;
+pub use addr::{Addr, AddrV2};
 
 pub mod block;
 
+pub mod equihash;
+
 pub mod inv;
 pub use inv::Inv;
 
 pub mod tx;
 pub use tx::Tx;
 
+pub mod script;
+
+pub mod sighash;
+
 pub mod version;
 pub use version::Version;
 
 pub mod reject;
 pub use reject::Reject;
 
-use self::codec::Codec;
+pub mod services;
+pub use services::Services;
+
+use self::codec::{Codec, DecodeOptions};
 use crate::protocol::message::constants::{MAX_MESSAGE_LEN, PROTOCOL_VERSION};
 
 pub mod codec;
 
+pub mod error;
+pub use error::{DecodeContext, ProtocolError};
+
 pub mod filter;
 pub use filter::{FilterAdd, FilterLoad};
 
+pub(crate) mod serde_helpers;
+use serde_helpers::HexRepr;
+
 /// A `u64`-backed nonce.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
 pub struct Nonce(u64);
 
+impl Nonce {
+    /// Creates a nonce with the given value.
+    pub fn new(value: u64) -> Self {
+        Self(value)
+    }
+}
+
 impl Default for Nonce {
     fn default() -> Self {
         Self(thread_rng().gen())
@@ -48,9 +71,9 @@ impl Codec for Nonce {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
         if bytes.remaining() < 8 {
-            return Err(io::ErrorKind::InvalidData.into());
+            return Err(ProtocolError::unexpected_eof(8, bytes.remaining()).into());
         }
         let nonce = bytes.get_u64_le();
 
@@ -59,7 +82,7 @@ impl Codec for Nonce {
 }
 
 /// Specifies the protocol version.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct ProtocolVersion(pub u32);
 
 impl ProtocolVersion {
@@ -76,7 +99,7 @@ impl Codec for ProtocolVersion {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
         let version = u32::from_le_bytes(read_n_bytes(bytes)?);
 
         Ok(Self(version))
@@ -84,7 +107,7 @@ impl Codec for ProtocolVersion {
 }
 
 /// A variable length integer.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct VarInt(usize);
 
 impl VarInt {
@@ -103,44 +126,17 @@ impl std::ops::Deref for VarInt {
 
 impl Codec for VarInt {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
-        // length of the payload to be written.
-        let l = self.0;
-        match l {
-            0x0000_0000..=0x0000_00fc => {
-                buffer.put_u8(l as u8);
-            }
-            0x0000_00fd..=0x0000_ffff => {
-                buffer.put_u8(0xfdu8);
-                buffer.put_u16_le(l as u16);
-            }
-            0x0001_0000..=0xffff_ffff => {
-                buffer.put_u8(0xfeu8);
-                buffer.put_u32_le(l as u32);
-            }
-            _ => {
-                buffer.put_u8(0xffu8);
-                buffer.put_u64_le(l as u64);
-            }
-        };
+        write_compact_size(buffer, self.0 as u64);
 
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let flag = u8::from_le_bytes(read_n_bytes(bytes)?);
-
-        let len = match flag {
-            len @ 0x00..=0xfc => len as u64,
-            0xfd => u16::from_le_bytes(read_n_bytes(bytes)?) as u64,
-            0xfe => u32::from_le_bytes(read_n_bytes(bytes)?) as u64,
-            0xff => u64::from_le_bytes(read_n_bytes(bytes)?),
-        };
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let remaining = bytes.remaining();
+        let len = read_compact_size(bytes, options)?;
 
         if len > MAX_MESSAGE_LEN as u64 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("VarInt length of {len} exceeds max message length of {MAX_MESSAGE_LEN}"),
-            ));
+            return Err(ProtocolError::too_large(MAX_MESSAGE_LEN, len, remaining).into());
         }
 
         Ok(VarInt(len as usize))
@@ -148,7 +144,7 @@ impl Codec for VarInt {
 }
 
 /// A variable length string.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct VarStr(pub String);
 
 impl VarStr {
@@ -159,34 +155,44 @@ impl VarStr {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let str_len = VarInt::decode(bytes)?;
-
-        if *str_len > MAX_MESSAGE_LEN {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "VarStr length of {} exceeds max message length of {}",
-                    *str_len, MAX_MESSAGE_LEN
-                ),
-            ));
+    /// Decodes a string of at most `max` bytes, the maximum is only enforced when decoding
+    /// strictly (see [`DecodeOptions::strict`]).
+    fn decode_bounded<B: Buf>(
+        bytes: &mut B,
+        max: usize,
+        options: DecodeOptions,
+    ) -> io::Result<Self> {
+        let remaining = bytes.remaining();
+        let str_len = VarInt::decode_with(bytes, options)?;
+
+        let max = if options.is_strict() {
+            max
+        } else {
+            MAX_MESSAGE_LEN
+        };
+        if *str_len > max {
+            return Err(ProtocolError::too_large(max, *str_len as u64, remaining).into());
         }
 
-        if bytes.remaining() < str_len.0 {
-            return Err(io::ErrorKind::InvalidData.into());
+        let remaining = bytes.remaining();
+        if remaining < str_len.0 {
+            return Err(ProtocolError::unexpected_eof(str_len.0, remaining).into());
         }
 
         let mut buffer = vec![0u8; str_len.0];
         bytes.copy_to_slice(&mut buffer);
 
         Ok(VarStr(String::from_utf8(buffer).map_err(|err| {
-            std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+            ProtocolError::invalid_value("a UTF-8 string", err, remaining)
         })?))
     }
 }
 
 /// A general purpose hash of length `32`.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+///
+/// Serializes to hex in the byte order displayed by `zcashd` (i.e. reversed), so that serialized
+/// block and transaction hashes match those of explorers and RPC output.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub struct Hash([u8; 32]);
 
 impl Hash {
@@ -201,6 +207,32 @@ impl Hash {
     }
 }
 
+/// Displays the hash in hex, in the same byte order as its serialization.
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bytes = self.0;
+        bytes.reverse();
+        f.write_str(&hex::encode(bytes))
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = self.0;
+        bytes.reverse();
+        bytes.serialize_hex(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut bytes = <[u8; 32]>::deserialize_hex(deserializer)?;
+        bytes.reverse();
+
+        Ok(Self(bytes))
+    }
+}
+
 impl Codec for Hash {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         buffer.put_slice(&self.0);
@@ -208,9 +240,9 @@ impl Codec for Hash {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
         if bytes.remaining() < 32 {
-            return Err(io::ErrorKind::InvalidData.into());
+            return Err(ProtocolError::unexpected_eof(32, bytes.remaining()).into());
         }
 
         let mut hash = Hash([0u8; 32]);
@@ -223,7 +255,7 @@ impl Codec for Hash {
 /// Reads `n` bytes from the bytes.
 pub fn read_n_bytes<const N: usize, B: Buf>(bytes: &mut B) -> io::Result<[u8; N]> {
     if bytes.remaining() < N {
-        return Err(io::ErrorKind::InvalidData.into());
+        return Err(ProtocolError::unexpected_eof(N, bytes.remaining()).into());
     }
 
     let mut buffer = [0u8; N];
@@ -232,16 +264,67 @@ pub fn read_n_bytes<const N: usize, B: Buf>(bytes: &mut B) -> io::Result<[u8; N]
     Ok(buffer)
 }
 
+/// Reads a `CompactSize` encoded integer, without the bound [`VarInt`] places on lengths.
+///
+/// Encodings longer than necessary are rejected when decoding strictly (see
+/// [`DecodeOptions::strict`]).
+pub fn read_compact_size<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<u64> {
+    let remaining = bytes.remaining();
+    let flag = u8::from_le_bytes(read_n_bytes(bytes)?);
+
+    let (value, min) = match flag {
+        value @ 0x00..=0xfc => (value as u64, 0),
+        0xfd => (u16::from_le_bytes(read_n_bytes(bytes)?) as u64, 0xfd),
+        0xfe => (u32::from_le_bytes(read_n_bytes(bytes)?) as u64, 0x1_0000),
+        0xff => (u64::from_le_bytes(read_n_bytes(bytes)?), 0x1_0000_0000),
+    };
+
+    if options.is_strict() && value < min {
+        let len = remaining - bytes.remaining();
+        return Err(ProtocolError::invalid_value(
+            "a canonical CompactSize",
+            format!("{value} encoded in {len} bytes"),
+            remaining,
+        )
+        .into());
+    }
+
+    Ok(value)
+}
+
+/// Writes an integer in its shortest `CompactSize` encoding.
+pub fn write_compact_size<B: BufMut>(buffer: &mut B, value: u64) {
+    match value {
+        0x0000_0000..=0x0000_00fc => buffer.put_u8(value as u8),
+        0x0000_00fd..=0x0000_ffff => {
+            buffer.put_u8(0xfd);
+            buffer.put_u16_le(value as u16);
+        }
+        0x0001_0000..=0xffff_ffff => {
+            buffer.put_u8(0xfe);
+            buffer.put_u32_le(value as u32);
+        }
+        _ => {
+            buffer.put_u8(0xff);
+            buffer.put_u64_le(value);
+        }
+    }
+}
+
 /// Reads a timestamp encoded as 8 bytes.
 pub fn read_timestamp<B: Buf>(bytes: &mut B) -> io::Result<OffsetDateTime> {
+    let remaining = bytes.remaining();
     let timestamp_i64 = i64::from_le_bytes(read_n_bytes(bytes)?);
-    OffsetDateTime::from_unix_timestamp(timestamp_i64)
-        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Bad UTC timestamp"))
+    OffsetDateTime::from_unix_timestamp(timestamp_i64).map_err(|_| {
+        ProtocolError::invalid_value("a valid UTC timestamp", timestamp_i64, remaining).into()
+    })
 }
 
 /// Reads a timestamp encoded as 4 bytes.
 pub fn read_short_timestamp<B: Buf>(bytes: &mut B) -> io::Result<OffsetDateTime> {
+    let remaining = bytes.remaining();
     let timestamp_u32 = u32::from_le_bytes(read_n_bytes(bytes)?);
-    OffsetDateTime::from_unix_timestamp(timestamp_u32.into())
-        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Bad UTC timestamp"))
+    OffsetDateTime::from_unix_timestamp(timestamp_u32.into()).map_err(|_| {
+        ProtocolError::invalid_value("a valid UTC timestamp", timestamp_u32, remaining).into()
+    })
 }
@@ -3,11 +3,20 @@
 use std::io::{self, Read};
 
 use bytes::{Buf, BufMut};
-
-use crate::protocol::payload::{codec::Codec, VarStr};
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{
+    message::constants::{COMMAND_LEN, MAX_REJECT_MESSAGE_LENGTH},
+    payload::{
+        codec::{Codec, DecodeOptions},
+        inv::{InvHash, ObjectKind},
+        serde_helpers::as_hex,
+        DecodeContext, Hash, ProtocolError, VarStr,
+    },
+};
 
 /// A reject message payload.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Reject {
     /// The type of message rejected.
     pub message: VarStr,
@@ -21,9 +30,82 @@ pub struct Reject {
     /// so the field is 32 bytes.
     ///
     /// We support any length data to fully adhere to the spec.
+    #[serde(with = "as_hex")]
     pub data: Vec<u8>,
 }
 
+impl Reject {
+    /// Returns the hash carried in the extra data, if it holds exactly one.
+    pub fn data_hash(&self) -> Option<Hash> {
+        let data: [u8; 32] = self.data.as_slice().try_into().ok()?;
+
+        Some(Hash::new(data))
+    }
+
+    /// Returns the inventory hash of the rejected transaction or block, if the reject is for one
+    /// and carries its hash.
+    pub fn rejected_object(&self) -> Option<InvHash> {
+        let kind = match self.message.0.as_str() {
+            "tx" => ObjectKind::Tx,
+            "block" => ObjectKind::Block,
+            _ => return None,
+        };
+
+        Some(InvHash::new(kind, self.data_hash()?))
+    }
+
+    /// Returns `true` if this rejects the object of `inv_hash`.
+    ///
+    /// Transactions are rejected by ID, a [`ObjectKind::WTx`] hash matches regardless of its auth
+    /// digest.
+    pub fn rejects(&self, inv_hash: &InvHash) -> bool {
+        let rejected = match self.rejected_object() {
+            Some(rejected) => rejected,
+            None => return false,
+        };
+
+        let kind = match inv_hash.kind() {
+            ObjectKind::WTx => ObjectKind::Tx,
+            kind => kind,
+        };
+
+        rejected.kind() == kind && rejected.hash() == inv_hash.hash()
+    }
+
+    /// Checks that this rejects the object of `inv_hash` with `ccode`, the error describes the
+    /// mismatch otherwise.
+    pub fn check_rejects(&self, inv_hash: &InvHash, ccode: CCode) -> io::Result<()> {
+        if !self.rejects(inv_hash) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Rejected {:?} ({:?}) instead of {:?}",
+                    self.message.0,
+                    self.data_hash(),
+                    inv_hash
+                ),
+            ));
+        }
+
+        self.check_ccode(ccode)
+    }
+
+    /// Checks that this rejects with `ccode`, the error describes the mismatch otherwise.
+    pub fn check_ccode(&self, ccode: CCode) -> io::Result<()> {
+        if self.ccode != ccode {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Incorrect rejection ccode: {:?} instead of {:?} (reason: {:?})",
+                    self.ccode, ccode, self.reason.0
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
 impl Codec for Reject {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         self.message.encode(buffer)?;
@@ -34,10 +116,11 @@ impl Codec for Reject {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let message = VarStr::decode(bytes)?;
-        let ccode = CCode::decode(bytes)?;
-        let reason = VarStr::decode(bytes)?;
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let message = VarStr::decode_bounded(bytes, COMMAND_LEN, options).field("message")?;
+        let ccode = CCode::decode_with(bytes, options).field("ccode")?;
+        let reason =
+            VarStr::decode_bounded(bytes, MAX_REJECT_MESSAGE_LENGTH, options).field("reason")?;
 
         // Current usage of the data field is `Option<[u8; 32]>`,
         // but the spec allows for any length [u8], so we support that case.
@@ -64,7 +147,7 @@ const CHECKPOINT_CODE: u8 = 0x43;
 const OTHER_CODE: u8 = 0x50;
 
 /// The code specifying the reject reason.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CCode {
     Malformed,
     Invalid,
@@ -96,9 +179,10 @@ impl Codec for CCode {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        if bytes.remaining() == 0 {
-            return Err(io::ErrorKind::InvalidData.into());
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
+        let remaining = bytes.remaining();
+        if remaining == 0 {
+            return Err(ProtocolError::unexpected_eof(1, remaining).into());
         }
 
         match bytes.get_u8() {
@@ -111,10 +195,57 @@ impl Codec for CCode {
             INSUFFICIENT_FEE_CODE => Ok(Self::InsufficientFee),
             CHECKPOINT_CODE => Ok(Self::Checkpoint),
             OTHER_CODE => Ok(Self::Other),
-            b => Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Invalid CCode {b:#x}"),
-            )),
+            b => Err(
+                ProtocolError::invalid_value("a known CCode", format!("{b:#x}"), remaining).into(),
+            ),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::payload::{block::Block, tx::encoded};
+
+    fn reject(message: &str, ccode: CCode, data: Vec<u8>) -> Reject {
+        Reject {
+            message: VarStr(message.to_owned()),
+            ccode,
+            reason: VarStr("bad-txns-inputs-missingorspent".to_owned()),
+            data,
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn rejects_are_linked_to_objects() {
+        let block = Block::testnet_1();
+        let tx = &block.txs[0];
+        let txid = tx.inv_hash().hash();
+
+        let tx_reject = reject("tx", CCode::Invalid, encoded(&txid));
+        assert_eq!(tx_reject.data_hash(), Some(txid));
+        assert_eq!(tx_reject.rejected_object(), Some(tx.inv_hash()));
+        assert!(tx_reject.rejects(&tx.inv_hash()));
+        assert!(tx_reject.rejects(&tx.wtx_inv_hash()));
+        assert!(!tx_reject.rejects(&block.inv_hash()));
+
+        tx_reject
+            .check_rejects(&tx.wtx_inv_hash(), CCode::Invalid)
+            .unwrap();
+        assert!(tx_reject
+            .check_rejects(&tx.inv_hash(), CCode::Duplicate)
+            .is_err());
+
+        // A block with the transaction's hash is a different object.
+        let block_reject = reject("block", CCode::Invalid, encoded(&txid));
+        assert!(!block_reject.rejects(&tx.inv_hash()));
+
+        // Rejects of other messages don't carry data.
+        let version_reject = reject("version", CCode::Obsolete, Vec::new());
+        assert_eq!(version_reject.data_hash(), None);
+        assert_eq!(version_reject.rejected_object(), None);
+        version_reject.check_ccode(CCode::Obsolete).unwrap();
+        assert!(version_reject.check_ccode(CCode::Invalid).is_err());
+    }
+}
@@ -0,0 +1,670 @@
+//! Transparent scripts, the standard templates they follow and the `t`-addresses they pay to.
+
+use std::{fmt, io, str::FromStr};
+
+use sha2::Digest;
+
+use crate::protocol::{
+    network::Network,
+    payload::{tx::TxOut, ProtocolError},
+};
+
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_PUSHDATA2: u8 = 0x4d;
+pub const OP_PUSHDATA4: u8 = 0x4e;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_RETURN: u8 = 0x6a;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_HASH160: u8 = 0xa9;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKMULTISIG: u8 = 0xae;
+
+/// The largest `OP_RETURN` script `zcashd` relays (`MAX_OP_RETURN_RELAY`).
+pub const MAX_NULL_DATA_SCRIPT_LEN: usize = 223;
+/// The most public keys in a multisig script `zcashd` relays.
+pub const MAX_STANDARD_MULTISIG_KEYS: usize = 3;
+
+/// The Base58Check prefixes of mainnet addresses (`t1` and `t3`).
+const MAINNET_PREFIXES: AddressPrefixes = AddressPrefixes {
+    pub_key_hash: [0x1c, 0xb8],
+    script_hash: [0x1c, 0xbd],
+};
+/// The Base58Check prefixes of testnet and regtest addresses (`tm` and `t2`).
+const TESTNET_PREFIXES: AddressPrefixes = AddressPrefixes {
+    pub_key_hash: [0x1d, 0x25],
+    script_hash: [0x1c, 0xba],
+};
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// A parsed script instruction.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instruction<'a> {
+    /// Pushes data onto the stack, `OP_0` pushes an empty slice.
+    Push(&'a [u8]),
+    /// Any other opcode.
+    Op(u8),
+}
+
+impl Instruction<'_> {
+    /// Returns the number pushed by `OP_1` to `OP_16`.
+    fn small_number(&self) -> Option<usize> {
+        match *self {
+            Self::Op(opcode @ OP_1..=OP_16) => Some((opcode - OP_1) as usize + 1),
+            _ => None,
+        }
+    }
+}
+
+/// An iterator over the instructions of a script, see [`instructions`].
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    script: &'a [u8],
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = io::Result<Instruction<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&opcode, rest) = self.script.split_first()?;
+
+        let len_size = match opcode {
+            OP_0..=0x4b => 0,
+            OP_PUSHDATA1 => 1,
+            OP_PUSHDATA2 => 2,
+            OP_PUSHDATA4 => 4,
+            _ => {
+                self.script = rest;
+                return Some(Ok(Instruction::Op(opcode)));
+            }
+        };
+
+        // Parsing stops at the first malformed push.
+        self.script = &[];
+
+        if rest.len() < len_size {
+            return Some(Err(
+                ProtocolError::unexpected_eof(len_size, rest.len()).into()
+            ));
+        }
+        let (len_bytes, rest) = rest.split_at(len_size);
+        let len = if len_size == 0 {
+            opcode as usize
+        } else {
+            len_bytes
+                .iter()
+                .rev()
+                .fold(0, |len, byte| len << 8 | *byte as usize)
+        };
+
+        if rest.len() < len {
+            return Some(Err(ProtocolError::unexpected_eof(len, rest.len()).into()));
+        }
+        let (data, rest) = rest.split_at(len);
+        self.script = rest;
+
+        Some(Ok(Instruction::Push(data)))
+    }
+}
+
+/// Returns an iterator over the instructions of `script`, which yields an error and stops if a
+/// push runs past the end of the script.
+pub fn instructions(script: &[u8]) -> Instructions<'_> {
+    Instructions { script }
+}
+
+/// Appends the shortest push of `data` to `script`.
+pub fn push_data(script: &mut Vec<u8>, data: &[u8]) {
+    match data.len() {
+        len @ 0..=0x4b => script.push(len as u8),
+        len @ 0x4c..=0xff => script.extend([OP_PUSHDATA1, len as u8]),
+        len @ 0x100..=0xffff => {
+            script.push(OP_PUSHDATA2);
+            script.extend((len as u16).to_le_bytes());
+        }
+        len => {
+            script.push(OP_PUSHDATA4);
+            script.extend((len as u32).to_le_bytes());
+        }
+    }
+    script.extend_from_slice(data);
+}
+
+/// The template followed by a public key script, as classified by `zcashd`'s relay policy.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ScriptKind {
+    /// Pays to a compressed (33 bytes) or uncompressed (65 bytes) public key (P2PK), which has no
+    /// address.
+    PubKey(Vec<u8>),
+    /// Pays to the hash of a public key (P2PKH), addressed by `t1` and `tm` addresses.
+    PubKeyHash([u8; 20]),
+    /// Pays to the hash of a redeem script (P2SH), addressed by `t3` and `t2` addresses.
+    ScriptHash([u8; 20]),
+    /// A bare multisig script, needing `required` signatures from `pub_keys`.
+    Multisig {
+        required: usize,
+        pub_keys: Vec<Vec<u8>>,
+    },
+    /// An unspendable `OP_RETURN` script, holding the data it pushes.
+    NullData(Vec<u8>),
+    /// Any other script, which nodes don't relay.
+    NonStandard,
+}
+
+impl ScriptKind {
+    /// Classifies `script`, which is non-standard unless it matches one of the templates and
+    /// respects `zcashd`'s size limits.
+    pub fn classify(script: &[u8]) -> Self {
+        match script {
+            [len @ (33 | 65), key @ .., OP_CHECKSIG] if key.len() == *len as usize => {
+                Self::PubKey(key.to_vec())
+            }
+            [OP_DUP, OP_HASH160, 20, hash @ .., OP_EQUALVERIFY, OP_CHECKSIG]
+                if hash.len() == 20 =>
+            {
+                Self::PubKeyHash(hash.try_into().unwrap())
+            }
+            [OP_HASH160, 20, hash @ .., OP_EQUAL] if hash.len() == 20 => {
+                Self::ScriptHash(hash.try_into().unwrap())
+            }
+            [OP_RETURN, ..] => Self::classify_null_data(script),
+            [.., OP_CHECKMULTISIG] => Self::classify_multisig(script),
+            _ => Self::NonStandard,
+        }
+    }
+
+    fn classify_null_data(script: &[u8]) -> Self {
+        if script.len() > MAX_NULL_DATA_SCRIPT_LEN {
+            return Self::NonStandard;
+        }
+
+        let mut data = Vec::new();
+        for instruction in instructions(&script[1..]) {
+            match instruction {
+                Ok(Instruction::Push(bytes)) => data.extend_from_slice(bytes),
+                // Small numbers are pushes too.
+                Ok(Instruction::Op(opcode)) if opcode <= OP_16 => {}
+                _ => return Self::NonStandard,
+            }
+        }
+
+        Self::NullData(data)
+    }
+
+    fn classify_multisig(script: &[u8]) -> Self {
+        let Ok(instructions) = instructions(script).collect::<io::Result<Vec<_>>>() else {
+            return Self::NonStandard;
+        };
+
+        let [first, keys @ .., last, Instruction::Op(OP_CHECKMULTISIG)] = &instructions[..] else {
+            return Self::NonStandard;
+        };
+
+        let (Some(required), Some(key_count)) = (first.small_number(), last.small_number()) else {
+            return Self::NonStandard;
+        };
+
+        let pub_keys: Vec<Vec<u8>> = keys
+            .iter()
+            .filter_map(|key| match key {
+                Instruction::Push(key) if key.len() == 33 || key.len() == 65 => Some(key.to_vec()),
+                _ => None,
+            })
+            .collect();
+
+        if pub_keys.len() != keys.len()
+            || key_count != keys.len()
+            || required > key_count
+            || key_count > MAX_STANDARD_MULTISIG_KEYS
+        {
+            return Self::NonStandard;
+        }
+
+        Self::Multisig { required, pub_keys }
+    }
+
+    /// Returns `true` unless the script is non-standard.
+    pub fn is_standard(&self) -> bool {
+        *self != Self::NonStandard
+    }
+
+    /// Builds the script following this template, `None` for [`ScriptKind::NonStandard`].
+    pub fn to_script(&self) -> Option<Vec<u8>> {
+        let mut script = Vec::new();
+
+        match self {
+            Self::PubKey(key) => {
+                push_data(&mut script, key);
+                script.push(OP_CHECKSIG);
+            }
+            Self::PubKeyHash(hash) => {
+                script.extend([OP_DUP, OP_HASH160, 20]);
+                script.extend_from_slice(hash);
+                script.extend([OP_EQUALVERIFY, OP_CHECKSIG]);
+            }
+            Self::ScriptHash(hash) => {
+                script.extend([OP_HASH160, 20]);
+                script.extend_from_slice(hash);
+                script.push(OP_EQUAL);
+            }
+            Self::Multisig { required, pub_keys } => {
+                script.push(OP_1 + *required as u8 - 1);
+                for key in pub_keys {
+                    push_data(&mut script, key);
+                }
+                script.push(OP_1 + pub_keys.len() as u8 - 1);
+                script.push(OP_CHECKMULTISIG);
+            }
+            Self::NullData(data) => {
+                script.push(OP_RETURN);
+                if !data.is_empty() {
+                    push_data(&mut script, data);
+                }
+            }
+            Self::NonStandard => return None,
+        }
+
+        Some(script)
+    }
+
+    /// Returns the address the script pays to on `network`, if it has one.
+    pub fn address(&self, network: Network) -> Option<Address> {
+        match self {
+            Self::PubKeyHash(hash) => Some(Address::PubKeyHash {
+                network,
+                hash: *hash,
+            }),
+            Self::ScriptHash(hash) => Some(Address::ScriptHash {
+                network,
+                hash: *hash,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl TxOut {
+    /// Classifies the public key script.
+    pub fn script_kind(&self) -> ScriptKind {
+        ScriptKind::classify(self.pk_script())
+    }
+
+    /// Returns the address the output pays to on `network`, if it has one.
+    pub fn address(&self, network: Network) -> Option<Address> {
+        self.script_kind().address(network)
+    }
+}
+
+/// A transparent Zcash address, displayed and parsed in its Base58Check form.
+///
+/// All networks but mainnet share the testnet prefixes, so addresses parsed from a string are
+/// either on [`Network::Mainnet`] or [`Network::Testnet`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Address {
+    /// A P2PKH address, `t1` on mainnet and `tm` elsewhere.
+    PubKeyHash { network: Network, hash: [u8; 20] },
+    /// A P2SH address, `t3` on mainnet and `t2` elsewhere.
+    ScriptHash { network: Network, hash: [u8; 20] },
+}
+
+impl Address {
+    /// Returns the network the address is used on.
+    pub fn network(&self) -> Network {
+        match self {
+            Self::PubKeyHash { network, .. } | Self::ScriptHash { network, .. } => *network,
+        }
+    }
+
+    /// Returns the public key or script hash.
+    pub fn hash(&self) -> [u8; 20] {
+        match self {
+            Self::PubKeyHash { hash, .. } | Self::ScriptHash { hash, .. } => *hash,
+        }
+    }
+
+    /// Returns the public key script paying to this address.
+    pub fn pk_script(&self) -> Vec<u8> {
+        let kind = match self {
+            Self::PubKeyHash { hash, .. } => ScriptKind::PubKeyHash(*hash),
+            Self::ScriptHash { hash, .. } => ScriptKind::ScriptHash(*hash),
+        };
+
+        kind.to_script().unwrap()
+    }
+
+    fn prefix(&self) -> [u8; 2] {
+        let prefixes = match self.network() {
+            Network::Mainnet => MAINNET_PREFIXES,
+            _ => TESTNET_PREFIXES,
+        };
+
+        match self {
+            Self::PubKeyHash { .. } => prefixes.pub_key_hash,
+            Self::ScriptHash { .. } => prefixes.script_hash,
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut payload = self.prefix().to_vec();
+        payload.extend_from_slice(&self.hash());
+
+        f.write_str(&base58_check_encode(&payload))
+    }
+}
+
+impl FromStr for Address {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let payload = base58_check_decode(s)?;
+
+        let (prefix, hash) = match payload.len() {
+            22 => payload.split_at(2),
+            len => return Err(invalid_address(format!("expected 22 bytes, got {len}"))),
+        };
+        let hash = hash.try_into().unwrap();
+
+        [
+            (Network::Mainnet, MAINNET_PREFIXES),
+            (Network::Testnet, TESTNET_PREFIXES),
+        ]
+        .into_iter()
+        .find_map(|(network, prefixes)| {
+            if prefix == prefixes.pub_key_hash {
+                Some(Self::PubKeyHash { network, hash })
+            } else if prefix == prefixes.script_hash {
+                Some(Self::ScriptHash { network, hash })
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| invalid_address(format!("unknown prefix {}", hex::encode(prefix))))
+    }
+}
+
+/// The Base58Check prefixes of the addresses of a network.
+struct AddressPrefixes {
+    pub_key_hash: [u8; 2],
+    script_hash: [u8; 2],
+}
+
+/// Encodes `payload` followed by its checksum, the first 4 bytes of its double Sha256 hash.
+fn base58_check_encode(payload: &[u8]) -> String {
+    let mut bytes = payload.to_vec();
+    bytes.extend_from_slice(&checksum(payload));
+
+    // Each leading zero byte is encoded as a leading '1'.
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+
+    // The base 58 digits, least significant first.
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in &bytes[zeros..] {
+        let mut carry = *byte as usize;
+        for digit in digits.iter_mut() {
+            carry += (*digit as usize) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    std::iter::repeat_n('1', zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|digit| BASE58_ALPHABET[*digit as usize] as char),
+        )
+        .collect()
+}
+
+/// Decodes a Base58Check string into its payload, checking and stripping the checksum.
+fn base58_check_decode(s: &str) -> io::Result<Vec<u8>> {
+    let zeros = s.chars().take_while(|c| *c == '1').count();
+
+    // The bytes, least significant first.
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in s.chars().skip(zeros) {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|digit| *digit as char == c)
+            .ok_or_else(|| invalid_address(format!("invalid Base58 character {c:?}")))?;
+
+        for byte in bytes.iter_mut() {
+            carry += *byte as usize * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    bytes.extend(std::iter::repeat_n(0, zeros));
+    bytes.reverse();
+
+    if bytes.len() < 4 {
+        return Err(invalid_address("missing checksum".to_string()));
+    }
+    let (payload, expected) = bytes.split_at(bytes.len() - 4);
+    if checksum(payload) != expected {
+        return Err(invalid_address("invalid checksum".to_string()));
+    }
+
+    Ok(payload.to_vec())
+}
+
+fn checksum(payload: &[u8]) -> [u8; 4] {
+    let hash = sha2::Sha256::digest(sha2::Sha256::digest(payload));
+
+    hash[..4].try_into().unwrap()
+}
+
+fn invalid_address(message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid address: {message}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        protocol::payload::{block::Block, codec::Codec},
+        vectors::*,
+    };
+
+    #[test]
+    #[ignore]
+    fn instructions_are_parsed() {
+        let script = [
+            &[OP_0, 2, 0xaa, 0xbb, OP_PUSHDATA1, 1, 0xcc][..],
+            &[OP_PUSHDATA2, 1, 0, 0xdd, OP_1, OP_CHECKSIG],
+        ]
+        .concat();
+
+        assert_eq!(
+            instructions(&script)
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap(),
+            [
+                Instruction::Push(&[]),
+                Instruction::Push(&[0xaa, 0xbb]),
+                Instruction::Push(&[0xcc]),
+                Instruction::Push(&[0xdd]),
+                Instruction::Op(OP_1),
+                Instruction::Op(OP_CHECKSIG),
+            ]
+        );
+
+        // Truncated pushes end the iteration with an error.
+        for script in [&[OP_CHECKSIG, 3, 0xaa][..], &[OP_PUSHDATA2, 1]] {
+            let results: Vec<_> = instructions(script).collect();
+            assert!(results.last().unwrap().is_err());
+        }
+
+        for len in [0, 0x4b, 0x4c, 0xff, 0x100, 0x1_0000] {
+            let data = vec![0xee; len];
+            let mut script = Vec::new();
+            push_data(&mut script, &data);
+
+            let mut parsed = instructions(&script);
+            assert_eq!(parsed.next().unwrap().unwrap(), Instruction::Push(&data));
+            assert!(parsed.next().is_none());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn captured_outputs_pay_to_known_addresses() {
+        let cases = [
+            // The first Overwinter block's coinbase pays the miner and the founders' reward.
+            (
+                &BLOCK_TESTNET_0_207_500_BYTES[..],
+                &[
+                    "tmNVze4878moffDRq4EueZtiakvUo7v3noZ",
+                    "t2D3k4fNdErd66YxtvXEdft9xuLoKD7CcVo",
+                ][..],
+            ),
+            // NU5 coinbases pay the development fund's funding streams.
+            (
+                &BLOCK_TESTNET_1_599_200_BYTES[..],
+                &[
+                    "tmKngFiAixfhGm67E6YThCV34yqgvscDQuw",
+                    "t27eWDgjFYJGVXmzrXeVjnb5J3uXDM9xH9v",
+                    "t29fBCFbhgsjL3XYEZ1yk1TUh7eTusB6dPg",
+                    "t2Gvxv2uNM7hbbACjNox4H6DjByoKZ2Fa3P",
+                ],
+            ),
+        ];
+
+        for (bytes, expected) in cases {
+            let block = Block::decode(&mut Cursor::new(bytes)).unwrap();
+            let addresses: Vec<String> = block.txs[0]
+                .tx_out()
+                .iter()
+                .map(|output| output.address(Network::Testnet).unwrap().to_string())
+                .collect();
+            assert_eq!(addresses, expected);
+
+            for (output, address) in block.txs[0].tx_out().iter().zip(expected) {
+                let address: Address = address.parse().unwrap();
+                assert_eq!(address.network(), Network::Testnet);
+                assert_eq!(address.pk_script(), output.pk_script());
+            }
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn scripts_are_classified() {
+        let pub_keys = vec![vec![0x02; 33], vec![0x04; 65], vec![0x03; 33]];
+        let standard = [
+            ScriptKind::PubKey(vec![0x02; 33]),
+            ScriptKind::PubKey(vec![0x04; 65]),
+            ScriptKind::PubKeyHash([0x11; 20]),
+            ScriptKind::ScriptHash([0x22; 20]),
+            ScriptKind::Multisig {
+                required: 2,
+                pub_keys: pub_keys.clone(),
+            },
+            ScriptKind::NullData(Vec::new()),
+            // Past the 83 bytes Bitcoin Core relays.
+            ScriptKind::NullData(vec![0x33; 81]),
+            ScriptKind::NullData(vec![0x33; MAX_NULL_DATA_SCRIPT_LEN - 3]),
+        ];
+        for kind in standard {
+            let script = kind.to_script().unwrap();
+            assert_eq!(ScriptKind::classify(&script), kind);
+            assert!(kind.is_standard());
+        }
+
+        let mut too_many_keys = pub_keys.clone();
+        too_many_keys.push(vec![0x02; 33]);
+        let non_standard = [
+            // Trailing opcodes.
+            [
+                ScriptKind::PubKeyHash([0x11; 20]).to_script().unwrap(),
+                vec![OP_CHECKSIG],
+            ]
+            .concat(),
+            ScriptKind::NullData(vec![0x33; MAX_NULL_DATA_SCRIPT_LEN - 2])
+                .to_script()
+                .unwrap(),
+            ScriptKind::Multisig {
+                required: 1,
+                pub_keys: too_many_keys,
+            }
+            .to_script()
+            .unwrap(),
+            ScriptKind::Multisig {
+                required: 4,
+                pub_keys,
+            }
+            .to_script()
+            .unwrap(),
+            vec![OP_RETURN, OP_CHECKSIG],
+            // Public keys are 33 or 65 bytes long.
+            ScriptKind::PubKey(vec![0x02; 34]).to_script().unwrap(),
+            vec![OP_1],
+            Vec::new(),
+        ];
+        for script in non_standard {
+            assert_eq!(ScriptKind::classify(&script), ScriptKind::NonStandard);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn addresses_round_trip() {
+        for (address, expected) in [
+            (
+                Address::PubKeyHash {
+                    network: Network::Mainnet,
+                    hash: [0; 20],
+                },
+                "t1Hsc1LR8yKnbbe3twRp88p6vFfC5t7DLbs",
+            ),
+            (
+                Address::ScriptHash {
+                    network: Network::Mainnet,
+                    hash: [0xff; 20],
+                },
+                "t3huDuzCsEQZrWEtR5wp33Zje2gjjzEm8RA",
+            ),
+        ] {
+            assert_eq!(address.to_string(), expected);
+            assert_eq!(expected.parse::<Address>().unwrap(), address);
+        }
+
+        // Regtest addresses share the testnet prefixes.
+        let regtest = Address::PubKeyHash {
+            network: Network::Regtest,
+            hash: [0x8c; 20],
+        };
+        assert!(regtest.to_string().starts_with("tm"));
+
+        for invalid in [
+            // A changed character breaks the checksum.
+            "t1Hsc1LR8yKnbbe3twRp88p6vFfC5t7DLbt",
+            "t1Hsc1LR8yKnbbe3twRp88p6vFfC5t7DLb0",
+            // A Bitcoin address.
+            "1BoatSLRHtKNngkdXEeobR76b53LETtpyT",
+            "",
+        ] {
+            assert!(invalid.parse::<Address>().is_err());
+        }
+    }
+}
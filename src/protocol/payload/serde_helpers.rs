@@ -0,0 +1,213 @@
+//! Helpers for the serde representation of payloads, used through `#[serde(with = "...")]`.
+//!
+//! Byte strings are represented in hex and timestamps in RFC 3339, so that messages serialize to
+//! human-readable JSON which can be dumped, diffed and replayed.
+
+use std::convert::TryFrom;
+
+use bytes::Bytes;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// A type represented as a hex string, or as an option or list of hex strings.
+pub(crate) trait HexRepr: Sized {
+    fn serialize_hex<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+
+    fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    hex::decode(s).map_err(de::Error::custom)
+}
+
+impl HexRepr for Vec<u8> {
+    fn serialize_hex<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+
+    fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer)
+    }
+}
+
+impl HexRepr for Bytes {
+    fn serialize_hex<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+
+    fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_bytes(deserializer).map(Bytes::from)
+    }
+}
+
+impl<const N: usize> HexRepr for [u8; N] {
+    fn serialize_hex<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self, serializer)
+    }
+
+    fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        let len = bytes.len();
+
+        <[u8; N]>::try_from(bytes)
+            .map_err(|_| de::Error::invalid_length(len, &format!("{N} bytes").as_str()))
+    }
+}
+
+impl<T: HexRepr> HexRepr for Option<T> {
+    fn serialize_hex<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Some(value) => serializer.serialize_some(&AsHex(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Option::<FromHex<T>>::deserialize(deserializer)?;
+
+        Ok(value.map(|value| value.0))
+    }
+}
+
+impl<const N: usize> HexRepr for Vec<[u8; N]> {
+    fn serialize_hex<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter().map(AsHex))
+    }
+
+    fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values = Vec::<FromHex<[u8; N]>>::deserialize(deserializer)?;
+
+        Ok(values.into_iter().map(|value| value.0).collect())
+    }
+}
+
+/// Serializes the wrapped value with its [`HexRepr`].
+struct AsHex<'a, T>(&'a T);
+
+impl<T: HexRepr> Serialize for AsHex<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_hex(serializer)
+    }
+}
+
+/// Deserializes the wrapped value with its [`HexRepr`].
+struct FromHex<T>(T);
+
+impl<'de, T: HexRepr> Deserialize<'de> for FromHex<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_hex(deserializer).map(Self)
+    }
+}
+
+/// Represents byte strings (and options or lists of them) in hex, see [`HexRepr`].
+pub(crate) mod as_hex {
+    use super::*;
+
+    pub(crate) fn serialize<T: HexRepr, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.serialize_hex(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T: HexRepr, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        T::deserialize_hex(deserializer)
+    }
+}
+
+/// A timestamp represented as an RFC 3339 string.
+pub(crate) trait Rfc3339Repr: Sized {
+    fn serialize_rfc3339<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+
+    fn deserialize_rfc3339<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+impl Rfc3339Repr for OffsetDateTime {
+    fn serialize_rfc3339<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let s = self.format(&Rfc3339).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&s)
+    }
+
+    fn deserialize_rfc3339<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        OffsetDateTime::parse(&s, &Rfc3339).map_err(de::Error::custom)
+    }
+}
+
+/// Unix timestamps, as encoded in block headers.
+impl Rfc3339Repr for u32 {
+    fn serialize_rfc3339<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        OffsetDateTime::from_unix_timestamp((*self).into())
+            .map_err(serde::ser::Error::custom)?
+            .serialize_rfc3339(serializer)
+    }
+
+    fn deserialize_rfc3339<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let timestamp = OffsetDateTime::deserialize_rfc3339(deserializer)?.unix_timestamp();
+
+        u32::try_from(timestamp).map_err(|_| {
+            de::Error::invalid_value(
+                de::Unexpected::Signed(timestamp),
+                &"a timestamp between 1970 and 2106",
+            )
+        })
+    }
+}
+
+impl<T: Rfc3339Repr> Rfc3339Repr for Option<T> {
+    fn serialize_rfc3339<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Some(value) => serializer.serialize_some(&AsRfc3339(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    fn deserialize_rfc3339<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Option::<FromRfc3339<T>>::deserialize(deserializer)?;
+
+        Ok(value.map(|value| value.0))
+    }
+}
+
+/// Serializes the wrapped value with its [`Rfc3339Repr`].
+struct AsRfc3339<'a, T>(&'a T);
+
+impl<T: Rfc3339Repr> Serialize for AsRfc3339<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_rfc3339(serializer)
+    }
+}
+
+/// Deserializes the wrapped value with its [`Rfc3339Repr`].
+struct FromRfc3339<T>(T);
+
+impl<'de, T: Rfc3339Repr> Deserialize<'de> for FromRfc3339<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_rfc3339(deserializer).map(Self)
+    }
+}
+
+/// Represents timestamps (and options of them) in RFC 3339, see [`Rfc3339Repr`].
+pub(crate) mod as_rfc3339 {
+    use super::*;
+
+    pub(crate) fn serialize<T: Rfc3339Repr, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.serialize_rfc3339(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T: Rfc3339Repr, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        T::deserialize_rfc3339(deserializer)
+    }
+}
@@ -0,0 +1,172 @@
+//! Service flags advertised in version messages and network addresses.
+
+use std::{fmt, io, ops};
+
+use bytes::{Buf, BufMut};
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::payload::{
+    codec::{Codec, DecodeOptions},
+    read_n_bytes,
+};
+
+/// The set of services a node supports, as a bit field.
+///
+/// Bits without a known meaning are kept as-is, so that flags round-trip losslessly.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Services(u64);
+
+impl Services {
+    /// The node can serve the full block chain.
+    pub const NODE_NETWORK: Self = Self(1 << 0);
+    /// The node supports bloom filtered connections (BIP 111).
+    pub const NODE_BLOOM: Self = Self(1 << 2);
+
+    /// The known flags and their names, as used by [`Display`](fmt::Display).
+    const NAMED: [(Self, &'static str); 2] = [
+        (Self::NODE_NETWORK, "NODE_NETWORK"),
+        (Self::NODE_BLOOM, "NODE_BLOOM"),
+    ];
+
+    /// Returns a set with no services.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns the set with the given bits, known or not.
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw bits of the set.
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the bits which don't correspond to a known service.
+    pub fn unknown_bits(self) -> u64 {
+        let known = Self::NAMED.iter().fold(0, |bits, (flag, _)| bits | flag.0);
+        self.0 & !known
+    }
+
+    /// Returns `true` if no bits are set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all the services of `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Sets the services of `other`.
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    /// Clears the services of `other`.
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+}
+
+impl ops::BitOr for Services {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for Services {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl ops::BitAnd for Services {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl ops::Not for Services {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl From<u64> for Services {
+    fn from(bits: u64) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<Services> for u64 {
+    fn from(services: Services) -> Self {
+        services.0
+    }
+}
+
+/// Displays the names of the known services and the unknown bits in hex, separated by `|`, e.g.
+/// `NODE_NETWORK | 0x100`.
+impl fmt::Display for Services {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("(none)");
+        }
+
+        let mut names: Vec<String> = Self::NAMED
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name.to_string())
+            .collect();
+        if self.unknown_bits() != 0 {
+            names.push(format!("{:#x}", self.unknown_bits()));
+        }
+
+        f.write_str(&names.join(" | "))
+    }
+}
+
+impl Codec for Services {
+    fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
+        buffer.put_u64_le(self.0);
+
+        Ok(())
+    }
+
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
+        Ok(Self(u64::from_le_bytes(read_n_bytes(bytes)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn unknown_bits_round_trip() {
+        let services = Services::NODE_NETWORK | Services::from_bits(1 << 24);
+        assert!(services.contains(Services::NODE_NETWORK));
+        assert!(!services.contains(Services::NODE_BLOOM));
+        assert_eq!(services.unknown_bits(), 1 << 24);
+        assert_eq!(services.to_string(), "NODE_NETWORK | 0x1000000");
+
+        let mut bytes = Vec::new();
+        services.encode(&mut bytes).unwrap();
+        assert_eq!(Services::decode(&mut &bytes[..]).unwrap(), services);
+
+        let mut services = services;
+        services.insert(Services::NODE_BLOOM);
+        services.remove(Services::NODE_NETWORK);
+        assert_eq!(services.bits(), (1 << 24) | (1 << 2));
+        assert_eq!(Services::empty().to_string(), "(none)");
+    }
+}
@@ -0,0 +1,642 @@
+//! Signature hashes of transactions, as signed by transparent inputs ([ZIP
+//! 143](https://zips.z.cash/zip-0143) for V3, [ZIP 243](https://zips.z.cash/zip-0243) for V4 and
+//! [ZIP 244](https://zips.z.cash/zip-0244) for V5 transactions).
+
+use std::{fmt, io};
+
+use crate::protocol::payload::{
+    tx::{
+        blake2b_256, encoded, finalize, outputs_digest, prevouts_digest, sequences_digest,
+        JoinSplit, OutputDescriptionV4, SpendDescriptionV4, TxIn, TxOut, TxV3, TxV4, TxV5,
+    },
+    Hash, Tx, VarInt,
+};
+
+/// The hash type of a transparent signature, which selects the parts of the transaction it
+/// commits to. It is appended to the signature in the signature script.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct HashType(u8);
+
+impl HashType {
+    /// Commits to all inputs and outputs.
+    pub const ALL: Self = Self(0x01);
+    /// Commits to all inputs and none of the outputs.
+    pub const NONE: Self = Self(0x02);
+    /// Commits to all inputs and the output with the same index as the signed input.
+    pub const SINGLE: Self = Self(0x03);
+
+    /// The flag restricting the commitment to the signed input only.
+    const ANYONE_CAN_PAY: u8 = 0x80;
+    /// The bits selecting the outputs.
+    const OUTPUTS_MASK: u8 = 0x1f;
+
+    /// Returns this hash type, only committing to the signed input.
+    pub fn anyone_can_pay(self) -> Self {
+        Self(self.0 | Self::ANYONE_CAN_PAY)
+    }
+
+    /// Returns the hash type encoded in `byte`, if it is one of the six valid hash types.
+    pub fn from_byte(byte: u8) -> io::Result<Self> {
+        match byte & !Self::ANYONE_CAN_PAY {
+            0x01..=0x03 => Ok(Self(byte)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid hash type {byte:#04x}"),
+            )),
+        }
+    }
+
+    /// Returns the byte encoding of the hash type.
+    pub fn to_byte(self) -> u8 {
+        self.0
+    }
+
+    fn is_anyone_can_pay(self) -> bool {
+        self.0 & Self::ANYONE_CAN_PAY != 0
+    }
+
+    fn outputs(self) -> u8 {
+        self.0 & Self::OUTPUTS_MASK
+    }
+}
+
+impl fmt::Display for HashType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outputs = match self.outputs() {
+            0x01 => "ALL",
+            0x02 => "NONE",
+            _ => "SINGLE",
+        };
+
+        if self.is_anyone_can_pay() {
+            write!(f, "{outputs}|ANYONECANPAY")
+        } else {
+            f.write_str(outputs)
+        }
+    }
+}
+
+/// A transparent input being signed, for V3 and V4 transactions.
+#[derive(Debug, Clone, Copy)]
+pub struct SignableInput<'a> {
+    /// The index of the input in the transaction.
+    pub index: usize,
+    /// The script the signature commits to, the `pk_script` of the spent output or the redeem
+    /// script for P2SH outputs.
+    pub script_code: &'a [u8],
+    /// The value of the spent output, in zatoshis.
+    pub value: i64,
+}
+
+impl Tx {
+    /// Computes the hash signed by the transparent input with the given index, or the hash
+    /// signed by the shielded components if `None`.
+    ///
+    /// `spent_outputs` are the outputs spent by each of the transaction's transparent inputs,
+    /// the `pk_script` of the signed input's spent output is used as its script code. Use
+    /// [`TxV4::signature_hash`] directly to sign with a different script code, e.g. for P2SH.
+    /// V5 transactions commit to their own consensus branch ID rather than `consensus_branch`,
+    /// legacy (V1 and V2) transactions aren't supported.
+    pub fn signature_hash(
+        &self,
+        input_index: Option<usize>,
+        hash_type: HashType,
+        consensus_branch: u32,
+        spent_outputs: &[TxOut],
+    ) -> io::Result<Hash> {
+        let input = input_index
+            .map(|index| {
+                spent_outputs
+                    .get(index)
+                    .map(|output| SignableInput {
+                        index,
+                        script_code: output.pk_script(),
+                        value: output.value(),
+                    })
+                    .ok_or_else(|| invalid_input(format!("no spent output for input {index}")))
+            })
+            .transpose()?;
+
+        match self {
+            Tx::V1(_) | Tx::V2(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "legacy signature hashes aren't supported",
+            )),
+            Tx::V3(tx) => tx.signature_hash(input, hash_type, consensus_branch),
+            Tx::V4(tx) => tx.signature_hash(input, hash_type, consensus_branch),
+            Tx::V5(tx) => tx.signature_hash(input_index, hash_type, spent_outputs),
+        }
+    }
+}
+
+impl TxV3 {
+    /// Computes the [ZIP 143](https://zips.z.cash/zip-0143) signature hash, see
+    /// [`Tx::signature_hash`].
+    pub fn signature_hash(
+        &self,
+        input: Option<SignableInput>,
+        hash_type: HashType,
+        consensus_branch: u32,
+    ) -> io::Result<Hash> {
+        let fields = PreV5Fields {
+            header: 3 | 1 << 31,
+            group_id: self.group_id(),
+            tx_in: self.tx_in(),
+            tx_out: self.tx_out(),
+            lock_time: self.lock_time(),
+            expiry_height: self.expiry_height(),
+            join_split: self.join_split(),
+            join_split_pub_key: self.join_split_pub_key(),
+            sapling: None,
+        };
+
+        fields.signature_hash(input, hash_type, consensus_branch)
+    }
+}
+
+impl TxV4 {
+    /// Computes the [ZIP 243](https://zips.z.cash/zip-0243) signature hash, see
+    /// [`Tx::signature_hash`].
+    pub fn signature_hash(
+        &self,
+        input: Option<SignableInput>,
+        hash_type: HashType,
+        consensus_branch: u32,
+    ) -> io::Result<Hash> {
+        let fields = PreV5Fields {
+            header: 4 | 1 << 31,
+            group_id: self.group_id(),
+            tx_in: self.tx_in(),
+            tx_out: self.tx_out(),
+            lock_time: self.lock_time(),
+            expiry_height: self.expiry_height(),
+            join_split: self.join_split(),
+            join_split_pub_key: self.join_split_pub_key(),
+            sapling: Some((
+                self.spends_sapling(),
+                self.outputs_sapling(),
+                self.value_balance_sapling(),
+            )),
+        };
+
+        fields.signature_hash(input, hash_type, consensus_branch)
+    }
+}
+
+impl TxV5 {
+    /// Computes the [ZIP 244](https://zips.z.cash/zip-0244) signature hash, see
+    /// [`Tx::signature_hash`].
+    ///
+    /// Signatures commit to the consensus branch ID of the transaction itself. `spent_outputs`
+    /// must hold the outputs spent by every transparent input, unless the transaction is a
+    /// coinbase or has no transparent inputs.
+    pub fn signature_hash(
+        &self,
+        input_index: Option<usize>,
+        hash_type: HashType,
+        spent_outputs: &[TxOut],
+    ) -> io::Result<Hash> {
+        let tx_in = self.tx_in();
+        let tx_out = self.tx_out();
+
+        if input_index.is_none() && hash_type != HashType::ALL {
+            return Err(invalid_input(format!(
+                "shielded signatures must use SIGHASH_ALL, not {hash_type}"
+            )));
+        }
+
+        let transparent_digest = if input_index.is_none() && is_coinbase_or_shielded(tx_in) {
+            // Without transparent inputs to sign for, shielded signatures commit to the same
+            // transparent data as the txid.
+            self.transparent_digest()
+        } else {
+            if spent_outputs.len() != tx_in.len() {
+                return Err(invalid_input(format!(
+                    "expected {} spent outputs, got {}",
+                    tx_in.len(),
+                    spent_outputs.len()
+                )));
+            }
+            let input = input_index
+                .map(|index| {
+                    tx_in
+                        .get(index)
+                        .map(|input| (input, &spent_outputs[index]))
+                        .ok_or_else(|| no_input(index))
+                })
+                .transpose()?;
+
+            let anyone_can_pay = hash_type.is_anyone_can_pay();
+            let (own_inputs, own_spent_outputs) = if anyone_can_pay {
+                (&[][..], &[][..])
+            } else {
+                (tx_in, spent_outputs)
+            };
+
+            let mut amounts = blake2b_256(b"ZTxTrAmountsHash");
+            let mut scripts = blake2b_256(b"ZTxTrScriptsHash");
+            for output in own_spent_outputs {
+                amounts.update(&output.value().to_le_bytes());
+                scripts.update(&encoded(&VarInt::new(output.pk_script().len())));
+                scripts.update(output.pk_script());
+            }
+
+            let signed_outputs = match (hash_type.outputs(), input_index) {
+                (0x01, _) => tx_out,
+                (0x03, Some(index)) if index < tx_out.len() => &tx_out[index..=index],
+                _ => &[],
+            };
+
+            // Shielded signatures commit to the empty digest of the input being signed.
+            let mut txin = blake2b_256(b"Zcash___TxInHash");
+            if let Some((input, output)) = input {
+                txin.update(&encoded(&input.prev_out_hash()));
+                txin.update(&input.prev_out_index().to_le_bytes());
+                txin.update(&output.value().to_le_bytes());
+                txin.update(&encoded(&VarInt::new(output.pk_script().len())));
+                txin.update(output.pk_script());
+                txin.update(&input.sequence().to_le_bytes());
+            }
+
+            let mut state = blake2b_256(b"ZTxIdTranspaHash");
+            state.update(&[hash_type.to_byte()]);
+            state.update(&prevouts_digest(b"ZTxIdPrevoutHash", own_inputs));
+            state.update(&finalize(amounts));
+            state.update(&finalize(scripts));
+            // Unlike ZIP 143, NONE and SINGLE still commit to the sequence numbers.
+            state.update(&sequences_digest(b"ZTxIdSequencHash", own_inputs));
+            state.update(&outputs_digest(b"ZTxIdOutputsHash", signed_outputs));
+            state.update(&finalize(txin));
+
+            finalize(state)
+        };
+
+        let mut state = blake2b_256(&self.branch_personalization(b"ZcashTxHash_"));
+        state.update(&self.header_digest());
+        state.update(&transparent_digest);
+        state.update(&self.sapling_digest());
+        state.update(&self.orchard_digest());
+
+        Ok(Hash::new(finalize(state)))
+    }
+}
+
+/// The fields of V3 and V4 transactions their signature hashes commit to.
+struct PreV5Fields<'a> {
+    header: u32,
+    group_id: u32,
+    tx_in: &'a [TxIn],
+    tx_out: &'a [TxOut],
+    lock_time: u32,
+    expiry_height: u32,
+    join_split: &'a [JoinSplit],
+    join_split_pub_key: Option<&'a [u8; 32]>,
+    // Only present for V4 transactions.
+    sapling: Option<(&'a [SpendDescriptionV4], &'a [OutputDescriptionV4], i64)>,
+}
+
+impl PreV5Fields<'_> {
+    fn signature_hash(
+        &self,
+        input: Option<SignableInput>,
+        hash_type: HashType,
+        consensus_branch: u32,
+    ) -> io::Result<Hash> {
+        if input.is_none() && hash_type != HashType::ALL {
+            return Err(invalid_input(format!(
+                "shielded signatures must use SIGHASH_ALL, not {hash_type}"
+            )));
+        }
+
+        let anyone_can_pay = hash_type.is_anyone_can_pay();
+        let outputs = hash_type.outputs();
+
+        let hash_prevouts = if anyone_can_pay {
+            [0; 32]
+        } else {
+            prevouts_digest(b"ZcashPrevoutHash", self.tx_in)
+        };
+
+        let hash_sequence = if anyone_can_pay || outputs != HashType::ALL.0 {
+            [0; 32]
+        } else {
+            sequences_digest(b"ZcashSequencHash", self.tx_in)
+        };
+
+        let hash_outputs = match (outputs, input) {
+            (0x01, _) => outputs_digest(b"ZcashOutputsHash", self.tx_out),
+            (0x03, Some(input)) if input.index < self.tx_out.len() => {
+                outputs_digest(b"ZcashOutputsHash", &self.tx_out[input.index..=input.index])
+            }
+            _ => [0; 32],
+        };
+
+        let hash_join_splits = match self.join_split_pub_key {
+            Some(pub_key) if !self.join_split.is_empty() => {
+                let mut state = blake2b_256(b"ZcashJSplitsHash");
+                for join_split in self.join_split {
+                    let mut buffer = Vec::new();
+                    join_split.encode(&mut buffer)?;
+                    state.update(&buffer);
+                }
+                state.update(pub_key);
+
+                finalize(state)
+            }
+            _ => [0; 32],
+        };
+
+        let mut personalization = [0u8; 16];
+        personalization[..12].copy_from_slice(b"ZcashSigHash");
+        personalization[12..].copy_from_slice(&consensus_branch.to_le_bytes());
+
+        let mut state = blake2b_256(&personalization);
+        state.update(&self.header.to_le_bytes());
+        state.update(&self.group_id.to_le_bytes());
+        state.update(&hash_prevouts);
+        state.update(&hash_sequence);
+        state.update(&hash_outputs);
+        state.update(&hash_join_splits);
+
+        if let Some((spends, outputs, _)) = self.sapling {
+            state.update(&shielded_spends_digest(spends));
+            state.update(&shielded_outputs_digest(outputs));
+        }
+
+        state.update(&self.lock_time.to_le_bytes());
+        state.update(&self.expiry_height.to_le_bytes());
+
+        if let Some((_, _, value_balance)) = self.sapling {
+            state.update(&value_balance.to_le_bytes());
+        }
+
+        state.update(&u32::from(hash_type.to_byte()).to_le_bytes());
+
+        if let Some(input) = input {
+            let tx_in = self
+                .tx_in
+                .get(input.index)
+                .ok_or_else(|| no_input(input.index))?;
+
+            state.update(&encoded(&tx_in.prev_out_hash()));
+            state.update(&tx_in.prev_out_index().to_le_bytes());
+            state.update(&encoded(&VarInt::new(input.script_code.len())));
+            state.update(input.script_code);
+            state.update(&input.value.to_le_bytes());
+            state.update(&tx_in.sequence().to_le_bytes());
+        }
+
+        Ok(Hash::new(finalize(state)))
+    }
+}
+
+/// Hashes the Sapling spends of a V4 transaction, without their signatures.
+fn shielded_spends_digest(spends: &[SpendDescriptionV4]) -> [u8; 32] {
+    if spends.is_empty() {
+        return [0; 32];
+    }
+
+    let mut state = blake2b_256(b"ZcashSSpendsHash");
+    for spend in spends {
+        state.update(spend.cv());
+        state.update(spend.anchor());
+        state.update(spend.nullifier());
+        state.update(spend.rk());
+        state.update(spend.zkproof());
+    }
+
+    finalize(state)
+}
+
+/// Hashes the Sapling outputs of a V4 transaction.
+fn shielded_outputs_digest(outputs: &[OutputDescriptionV4]) -> [u8; 32] {
+    if outputs.is_empty() {
+        return [0; 32];
+    }
+
+    let mut state = blake2b_256(b"ZcashSOutputHash");
+    for output in outputs {
+        state.update(&encoded(output));
+    }
+
+    finalize(state)
+}
+
+/// Returns `true` if `tx_in` holds no transparent inputs, or only the null input of a coinbase.
+fn is_coinbase_or_shielded(tx_in: &[TxIn]) -> bool {
+    match tx_in {
+        [] => true,
+        [input] => input.prev_out_hash() == Hash::zeroed() && input.prev_out_index() == u32::MAX,
+        _ => false,
+    }
+}
+
+fn no_input(index: usize) -> io::Error {
+    invalid_input(format!("no transparent input {index}"))
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        protocol::{
+            network::NetworkUpgrade,
+            payload::{
+                block::Block,
+                codec::Codec,
+                tx::{ActionDescription, TxV1, TxV5, NU5_BRANCH_ID},
+            },
+        },
+        vectors::*,
+    };
+
+    const OVERWINTER_BRANCH_ID: u32 = NetworkUpgrade::Overwinter.branch_id();
+    const SAPLING_BRANCH_ID: u32 = NetworkUpgrade::Sapling.branch_id();
+
+    fn input(index: u8) -> TxIn {
+        TxIn::new(Hash::new([index; 32]), 0, Vec::new(), u32::MAX - 1)
+    }
+
+    fn spent_outputs() -> Vec<TxOut> {
+        vec![TxOut::new(5_000, vec![0x51]), TxOut::new(7_000, vec![0x52])]
+    }
+
+    /// Returns the signature hashes of the first input of transactions built from `tx_in` and
+    /// `tx_out`, for each hash type and V4 and V5 transactions.
+    fn signature_hashes(tx_in: Vec<TxIn>, tx_out: Vec<TxOut>) -> Vec<Hash> {
+        let v4: Tx = TxV4::builder()
+            .with_tx_in(tx_in.clone())
+            .with_tx_out(tx_out.clone())
+            .build()
+            .into();
+        let v5: Tx = TxV5::builder()
+            .with_tx_in(tx_in)
+            .with_tx_out(tx_out)
+            .build()
+            .unwrap()
+            .into();
+
+        let mut hashes = Vec::new();
+        for tx in [v4, v5] {
+            for hash_type in [HashType::ALL, HashType::NONE, HashType::SINGLE] {
+                for hash_type in [hash_type, hash_type.anyone_can_pay()] {
+                    hashes.push(
+                        tx.signature_hash(Some(0), hash_type, SAPLING_BRANCH_ID, &spent_outputs())
+                            .unwrap(),
+                    );
+                }
+            }
+        }
+
+        hashes
+    }
+
+    #[test]
+    #[ignore]
+    fn signature_hash_verifies_testnet_signature() {
+        // The first Overwinter block spends a P2PKH output worth 77.21852623 TAZ in its second
+        // transaction, the signature in its script verifies against this hash.
+        let block = Block::decode(&mut Cursor::new(&BLOCK_TESTNET_0_207_500_BYTES[..])).unwrap();
+        let script_code =
+            hex::decode("76a9147e17919bd91d8969d4e982076ed89154d11f529888ac").unwrap();
+        let spent_outputs = [TxOut::new(7_721_852_623, script_code)];
+
+        let hash = block.txs[1]
+            .signature_hash(Some(0), HashType::ALL, OVERWINTER_BRANCH_ID, &spent_outputs)
+            .unwrap();
+        assert_eq!(
+            hex::encode(hash.0),
+            "c73a9fc793931a09fe8326d9a42698ab6a7ddfda036e682ca2d18a2bc8be5966"
+        );
+
+        // Signatures commit to the consensus branch.
+        assert_ne!(
+            block.txs[1]
+                .signature_hash(Some(0), HashType::ALL, SAPLING_BRANCH_ID, &spent_outputs)
+                .unwrap(),
+            hash
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn shielded_signature_hash_of_v5_is_txid() {
+        let block = Block::decode(&mut Cursor::new(&BLOCK_TESTNET_1_599_200_BYTES[..])).unwrap();
+        let tx = &block.txs[0];
+
+        assert_eq!(
+            tx.signature_hash(None, HashType::ALL, NU5_BRANCH_ID, &[])
+                .unwrap(),
+            tx.txid().unwrap()
+        );
+        assert!(tx
+            .signature_hash(None, HashType::NONE, NU5_BRANCH_ID, &[])
+            .is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn shielded_signature_hash_of_v5_commits_to_spent_outputs() {
+        let tx: Tx = TxV5::builder()
+            .with_tx_in(vec![input(0), input(1)])
+            .with_tx_out(spent_outputs())
+            .with_actions_orchard(vec![ActionDescription::zeroed()])
+            .with_auth_sigs_orchard(vec![[0; 64]])
+            .build()
+            .unwrap()
+            .into();
+        let hash = tx
+            .signature_hash(None, HashType::ALL, NU5_BRANCH_ID, &spent_outputs())
+            .unwrap();
+
+        // With transparent inputs, the hash commits to the amounts and scripts being spent
+        // rather than matching the txid, and differs from the signatures of the inputs.
+        assert_ne!(hash, tx.txid().unwrap());
+        assert_ne!(
+            hash,
+            tx.signature_hash(Some(0), HashType::ALL, NU5_BRANCH_ID, &spent_outputs())
+                .unwrap()
+        );
+
+        let mut other_outputs = spent_outputs();
+        other_outputs[1] = TxOut::new(7_001, vec![0x52]);
+        assert_ne!(
+            tx.signature_hash(None, HashType::ALL, NU5_BRANCH_ID, &other_outputs)
+                .unwrap(),
+            hash
+        );
+        other_outputs[1] = TxOut::new(7_000, vec![0x53]);
+        assert_ne!(
+            tx.signature_hash(None, HashType::ALL, NU5_BRANCH_ID, &other_outputs)
+                .unwrap(),
+            hash
+        );
+        assert!(tx
+            .signature_hash(None, HashType::ALL, NU5_BRANCH_ID, &[])
+            .is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn hash_types_commit_to_the_expected_data() {
+        let tx_in = vec![input(0), input(1)];
+        let tx_out = spent_outputs();
+        let hashes = signature_hashes(tx_in.clone(), tx_out.clone());
+
+        // Every hash type signs different data.
+        for (i, hash) in hashes.iter().enumerate() {
+            assert!(!hashes[i + 1..].contains(hash));
+        }
+
+        // Changing the other input only leaves ANYONECANPAY signatures valid.
+        let mut other_input = tx_in.clone();
+        other_input[1] = input(2);
+        let changed = signature_hashes(other_input, tx_out.clone());
+        for (i, (hash, changed)) in hashes.iter().zip(&changed).enumerate() {
+            assert_eq!(hash == changed, i % 2 == 1);
+        }
+
+        // Changing the second output leaves NONE and SINGLE signatures of the first input valid.
+        let mut other_output = tx_out;
+        other_output[1] = TxOut::new(1, Vec::new());
+        let changed = signature_hashes(tx_in, other_output);
+        for (i, (hash, changed)) in hashes.iter().zip(&changed).enumerate() {
+            assert_eq!(hash == changed, i % 6 >= 2);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn invalid_signature_hash_requests_are_rejected() {
+        for byte in [0x00, 0x04, 0x80, 0x84, 0x41] {
+            assert!(HashType::from_byte(byte).is_err());
+        }
+        for byte in [0x01, 0x02, 0x03, 0x81, 0x82, 0x83] {
+            assert_eq!(HashType::from_byte(byte).unwrap().to_byte(), byte);
+        }
+
+        let tx: Tx = TxV4::builder().with_tx_in(vec![input(0)]).build().into();
+        // Missing spent outputs or inputs.
+        assert!(tx
+            .signature_hash(Some(0), HashType::ALL, SAPLING_BRANCH_ID, &[])
+            .is_err());
+        assert!(tx
+            .signature_hash(Some(1), HashType::ALL, SAPLING_BRANCH_ID, &spent_outputs())
+            .is_err());
+
+        let legacy: Tx = TxV1::builder().with_tx_in(vec![input(0)]).build().into();
+        assert_eq!(
+            legacy
+                .signature_hash(Some(0), HashType::ALL, SAPLING_BRANCH_ID, &spent_outputs())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::Unsupported
+        );
+    }
+}
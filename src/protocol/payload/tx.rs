@@ -3,18 +3,33 @@
 use std::{convert::TryInto, io};
 
 use bytes::{Buf, BufMut};
+use serde::{Deserialize, Serialize};
 use sha2::Digest;
 
-use crate::protocol::payload::{
-    codec::Codec,
-    inv::{InvHash, ObjectKind},
-    read_n_bytes, Hash, VarInt,
+use crate::protocol::{
+    network::{ActivationHeights, NetworkUpgrade},
+    payload::{
+        codec::{Codec, DecodeOptions},
+        inv::{InvHash, ObjectKind},
+        read_n_bytes,
+        serde_helpers::as_hex,
+        DecodeContext, Hash, ProtocolError, VarInt,
+    },
 };
 
+/// The version group ID of V3 (Overwinter) transactions.
+pub const OVERWINTER_VERSION_GROUP_ID: u32 = 0x03c4_8270;
+/// The version group ID of V4 (Sapling) transactions.
+pub const SAPLING_VERSION_GROUP_ID: u32 = 0x892f_2085;
+/// The version group ID of V5 (NU5) transactions.
+pub const NU5_VERSION_GROUP_ID: u32 = 0x26a7_270a;
+/// The consensus branch ID of the NU5 network upgrade.
+pub const NU5_BRANCH_ID: u32 = NetworkUpgrade::Nu5.branch_id();
+
 /// A Zcash transaction ([spec](https://zips.z.cash/protocol/canopy.pdf#txnencodingandconsensus)).
 ///
 /// Supports V1-V4, V5 isn't yet stable.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Tx {
     V1(TxV1),
     V2(TxV2),
@@ -24,7 +39,7 @@ pub enum Tx {
 }
 
 impl Tx {
-    /// Calculates the double Sha256 hash for this transaction.
+    /// Calculates the double Sha256 hash for this transaction, which is its ID before V5.
     pub fn double_sha256(&self) -> io::Result<Hash> {
         let mut buffer = Vec::new();
 
@@ -38,9 +53,175 @@ impl Tx {
         Ok(hash)
     }
 
+    /// Returns the transaction ID, the [ZIP 244](https://zips.z.cash/zip-0244) digest for V5
+    /// transactions and the double Sha256 hash otherwise.
+    pub fn txid(&self) -> io::Result<Hash> {
+        match self {
+            Tx::V5(tx) => Ok(tx.txid()),
+            _ => self.double_sha256(),
+        }
+    }
+
+    /// Returns the digest of the authorizing data, see [`TxV5::auth_digest`].
+    ///
+    /// Transactions before V5 have no separate authorizing data commitment, their digest is all
+    /// `0xff` ([ZIP 239](https://zips.z.cash/zip-0239)).
+    pub fn auth_digest(&self) -> Hash {
+        match self {
+            Tx::V5(tx) => tx.auth_digest(),
+            _ => Hash::new([0xff; 32]),
+        }
+    }
+
+    /// Returns the transparent inputs.
+    pub fn tx_in(&self) -> &[TxIn] {
+        match self {
+            Tx::V1(tx) => tx.tx_in(),
+            Tx::V2(tx) => tx.tx_in(),
+            Tx::V3(tx) => tx.tx_in(),
+            Tx::V4(tx) => tx.tx_in(),
+            Tx::V5(tx) => tx.tx_in(),
+        }
+    }
+
+    /// Returns the transparent outputs.
+    pub fn tx_out(&self) -> &[TxOut] {
+        match self {
+            Tx::V1(tx) => tx.tx_out(),
+            Tx::V2(tx) => tx.tx_out(),
+            Tx::V3(tx) => tx.tx_out(),
+            Tx::V4(tx) => tx.tx_out(),
+            Tx::V5(tx) => tx.tx_out(),
+        }
+    }
+
+    /// Checks the transaction's version, version group ID and consensus branch ID are valid
+    /// in a block at `height`, given the [`NetworkUpgrade`] active there. A
+    /// [`Network`](crate::protocol::network::Network) can be passed for its default
+    /// [`ActivationHeights`].
+    ///
+    /// Decoding only checks this when given activation heights (see
+    /// [`DecodeOptions::with_network`]), so that malformed transactions can otherwise be sent to
+    /// nodes.
+    pub fn validate_network_upgrade(
+        &self,
+        heights: impl Into<ActivationHeights>,
+        height: u32,
+    ) -> io::Result<()> {
+        let heights = heights.into();
+        let network = heights.network();
+        let upgrade = heights.current(height);
+
+        let (version, group_id, expected_group_id, valid) = match self {
+            Tx::V1(_) => (1, None, 0, upgrade < NetworkUpgrade::Overwinter),
+            Tx::V2(_) => (2, None, 0, upgrade < NetworkUpgrade::Overwinter),
+            Tx::V3(tx) => (
+                3,
+                Some(tx.group_id),
+                OVERWINTER_VERSION_GROUP_ID,
+                upgrade == NetworkUpgrade::Overwinter,
+            ),
+            Tx::V4(tx) => (
+                4,
+                Some(tx.group_id),
+                SAPLING_VERSION_GROUP_ID,
+                upgrade >= NetworkUpgrade::Sapling,
+            ),
+            Tx::V5(tx) => (
+                5,
+                Some(tx.group_id),
+                NU5_VERSION_GROUP_ID,
+                upgrade >= NetworkUpgrade::Nu5,
+            ),
+        };
+
+        if !valid {
+            return Err(invalid_for_upgrade(format!(
+                "V{version} transactions are invalid under {upgrade} (height {height} on {network})"
+            )));
+        }
+
+        if let Some(group_id) = group_id.filter(|group_id| *group_id != expected_group_id) {
+            return Err(invalid_for_upgrade(format!(
+                "expected version group ID {expected_group_id:#010x}, got {group_id:#010x}"
+            )));
+        }
+
+        if let Tx::V5(tx) = self {
+            if tx.consensus_branch != upgrade.branch_id() {
+                return Err(invalid_for_upgrade(format!(
+                    "expected the {upgrade} consensus branch ID {:#010x}, got {:#010x}",
+                    upgrade.branch_id(),
+                    tx.consensus_branch
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the earliest height the transaction's version, and from V5 on its consensus branch
+    /// ID, claims it can be mined at.
+    fn claimed_height(&self, heights: &ActivationHeights) -> io::Result<u32> {
+        let upgrade = match self {
+            Tx::V1(_) | Tx::V2(_) => NetworkUpgrade::Sprout,
+            Tx::V3(_) => NetworkUpgrade::Overwinter,
+            Tx::V4(_) => NetworkUpgrade::Sapling,
+            Tx::V5(tx) => NetworkUpgrade::from_branch_id(tx.consensus_branch).ok_or_else(|| {
+                invalid_for_upgrade(format!(
+                    "unknown consensus branch ID {:#010x}",
+                    tx.consensus_branch
+                ))
+            })?,
+        };
+
+        heights.activation_height(upgrade).ok_or_else(|| {
+            invalid_for_upgrade(format!(
+                "{upgrade} never activates on {}",
+                heights.network()
+            ))
+        })
+    }
+
     /// Convenience function which creates the [`InvHash`] for this `Tx`.
     pub fn inv_hash(&self) -> InvHash {
-        InvHash::new(ObjectKind::Tx, self.double_sha256().unwrap())
+        InvHash::new(ObjectKind::Tx, self.txid().unwrap())
+    }
+
+    /// Creates the [`ObjectKind::WTx`] [`InvHash`] for this `Tx`, with which V5 transactions are
+    /// announced ([ZIP 239](https://zips.z.cash/zip-0239)).
+    pub fn wtx_inv_hash(&self) -> InvHash {
+        InvHash::wtx(self.txid().unwrap(), self.auth_digest())
+    }
+}
+
+impl From<TxV1> for Tx {
+    fn from(tx: TxV1) -> Self {
+        Self::V1(tx)
+    }
+}
+
+impl From<TxV2> for Tx {
+    fn from(tx: TxV2) -> Self {
+        Self::V2(tx)
+    }
+}
+
+impl From<TxV3> for Tx {
+    fn from(tx: TxV3) -> Self {
+        Self::V3(tx)
+    }
+}
+
+impl From<TxV4> for Tx {
+    fn from(tx: TxV4) -> Self {
+        Self::V4(tx)
+    }
+}
+
+impl From<TxV5> for Tx {
+    fn from(tx: TxV5) -> Self {
+        Self::V5(Box::new(tx))
     }
 }
 
@@ -77,37 +258,43 @@ impl Codec for Tx {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        use std::io::{Error, ErrorKind};
-
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let remaining = bytes.remaining();
         let (version, overwinter) = {
             const LOW_31_BITS: u32 = !(1 << 31);
-            let header = u32::from_le_bytes(read_n_bytes(bytes)?);
+            let header = u32::from_le_bytes(read_n_bytes(bytes).field("header")?);
 
             // Extract transaction version and check if overwinter flag is set.
             (header & LOW_31_BITS, header >> 31 != 0)
         };
 
         let tx = match (version, overwinter) {
-            (1, false) => Self::V1(TxV1::decode(bytes)?),
-            (2, false) => Self::V2(TxV2::decode(bytes)?),
-            (3, true) => Self::V3(TxV3::decode(bytes)?),
-            (4, true) => Self::V4(TxV4::decode(bytes)?),
-            (5, true) => Self::V5(Box::new(TxV5::decode(bytes)?)),
+            (1, false) => Self::V1(TxV1::decode_with(bytes, options)?),
+            (2, false) => Self::V2(TxV2::decode_with(bytes, options)?),
+            (3, true) => Self::V3(TxV3::decode_with(bytes, options)?),
+            (4, true) => Self::V4(TxV4::decode_with(bytes, options)?),
+            (5, true) => Self::V5(Box::new(TxV5::decode_with(bytes, options)?)),
             (version, overwinter) => {
-                return Err(Error::new(
-                    ErrorKind::InvalidData,
-                    format!("Couldn't decode data with version {version} and overwinter {overwinter} into a known transaction version"),
-                ))
+                return Err(ProtocolError::invalid_value(
+                    "a known transaction version",
+                    format!("version {version} with overwinter {overwinter}"),
+                    remaining,
+                )
+                .into())
+                .field("header")
             }
         };
 
+        if let Some(heights) = options.activation_heights() {
+            tx.validate_network_upgrade(heights, tx.claimed_height(&heights)?)?;
+        }
+
         Ok(tx)
     }
 }
 
 /// A V1 transaction.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TxV1 {
     tx_in: Vec<TxIn>,
     tx_out: Vec<TxOut>,
@@ -126,11 +313,11 @@ impl Codec for TxV1 {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let tx_in = Vec::<TxIn>::decode(bytes)?;
-        let tx_out = Vec::<TxOut>::decode(bytes)?;
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let tx_in = Vec::<TxIn>::decode_with(bytes, options).field("tx_in")?;
+        let tx_out = Vec::<TxOut>::decode_with(bytes, options).field("tx_out")?;
 
-        let lock_time = u32::from_le_bytes(read_n_bytes(bytes)?);
+        let lock_time = u32::from_le_bytes(read_n_bytes(bytes).field("lock_time")?);
 
         Ok(Self {
             tx_in,
@@ -140,8 +327,67 @@ impl Codec for TxV1 {
     }
 }
 
+impl TxV1 {
+    /// Returns a builder for a V1 transaction.
+    pub fn builder() -> TxV1Builder {
+        TxV1Builder::default()
+    }
+
+    /// Returns the transparent inputs.
+    pub fn tx_in(&self) -> &[TxIn] {
+        &self.tx_in
+    }
+
+    /// Returns the transparent outputs.
+    pub fn tx_out(&self) -> &[TxOut] {
+        &self.tx_out
+    }
+
+    /// Returns the lock time.
+    pub fn lock_time(&self) -> u32 {
+        self.lock_time
+    }
+}
+
+/// A builder for [`TxV1`].
+#[derive(Debug, Default, Clone)]
+pub struct TxV1Builder {
+    tx_in: Vec<TxIn>,
+    tx_out: Vec<TxOut>,
+    lock_time: u32,
+}
+
+impl TxV1Builder {
+    /// Sets the transparent inputs.
+    pub fn with_tx_in(mut self, tx_in: Vec<TxIn>) -> Self {
+        self.tx_in = tx_in;
+        self
+    }
+
+    /// Sets the transparent outputs.
+    pub fn with_tx_out(mut self, tx_out: Vec<TxOut>) -> Self {
+        self.tx_out = tx_out;
+        self
+    }
+
+    /// Sets the lock time, `0` by default.
+    pub fn with_lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Creates the [`TxV1`].
+    pub fn build(self) -> TxV1 {
+        TxV1 {
+            tx_in: self.tx_in,
+            tx_out: self.tx_out,
+            lock_time: self.lock_time,
+        }
+    }
+}
+
 /// A V2 transaction.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TxV2 {
     tx_in: Vec<TxIn>,
     tx_out: Vec<TxOut>,
@@ -152,8 +398,10 @@ pub struct TxV2 {
     join_split: Vec<JoinSplit>,
 
     // Only present if the join_split count > 0.
+    #[serde(with = "as_hex")]
     join_split_pub_key: Option<[u8; 32]>,
-    join_split_sig: Option<[u8; 32]>,
+    #[serde(with = "as_hex")]
+    join_split_sig: Option<[u8; 64]>,
 }
 
 impl Codec for TxV2 {
@@ -178,29 +426,24 @@ impl Codec for TxV2 {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let tx_in = Vec::<TxIn>::decode(bytes)?;
-        let tx_out = Vec::<TxOut>::decode(bytes)?;
-        let lock_time = u32::from_le_bytes(read_n_bytes(bytes)?);
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let tx_in = Vec::<TxIn>::decode_with(bytes, options).field("tx_in")?;
+        let tx_out = Vec::<TxOut>::decode_with(bytes, options).field("tx_out")?;
+        let lock_time = u32::from_le_bytes(read_n_bytes(bytes).field("lock_time")?);
 
-        let join_split_count = *VarInt::decode(bytes)?;
+        let join_split_count = *VarInt::decode_with(bytes, options).field("join_split")?;
         let mut join_split = Vec::new();
 
-        for _ in 0..join_split_count {
-            let description = JoinSplit::decode_bctv14(bytes)?;
+        for i in 0..join_split_count {
+            let description = JoinSplit::decode_bctv14(bytes)
+                .index(i)
+                .field("join_split")?;
             join_split.push(description);
         }
 
         let (join_split_pub_key, join_split_sig) = if join_split_count > 0 {
-            if bytes.remaining() < 64 {
-                return Err(io::ErrorKind::InvalidData.into());
-            }
-
-            let mut pub_key = [0u8; 32];
-            bytes.copy_to_slice(&mut pub_key);
-
-            let mut sig = [0u8; 32];
-            bytes.copy_to_slice(&mut sig);
+            let pub_key = read_n_bytes(bytes).field("join_split_pub_key")?;
+            let sig = read_n_bytes(bytes).field("join_split_sig")?;
 
             (Some(pub_key), Some(sig))
         } else {
@@ -218,8 +461,121 @@ impl Codec for TxV2 {
     }
 }
 
+impl TxV2 {
+    /// Returns a builder for a V2 transaction.
+    pub fn builder() -> TxV2Builder {
+        TxV2Builder::default()
+    }
+
+    /// Returns the transparent inputs.
+    pub fn tx_in(&self) -> &[TxIn] {
+        &self.tx_in
+    }
+
+    /// Returns the transparent outputs.
+    pub fn tx_out(&self) -> &[TxOut] {
+        &self.tx_out
+    }
+
+    /// Returns the lock time.
+    pub fn lock_time(&self) -> u32 {
+        self.lock_time
+    }
+
+    /// Returns the Sprout join split descriptions.
+    pub fn join_split(&self) -> &[JoinSplit] {
+        &self.join_split
+    }
+
+    /// Returns the join split public key, present if there are join splits.
+    pub fn join_split_pub_key(&self) -> Option<&[u8; 32]> {
+        self.join_split_pub_key.as_ref()
+    }
+
+    /// Returns the join split signature, present if there are join splits.
+    pub fn join_split_sig(&self) -> Option<&[u8; 64]> {
+        self.join_split_sig.as_ref()
+    }
+}
+
+/// A builder for [`TxV2`].
+#[derive(Debug, Clone)]
+pub struct TxV2Builder {
+    tx_in: Vec<TxIn>,
+    tx_out: Vec<TxOut>,
+    lock_time: u32,
+    join_split: Vec<JoinSplit>,
+    join_split_pub_key: [u8; 32],
+    join_split_sig: [u8; 64],
+}
+
+impl Default for TxV2Builder {
+    fn default() -> Self {
+        Self {
+            tx_in: Vec::new(),
+            tx_out: Vec::new(),
+            lock_time: 0,
+            join_split: Vec::new(),
+            join_split_pub_key: [0; 32],
+            join_split_sig: [0; 64],
+        }
+    }
+}
+
+impl TxV2Builder {
+    /// Sets the transparent inputs.
+    pub fn with_tx_in(mut self, tx_in: Vec<TxIn>) -> Self {
+        self.tx_in = tx_in;
+        self
+    }
+
+    /// Sets the transparent outputs.
+    pub fn with_tx_out(mut self, tx_out: Vec<TxOut>) -> Self {
+        self.tx_out = tx_out;
+        self
+    }
+
+    /// Sets the lock time, `0` by default.
+    pub fn with_lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Sets the join split descriptions, which must carry [`Zkproof::BCTV14`] proofs.
+    pub fn with_join_split(mut self, join_split: Vec<JoinSplit>) -> Self {
+        self.join_split = join_split;
+        self
+    }
+
+    /// Sets the join split public key, zeroed by default. Only included with join splits.
+    pub fn with_join_split_pub_key(mut self, join_split_pub_key: [u8; 32]) -> Self {
+        self.join_split_pub_key = join_split_pub_key;
+        self
+    }
+
+    /// Sets the join split signature, zeroed by default. Only included with join splits.
+    pub fn with_join_split_sig(mut self, join_split_sig: [u8; 64]) -> Self {
+        self.join_split_sig = join_split_sig;
+        self
+    }
+
+    /// Creates the [`TxV2`].
+    pub fn build(self) -> TxV2 {
+        let has_join_split = !self.join_split.is_empty();
+
+        TxV2 {
+            tx_in: self.tx_in,
+            tx_out: self.tx_out,
+            lock_time: self.lock_time,
+            join_split: self.join_split,
+            join_split_pub_key: has_join_split.then_some(self.join_split_pub_key),
+            join_split_sig: has_join_split.then_some(self.join_split_sig),
+        }
+    }
+}
+
 /// A V3 transaction.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TxV3 {
     group_id: u32,
 
@@ -233,8 +589,10 @@ pub struct TxV3 {
     join_split: Vec<JoinSplit>,
 
     // Only present if the join_split count > 0.
+    #[serde(with = "as_hex")]
     join_split_pub_key: Option<[u8; 32]>,
-    join_split_sig: Option<[u8; 32]>,
+    #[serde(with = "as_hex")]
+    join_split_sig: Option<[u8; 64]>,
 }
 
 impl Codec for TxV3 {
@@ -262,32 +620,27 @@ impl Codec for TxV3 {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let group_id = u32::from_le_bytes(read_n_bytes(bytes)?);
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let group_id = u32::from_le_bytes(read_n_bytes(bytes).field("group_id")?);
 
-        let tx_in = Vec::<TxIn>::decode(bytes)?;
-        let tx_out = Vec::<TxOut>::decode(bytes)?;
-        let lock_time = u32::from_le_bytes(read_n_bytes(bytes)?);
-        let expiry_height = u32::from_le_bytes(read_n_bytes(bytes)?);
+        let tx_in = Vec::<TxIn>::decode_with(bytes, options).field("tx_in")?;
+        let tx_out = Vec::<TxOut>::decode_with(bytes, options).field("tx_out")?;
+        let lock_time = u32::from_le_bytes(read_n_bytes(bytes).field("lock_time")?);
+        let expiry_height = u32::from_le_bytes(read_n_bytes(bytes).field("expiry_height")?);
 
-        let join_split_count = *VarInt::decode(bytes)?;
+        let join_split_count = *VarInt::decode_with(bytes, options).field("join_split")?;
         let mut join_split = Vec::new();
 
-        for _ in 0..join_split_count {
-            let description = JoinSplit::decode_bctv14(bytes)?;
+        for i in 0..join_split_count {
+            let description = JoinSplit::decode_bctv14(bytes)
+                .index(i)
+                .field("join_split")?;
             join_split.push(description);
         }
 
         let (join_split_pub_key, join_split_sig) = if join_split_count > 0 {
-            if bytes.remaining() < 64 {
-                return Err(io::ErrorKind::InvalidData.into());
-            }
-
-            let mut pub_key = [0u8; 32];
-            bytes.copy_to_slice(&mut pub_key);
-
-            let mut sig = [0u8; 32];
-            bytes.copy_to_slice(&mut sig);
+            let pub_key = read_n_bytes(bytes).field("join_split_pub_key")?;
+            let sig = read_n_bytes(bytes).field("join_split_sig")?;
 
             (Some(pub_key), Some(sig))
         } else {
@@ -307,8 +660,149 @@ impl Codec for TxV3 {
     }
 }
 
+impl TxV3 {
+    /// Returns a builder for a V3 transaction.
+    pub fn builder() -> TxV3Builder {
+        TxV3Builder::default()
+    }
+
+    /// Returns the version group ID.
+    pub fn group_id(&self) -> u32 {
+        self.group_id
+    }
+
+    /// Returns the transparent inputs.
+    pub fn tx_in(&self) -> &[TxIn] {
+        &self.tx_in
+    }
+
+    /// Returns the transparent outputs.
+    pub fn tx_out(&self) -> &[TxOut] {
+        &self.tx_out
+    }
+
+    /// Returns the lock time.
+    pub fn lock_time(&self) -> u32 {
+        self.lock_time
+    }
+
+    /// Returns the height after which the transaction expires.
+    pub fn expiry_height(&self) -> u32 {
+        self.expiry_height
+    }
+
+    /// Returns the Sprout join split descriptions.
+    pub fn join_split(&self) -> &[JoinSplit] {
+        &self.join_split
+    }
+
+    /// Returns the join split public key, present if there are join splits.
+    pub fn join_split_pub_key(&self) -> Option<&[u8; 32]> {
+        self.join_split_pub_key.as_ref()
+    }
+
+    /// Returns the join split signature, present if there are join splits.
+    pub fn join_split_sig(&self) -> Option<&[u8; 64]> {
+        self.join_split_sig.as_ref()
+    }
+}
+
+/// A builder for [`TxV3`].
+#[derive(Debug, Clone)]
+pub struct TxV3Builder {
+    group_id: u32,
+    tx_in: Vec<TxIn>,
+    tx_out: Vec<TxOut>,
+    lock_time: u32,
+    expiry_height: u32,
+    join_split: Vec<JoinSplit>,
+    join_split_pub_key: [u8; 32],
+    join_split_sig: [u8; 64],
+}
+
+impl Default for TxV3Builder {
+    fn default() -> Self {
+        Self {
+            group_id: OVERWINTER_VERSION_GROUP_ID,
+            tx_in: Vec::new(),
+            tx_out: Vec::new(),
+            lock_time: 0,
+            expiry_height: 0,
+            join_split: Vec::new(),
+            join_split_pub_key: [0; 32],
+            join_split_sig: [0; 64],
+        }
+    }
+}
+
+impl TxV3Builder {
+    /// Sets the version group ID, [`OVERWINTER_VERSION_GROUP_ID`] by default.
+    pub fn with_group_id(mut self, group_id: u32) -> Self {
+        self.group_id = group_id;
+        self
+    }
+
+    /// Sets the transparent inputs.
+    pub fn with_tx_in(mut self, tx_in: Vec<TxIn>) -> Self {
+        self.tx_in = tx_in;
+        self
+    }
+
+    /// Sets the transparent outputs.
+    pub fn with_tx_out(mut self, tx_out: Vec<TxOut>) -> Self {
+        self.tx_out = tx_out;
+        self
+    }
+
+    /// Sets the lock time, `0` by default.
+    pub fn with_lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Sets the expiry height, `0` (no expiry) by default.
+    pub fn with_expiry_height(mut self, expiry_height: u32) -> Self {
+        self.expiry_height = expiry_height;
+        self
+    }
+
+    /// Sets the join split descriptions, which must carry [`Zkproof::BCTV14`] proofs.
+    pub fn with_join_split(mut self, join_split: Vec<JoinSplit>) -> Self {
+        self.join_split = join_split;
+        self
+    }
+
+    /// Sets the join split public key, zeroed by default. Only included with join splits.
+    pub fn with_join_split_pub_key(mut self, join_split_pub_key: [u8; 32]) -> Self {
+        self.join_split_pub_key = join_split_pub_key;
+        self
+    }
+
+    /// Sets the join split signature, zeroed by default. Only included with join splits.
+    pub fn with_join_split_sig(mut self, join_split_sig: [u8; 64]) -> Self {
+        self.join_split_sig = join_split_sig;
+        self
+    }
+
+    /// Creates the [`TxV3`].
+    pub fn build(self) -> TxV3 {
+        let has_join_split = !self.join_split.is_empty();
+
+        TxV3 {
+            group_id: self.group_id,
+            tx_in: self.tx_in,
+            tx_out: self.tx_out,
+            lock_time: self.lock_time,
+            expiry_height: self.expiry_height,
+            join_split: self.join_split,
+            join_split_pub_key: has_join_split.then_some(self.join_split_pub_key),
+            join_split_sig: has_join_split.then_some(self.join_split_sig),
+        }
+    }
+}
+
 /// A V4 transaction.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TxV4 {
     group_id: u32,
 
@@ -326,10 +820,13 @@ pub struct TxV4 {
     join_split: Vec<JoinSplit>,
 
     // Only present if the join_split count > 0.
+    #[serde(with = "as_hex")]
     join_split_pub_key: Option<[u8; 32]>,
-    join_split_sig: Option<[u8; 32]>,
+    #[serde(with = "as_hex")]
+    join_split_sig: Option<[u8; 64]>,
 
     // Present if and only if spends_sapling_count + outputs_sapling_count > 0.
+    #[serde(with = "as_hex")]
     binding_sig_sapling: Option<[u8; 64]>,
 }
 
@@ -367,36 +864,34 @@ impl Codec for TxV4 {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let group_id = u32::from_le_bytes(read_n_bytes(bytes)?);
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let group_id = u32::from_le_bytes(read_n_bytes(bytes).field("group_id")?);
 
-        let tx_in = Vec::<TxIn>::decode(bytes)?;
-        let tx_out = Vec::<TxOut>::decode(bytes)?;
-        let lock_time = u32::from_le_bytes(read_n_bytes(bytes)?);
-        let expiry_height = u32::from_le_bytes(read_n_bytes(bytes)?);
+        let tx_in = Vec::<TxIn>::decode_with(bytes, options).field("tx_in")?;
+        let tx_out = Vec::<TxOut>::decode_with(bytes, options).field("tx_out")?;
+        let lock_time = u32::from_le_bytes(read_n_bytes(bytes).field("lock_time")?);
+        let expiry_height = u32::from_le_bytes(read_n_bytes(bytes).field("expiry_height")?);
 
-        let value_balance_sapling = i64::from_le_bytes(read_n_bytes(bytes)?);
-        let spends_sapling = Vec::<SpendDescriptionV4>::decode(bytes)?;
-        let outputs_sapling = Vec::<OutputDescriptionV4>::decode(bytes)?;
+        let value_balance_sapling =
+            i64::from_le_bytes(read_n_bytes(bytes).field("value_balance_sapling")?);
+        let spends_sapling =
+            Vec::<SpendDescriptionV4>::decode_with(bytes, options).field("spends_sapling")?;
+        let outputs_sapling =
+            Vec::<OutputDescriptionV4>::decode_with(bytes, options).field("outputs_sapling")?;
 
-        let join_split_count = VarInt::decode(bytes)?;
+        let join_split_count = VarInt::decode_with(bytes, options).field("join_split")?;
         let mut join_split = Vec::new();
 
-        for _ in 0..*join_split_count {
-            let description = JoinSplit::decode_groth16(bytes)?;
+        for i in 0..*join_split_count {
+            let description = JoinSplit::decode_groth16(bytes)
+                .index(i)
+                .field("join_split")?;
             join_split.push(description);
         }
 
         let (join_split_pub_key, join_split_sig) = if *join_split_count > 0 {
-            if bytes.remaining() < 64 {
-                return Err(io::ErrorKind::InvalidData.into());
-            }
-
-            let mut pub_key = [0u8; 32];
-            bytes.copy_to_slice(&mut pub_key);
-
-            let mut sig = [0u8; 32];
-            bytes.copy_to_slice(&mut sig);
+            let pub_key = read_n_bytes(bytes).field("join_split_pub_key")?;
+            let sig = read_n_bytes(bytes).field("join_split_sig")?;
 
             (Some(pub_key), Some(sig))
         } else {
@@ -404,7 +899,7 @@ impl Codec for TxV4 {
         };
 
         let binding_sig_sapling = if !spends_sapling.is_empty() || !outputs_sapling.is_empty() {
-            Some(read_n_bytes(bytes)?)
+            Some(read_n_bytes(bytes).field("binding_sig_sapling")?)
         } else {
             None
         };
@@ -426,75 +921,283 @@ impl Codec for TxV4 {
     }
 }
 
-/// A V5 transaction.
-#[derive(Debug, PartialEq, Clone)]
-pub struct TxV5 {
-    group_id: u32,
-    consensus_branch: u32,
-    lock_time: u32,
-    expiry_height: u32,
-
-    tx_in: Vec<TxIn>,
-    tx_out: Vec<TxOut>,
-
-    spends_sapling: Vec<SpendDescriptionV5>,
-    outputs_sapling: Vec<OutputDescriptionV5>,
+impl TxV4 {
+    /// Returns a builder for a V4 transaction.
+    pub fn builder() -> TxV4Builder {
+        TxV4Builder::default()
+    }
 
-    value_balance_sapling: Option<i64>,
-    anchor_sapling: Option<[u8; 32]>,
+    /// Returns the version group ID.
+    pub fn group_id(&self) -> u32 {
+        self.group_id
+    }
 
-    spend_proofs_sapling: Vec<[u8; 192]>,
-    spend_auth_sigs_sapling: Vec<[u8; 64]>,
-    output_proofs_sapling: Vec<[u8; 192]>,
-    binding_sig_sapling: Option<[u8; 64]>,
+    /// Returns the transparent inputs.
+    pub fn tx_in(&self) -> &[TxIn] {
+        &self.tx_in
+    }
 
-    actions_orchard: Vec<ActionDescription>,
-    flags_orchard: Option<u8>,
-    value_balance_orchard: Option<i64>,
-    anchor_orchard: Option<[u8; 32]>,
+    /// Returns the transparent outputs.
+    pub fn tx_out(&self) -> &[TxOut] {
+        &self.tx_out
+    }
 
-    proofs_orchard: Option<Vec<u8>>,
-    auth_sigs_orchard: Option<Vec<[u8; 64]>>,
-    binding_sig_orchard: Option<[u8; 64]>,
-}
+    /// Returns the lock time.
+    pub fn lock_time(&self) -> u32 {
+        self.lock_time
+    }
 
-impl Codec for TxV5 {
-    fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
-        buffer.put_u32_le(self.group_id);
-        buffer.put_u32_le(self.consensus_branch);
-        buffer.put_u32_le(self.lock_time);
-        buffer.put_u32_le(self.expiry_height);
+    /// Returns the height after which the transaction expires.
+    pub fn expiry_height(&self) -> u32 {
+        self.expiry_height
+    }
 
-        self.tx_in.encode(buffer)?;
-        self.tx_out.encode(buffer)?;
+    /// Returns the net value of the Sapling spends minus outputs.
+    pub fn value_balance_sapling(&self) -> i64 {
+        self.value_balance_sapling
+    }
 
-        self.spends_sapling.encode(buffer)?;
-        self.outputs_sapling.encode(buffer)?;
+    /// Returns the Sapling spend descriptions.
+    pub fn spends_sapling(&self) -> &[SpendDescriptionV4] {
+        &self.spends_sapling
+    }
 
-        if self.spends_sapling.len() + self.outputs_sapling.len() > 0 {
-            // Must be present.
-            buffer.put_i64_le(self.value_balance_sapling.unwrap());
-        }
+    /// Returns the Sapling output descriptions.
+    pub fn outputs_sapling(&self) -> &[OutputDescriptionV4] {
+        &self.outputs_sapling
+    }
 
-        if !self.spends_sapling.is_empty() {
-            // Must be present.
-            buffer.put_slice(&self.anchor_sapling.unwrap());
-        }
+    /// Returns the Sprout join split descriptions.
+    pub fn join_split(&self) -> &[JoinSplit] {
+        &self.join_split
+    }
 
-        // Manually encode the contents of the `Vec` as it doesn't need the lenght prepended.
-        for proof in &self.spend_proofs_sapling {
-            buffer.put_slice(proof);
-        }
+    /// Returns the join split public key, present if there are join splits.
+    pub fn join_split_pub_key(&self) -> Option<&[u8; 32]> {
+        self.join_split_pub_key.as_ref()
+    }
 
-        for auth_sig in &self.spend_auth_sigs_sapling {
-            buffer.put_slice(auth_sig);
-        }
+    /// Returns the join split signature, present if there are join splits.
+    pub fn join_split_sig(&self) -> Option<&[u8; 64]> {
+        self.join_split_sig.as_ref()
+    }
 
-        for proof in &self.output_proofs_sapling {
-            buffer.put_slice(proof);
-        }
+    /// Returns the Sapling binding signature, present if there are Sapling spends or outputs.
+    pub fn binding_sig_sapling(&self) -> Option<&[u8; 64]> {
+        self.binding_sig_sapling.as_ref()
+    }
+}
 
-        if self.spends_sapling.len() + self.outputs_sapling.len() > 0 {
+/// A builder for [`TxV4`].
+#[derive(Debug, Clone)]
+pub struct TxV4Builder {
+    group_id: u32,
+    tx_in: Vec<TxIn>,
+    tx_out: Vec<TxOut>,
+    lock_time: u32,
+    expiry_height: u32,
+    value_balance_sapling: i64,
+    spends_sapling: Vec<SpendDescriptionV4>,
+    outputs_sapling: Vec<OutputDescriptionV4>,
+    join_split: Vec<JoinSplit>,
+    join_split_pub_key: [u8; 32],
+    join_split_sig: [u8; 64],
+    binding_sig_sapling: [u8; 64],
+}
+
+impl Default for TxV4Builder {
+    fn default() -> Self {
+        Self {
+            group_id: SAPLING_VERSION_GROUP_ID,
+            tx_in: Vec::new(),
+            tx_out: Vec::new(),
+            lock_time: 0,
+            expiry_height: 0,
+            value_balance_sapling: 0,
+            spends_sapling: Vec::new(),
+            outputs_sapling: Vec::new(),
+            join_split: Vec::new(),
+            join_split_pub_key: [0; 32],
+            join_split_sig: [0; 64],
+            binding_sig_sapling: [0; 64],
+        }
+    }
+}
+
+impl TxV4Builder {
+    /// Sets the version group ID, [`SAPLING_VERSION_GROUP_ID`] by default.
+    pub fn with_group_id(mut self, group_id: u32) -> Self {
+        self.group_id = group_id;
+        self
+    }
+
+    /// Sets the transparent inputs.
+    pub fn with_tx_in(mut self, tx_in: Vec<TxIn>) -> Self {
+        self.tx_in = tx_in;
+        self
+    }
+
+    /// Sets the transparent outputs.
+    pub fn with_tx_out(mut self, tx_out: Vec<TxOut>) -> Self {
+        self.tx_out = tx_out;
+        self
+    }
+
+    /// Sets the lock time, `0` by default.
+    pub fn with_lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Sets the expiry height, `0` (no expiry) by default.
+    pub fn with_expiry_height(mut self, expiry_height: u32) -> Self {
+        self.expiry_height = expiry_height;
+        self
+    }
+
+    /// Sets the net value of the Sapling spends minus outputs, `0` by default.
+    pub fn with_value_balance_sapling(mut self, value_balance_sapling: i64) -> Self {
+        self.value_balance_sapling = value_balance_sapling;
+        self
+    }
+
+    /// Sets the Sapling spend descriptions.
+    pub fn with_spends_sapling(mut self, spends_sapling: Vec<SpendDescriptionV4>) -> Self {
+        self.spends_sapling = spends_sapling;
+        self
+    }
+
+    /// Sets the Sapling output descriptions.
+    pub fn with_outputs_sapling(mut self, outputs_sapling: Vec<OutputDescriptionV4>) -> Self {
+        self.outputs_sapling = outputs_sapling;
+        self
+    }
+
+    /// Sets the join split descriptions, which must carry [`Zkproof::Groth16`] proofs.
+    pub fn with_join_split(mut self, join_split: Vec<JoinSplit>) -> Self {
+        self.join_split = join_split;
+        self
+    }
+
+    /// Sets the join split public key, zeroed by default. Only included with join splits.
+    pub fn with_join_split_pub_key(mut self, join_split_pub_key: [u8; 32]) -> Self {
+        self.join_split_pub_key = join_split_pub_key;
+        self
+    }
+
+    /// Sets the join split signature, zeroed by default. Only included with join splits.
+    pub fn with_join_split_sig(mut self, join_split_sig: [u8; 64]) -> Self {
+        self.join_split_sig = join_split_sig;
+        self
+    }
+
+    /// Sets the Sapling binding signature, zeroed by default. Only included with Sapling spends
+    /// or outputs.
+    pub fn with_binding_sig_sapling(mut self, binding_sig_sapling: [u8; 64]) -> Self {
+        self.binding_sig_sapling = binding_sig_sapling;
+        self
+    }
+
+    /// Creates the [`TxV4`].
+    pub fn build(self) -> TxV4 {
+        let has_join_split = !self.join_split.is_empty();
+        let has_sapling = !self.spends_sapling.is_empty() || !self.outputs_sapling.is_empty();
+
+        TxV4 {
+            group_id: self.group_id,
+            tx_in: self.tx_in,
+            tx_out: self.tx_out,
+            lock_time: self.lock_time,
+            expiry_height: self.expiry_height,
+            value_balance_sapling: self.value_balance_sapling,
+            spends_sapling: self.spends_sapling,
+            outputs_sapling: self.outputs_sapling,
+            join_split: self.join_split,
+            join_split_pub_key: has_join_split.then_some(self.join_split_pub_key),
+            join_split_sig: has_join_split.then_some(self.join_split_sig),
+            binding_sig_sapling: has_sapling.then_some(self.binding_sig_sapling),
+        }
+    }
+}
+
+/// A V5 transaction.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TxV5 {
+    group_id: u32,
+    consensus_branch: u32,
+    lock_time: u32,
+    expiry_height: u32,
+
+    tx_in: Vec<TxIn>,
+    tx_out: Vec<TxOut>,
+
+    spends_sapling: Vec<SpendDescriptionV5>,
+    outputs_sapling: Vec<OutputDescriptionV5>,
+
+    value_balance_sapling: Option<i64>,
+    #[serde(with = "as_hex")]
+    anchor_sapling: Option<[u8; 32]>,
+
+    #[serde(with = "as_hex")]
+    spend_proofs_sapling: Vec<[u8; 192]>,
+    #[serde(with = "as_hex")]
+    spend_auth_sigs_sapling: Vec<[u8; 64]>,
+    #[serde(with = "as_hex")]
+    output_proofs_sapling: Vec<[u8; 192]>,
+    #[serde(with = "as_hex")]
+    binding_sig_sapling: Option<[u8; 64]>,
+
+    actions_orchard: Vec<ActionDescription>,
+    flags_orchard: Option<u8>,
+    value_balance_orchard: Option<i64>,
+    #[serde(with = "as_hex")]
+    anchor_orchard: Option<[u8; 32]>,
+
+    #[serde(with = "as_hex")]
+    proofs_orchard: Option<Vec<u8>>,
+    #[serde(with = "as_hex")]
+    auth_sigs_orchard: Option<Vec<[u8; 64]>>,
+    #[serde(with = "as_hex")]
+    binding_sig_orchard: Option<[u8; 64]>,
+}
+
+impl Codec for TxV5 {
+    fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
+        buffer.put_u32_le(self.group_id);
+        buffer.put_u32_le(self.consensus_branch);
+        buffer.put_u32_le(self.lock_time);
+        buffer.put_u32_le(self.expiry_height);
+
+        self.tx_in.encode(buffer)?;
+        self.tx_out.encode(buffer)?;
+
+        self.spends_sapling.encode(buffer)?;
+        self.outputs_sapling.encode(buffer)?;
+
+        if self.spends_sapling.len() + self.outputs_sapling.len() > 0 {
+            // Must be present.
+            buffer.put_i64_le(self.value_balance_sapling.unwrap());
+        }
+
+        if !self.spends_sapling.is_empty() {
+            // Must be present.
+            buffer.put_slice(&self.anchor_sapling.unwrap());
+        }
+
+        // Manually encode the contents of the `Vec` as it doesn't need the lenght prepended.
+        for proof in &self.spend_proofs_sapling {
+            buffer.put_slice(proof);
+        }
+
+        for auth_sig in &self.spend_auth_sigs_sapling {
+            buffer.put_slice(auth_sig);
+        }
+
+        for proof in &self.output_proofs_sapling {
+            buffer.put_slice(proof);
+        }
+
+        if self.spends_sapling.len() + self.outputs_sapling.len() > 0 {
             // Must be present.
             buffer.put_slice(&self.binding_sig_sapling.unwrap());
         }
@@ -519,9 +1222,9 @@ impl Codec for TxV5 {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
         if bytes.remaining() < 16 {
-            return Err(io::ErrorKind::InvalidData.into());
+            return Err(ProtocolError::unexpected_eof(16, bytes.remaining()).into());
         }
 
         let group_id = bytes.get_u32_le();
@@ -529,15 +1232,18 @@ impl Codec for TxV5 {
         let lock_time = bytes.get_u32_le();
         let expiry_height = bytes.get_u32_le();
 
-        let tx_in = Vec::<TxIn>::decode(bytes)?;
-        let tx_out = Vec::<TxOut>::decode(bytes)?;
+        let tx_in = Vec::<TxIn>::decode_with(bytes, options).field("tx_in")?;
+        let tx_out = Vec::<TxOut>::decode_with(bytes, options).field("tx_out")?;
 
-        let spends_sapling = Vec::<SpendDescriptionV5>::decode(bytes)?;
-        let outputs_sapling = Vec::<OutputDescriptionV5>::decode(bytes)?;
+        let spends_sapling =
+            Vec::<SpendDescriptionV5>::decode_with(bytes, options).field("spends_sapling")?;
+        let outputs_sapling =
+            Vec::<OutputDescriptionV5>::decode_with(bytes, options).field("outputs_sapling")?;
 
         let value_balance_sapling = if spends_sapling.len() + outputs_sapling.len() > 0 {
             if bytes.remaining() < 8 {
-                return Err(io::ErrorKind::InvalidData.into());
+                return Err(ProtocolError::unexpected_eof(8, bytes.remaining()).into())
+                    .field("value_balance_sapling");
             }
 
             Some(bytes.get_i64_le())
@@ -546,36 +1252,45 @@ impl Codec for TxV5 {
         };
 
         let anchor_sapling = if !spends_sapling.is_empty() {
-            Some(read_n_bytes(bytes)?)
+            Some(read_n_bytes(bytes).field("anchor_sapling")?)
         } else {
             None
         };
 
         // Decode spend proofs sapling.
         let mut spend_proofs_sapling = Vec::new();
-        for _ in 0..spends_sapling.len() {
-            spend_proofs_sapling.push(read_n_bytes(bytes)?);
+        for i in 0..spends_sapling.len() {
+            spend_proofs_sapling.push(read_n_bytes(bytes).index(i).field("spend_proofs_sapling")?);
         }
 
         // Decode spend auth sigs.
         let mut spend_auth_sigs_sapling = Vec::new();
-        for _ in 0..spends_sapling.len() {
-            spend_auth_sigs_sapling.push(read_n_bytes(bytes)?);
+        for i in 0..spends_sapling.len() {
+            spend_auth_sigs_sapling.push(
+                read_n_bytes(bytes)
+                    .index(i)
+                    .field("spend_auth_sigs_sapling")?,
+            );
         }
 
         // Decode output proofs.
         let mut output_proofs_sapling = Vec::new();
-        for _ in 0..spends_sapling.len() {
-            output_proofs_sapling.push(read_n_bytes(bytes)?);
+        for i in 0..outputs_sapling.len() {
+            output_proofs_sapling.push(
+                read_n_bytes(bytes)
+                    .index(i)
+                    .field("output_proofs_sapling")?,
+            );
         }
 
         let binding_sig_sapling = if spends_sapling.len() + outputs_sapling.len() > 0 {
-            Some(read_n_bytes(bytes)?)
+            Some(read_n_bytes(bytes).field("binding_sig_sapling")?)
         } else {
             None
         };
 
-        let actions_orchard = Vec::<ActionDescription>::decode(bytes)?;
+        let actions_orchard =
+            Vec::<ActionDescription>::decode_with(bytes, options).field("actions_orchard")?;
 
         let (
             flags_orchard,
@@ -587,24 +1302,28 @@ impl Codec for TxV5 {
         ) = if !actions_orchard.is_empty() {
             // Decode the orchard flags.
             if bytes.remaining() == 0 {
-                return Err(io::ErrorKind::InvalidData.into());
+                return Err(ProtocolError::unexpected_eof(1, 0).into()).field("flags_orchard");
             }
 
             let flags_orchard = bytes.get_u8();
 
             // Decode the value balance.
             if bytes.remaining() < 8 {
-                return Err(io::ErrorKind::InvalidData.into());
+                return Err(ProtocolError::unexpected_eof(8, bytes.remaining()).into())
+                    .field("value_balance_orchard");
             }
 
             let value_balance_orchard = bytes.get_i64_le();
-            let anchor_orchard = read_n_bytes(bytes)?;
+            let anchor_orchard = read_n_bytes(bytes).field("anchor_orchard")?;
 
             // Decode the orchard proofs.
-            let n_proofs_orchard = VarInt::decode(bytes)?;
+            let n_proofs_orchard = VarInt::decode_with(bytes, options).field("proofs_orchard")?;
 
             if bytes.remaining() < *n_proofs_orchard {
-                return Err(io::ErrorKind::InvalidData.into());
+                return Err(
+                    ProtocolError::unexpected_eof(*n_proofs_orchard, bytes.remaining()).into(),
+                )
+                .field("proofs_orchard");
             }
 
             let mut proofs_orchard = Vec::new();
@@ -614,11 +1333,11 @@ impl Codec for TxV5 {
 
             // Decode orchard auth sigs.
             let mut auth_sigs_orchard = Vec::new();
-            for _ in 0..actions_orchard.len() {
-                auth_sigs_orchard.push(read_n_bytes(bytes)?);
+            for i in 0..actions_orchard.len() {
+                auth_sigs_orchard.push(read_n_bytes(bytes).index(i).field("auth_sigs_orchard")?);
             }
 
-            let binding_sig_orchard = read_n_bytes(bytes)?;
+            let binding_sig_orchard = read_n_bytes(bytes).field("binding_sig_orchard")?;
 
             (
                 Some(flags_orchard),
@@ -658,19 +1377,677 @@ impl Codec for TxV5 {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct TxIn {
+impl TxV5 {
+    /// Returns a builder for a V5 transaction.
+    pub fn builder() -> TxV5Builder {
+        TxV5Builder::default()
+    }
+
+    /// Returns the version group ID.
+    pub fn group_id(&self) -> u32 {
+        self.group_id
+    }
+
+    /// Returns the consensus branch ID the transaction is valid for.
+    pub fn consensus_branch(&self) -> u32 {
+        self.consensus_branch
+    }
+
+    /// Returns the lock time.
+    pub fn lock_time(&self) -> u32 {
+        self.lock_time
+    }
+
+    /// Returns the height after which the transaction expires.
+    pub fn expiry_height(&self) -> u32 {
+        self.expiry_height
+    }
+
+    /// Returns the transparent inputs.
+    pub fn tx_in(&self) -> &[TxIn] {
+        &self.tx_in
+    }
+
+    /// Returns the transparent outputs.
+    pub fn tx_out(&self) -> &[TxOut] {
+        &self.tx_out
+    }
+
+    /// Returns the Sapling spend descriptions.
+    pub fn spends_sapling(&self) -> &[SpendDescriptionV5] {
+        &self.spends_sapling
+    }
+
+    /// Returns the Sapling output descriptions.
+    pub fn outputs_sapling(&self) -> &[OutputDescriptionV5] {
+        &self.outputs_sapling
+    }
+
+    /// Returns the net value of the Sapling spends minus outputs, present if there are Sapling
+    /// spends or outputs.
+    pub fn value_balance_sapling(&self) -> Option<i64> {
+        self.value_balance_sapling
+    }
+
+    /// Returns the Sapling anchor, present if there are Sapling spends.
+    pub fn anchor_sapling(&self) -> Option<&[u8; 32]> {
+        self.anchor_sapling.as_ref()
+    }
+
+    /// Returns the proofs of the Sapling spends, one per spend.
+    pub fn spend_proofs_sapling(&self) -> &[[u8; 192]] {
+        &self.spend_proofs_sapling
+    }
+
+    /// Returns the authorizing signatures of the Sapling spends, one per spend.
+    pub fn spend_auth_sigs_sapling(&self) -> &[[u8; 64]] {
+        &self.spend_auth_sigs_sapling
+    }
+
+    /// Returns the proofs of the Sapling outputs, one per output.
+    pub fn output_proofs_sapling(&self) -> &[[u8; 192]] {
+        &self.output_proofs_sapling
+    }
+
+    /// Returns the Sapling binding signature, present if there are Sapling spends or outputs.
+    pub fn binding_sig_sapling(&self) -> Option<&[u8; 64]> {
+        self.binding_sig_sapling.as_ref()
+    }
+
+    /// Returns the Orchard action descriptions.
+    pub fn actions_orchard(&self) -> &[ActionDescription] {
+        &self.actions_orchard
+    }
+
+    /// Returns the Orchard flags, present if there are Orchard actions.
+    pub fn flags_orchard(&self) -> Option<u8> {
+        self.flags_orchard
+    }
+
+    /// Returns the net value of the Orchard actions, present if there are Orchard actions.
+    pub fn value_balance_orchard(&self) -> Option<i64> {
+        self.value_balance_orchard
+    }
+
+    /// Returns the Orchard anchor, present if there are Orchard actions.
+    pub fn anchor_orchard(&self) -> Option<&[u8; 32]> {
+        self.anchor_orchard.as_ref()
+    }
+
+    /// Returns the aggregated Orchard proof, present if there are Orchard actions.
+    pub fn proofs_orchard(&self) -> Option<&[u8]> {
+        self.proofs_orchard.as_deref()
+    }
+
+    /// Returns the authorizing signatures of the Orchard actions, present if there are Orchard
+    /// actions.
+    pub fn auth_sigs_orchard(&self) -> Option<&[[u8; 64]]> {
+        self.auth_sigs_orchard.as_deref()
+    }
+
+    /// Returns the Orchard binding signature, present if there are Orchard actions.
+    pub fn binding_sig_orchard(&self) -> Option<&[u8; 64]> {
+        self.binding_sig_orchard.as_ref()
+    }
+}
+
+/// The ZIP 244 digests of V5 transactions ([spec](https://zips.z.cash/zip-0244)).
+impl TxV5 {
+    /// Computes the transaction ID, which commits to the effecting data of the transaction.
+    pub fn txid(&self) -> Hash {
+        let mut state = blake2b_256(&self.branch_personalization(b"ZcashTxHash_"));
+        state.update(&self.header_digest());
+        state.update(&self.transparent_digest());
+        state.update(&self.sapling_digest());
+        state.update(&self.orchard_digest());
+
+        Hash::new(finalize(state))
+    }
+
+    /// Computes the digest of the authorizing data (scripts, proofs and signatures) of the
+    /// transaction.
+    pub fn auth_digest(&self) -> Hash {
+        let mut state = blake2b_256(&self.branch_personalization(b"ZTxAuthHash_"));
+        state.update(&self.transparent_auth_digest());
+        state.update(&self.sapling_auth_digest());
+        state.update(&self.orchard_auth_digest());
+
+        Hash::new(finalize(state))
+    }
+
+    pub(super) fn branch_personalization(&self, prefix: &[u8; 12]) -> [u8; 16] {
+        let mut personalization = [0u8; 16];
+        personalization[..12].copy_from_slice(prefix);
+        personalization[12..].copy_from_slice(&self.consensus_branch.to_le_bytes());
+
+        personalization
+    }
+
+    pub(super) fn header_digest(&self) -> [u8; 32] {
+        let mut state = blake2b_256(b"ZTxIdHeadersHash");
+        state.update(&(5u32 | 1 << 31).to_le_bytes());
+        state.update(&self.group_id.to_le_bytes());
+        state.update(&self.consensus_branch.to_le_bytes());
+        state.update(&self.lock_time.to_le_bytes());
+        state.update(&self.expiry_height.to_le_bytes());
+
+        finalize(state)
+    }
+
+    pub(super) fn transparent_digest(&self) -> [u8; 32] {
+        let mut state = blake2b_256(b"ZTxIdTranspaHash");
+
+        if self.tx_in.is_empty() && self.tx_out.is_empty() {
+            return finalize(state);
+        }
+
+        state.update(&prevouts_digest(b"ZTxIdPrevoutHash", &self.tx_in));
+        state.update(&sequences_digest(b"ZTxIdSequencHash", &self.tx_in));
+        state.update(&outputs_digest(b"ZTxIdOutputsHash", &self.tx_out));
+
+        finalize(state)
+    }
+
+    pub(super) fn sapling_digest(&self) -> [u8; 32] {
+        let mut state = blake2b_256(b"ZTxIdSaplingHash");
+
+        if self.spends_sapling.is_empty() && self.outputs_sapling.is_empty() {
+            return finalize(state);
+        }
+
+        let mut spends = blake2b_256(b"ZTxIdSSpendsHash");
+        if !self.spends_sapling.is_empty() {
+            let anchor = self.anchor_sapling.unwrap_or_default();
+            let mut compact = blake2b_256(b"ZTxIdSSpendCHash");
+            let mut noncompact = blake2b_256(b"ZTxIdSSpendNHash");
+            for spend in &self.spends_sapling {
+                compact.update(&spend.nullifier);
+                noncompact.update(&spend.cv);
+                noncompact.update(&anchor);
+                noncompact.update(&spend.rk);
+            }
+
+            spends.update(&finalize(compact));
+            spends.update(&finalize(noncompact));
+        }
+
+        let mut outputs = blake2b_256(b"ZTxIdSOutputHash");
+        if !self.outputs_sapling.is_empty() {
+            let mut compact = blake2b_256(b"ZTxIdSOutC__Hash");
+            let mut memos = blake2b_256(b"ZTxIdSOutM__Hash");
+            let mut noncompact = blake2b_256(b"ZTxIdSOutN__Hash");
+            for output in &self.outputs_sapling {
+                compact.update(&output.cmu);
+                compact.update(&output.ephemeral_key);
+                compact.update(&output.enc_ciphertext[..COMPACT_NOTE_LEN]);
+                memos.update(&output.enc_ciphertext[COMPACT_NOTE_LEN..MEMO_END]);
+                noncompact.update(&output.cv);
+                noncompact.update(&output.enc_ciphertext[MEMO_END..]);
+                noncompact.update(&output.out_ciphertext);
+            }
+
+            outputs.update(&finalize(compact));
+            outputs.update(&finalize(memos));
+            outputs.update(&finalize(noncompact));
+        }
+
+        state.update(&finalize(spends));
+        state.update(&finalize(outputs));
+        state.update(&self.value_balance_sapling.unwrap_or_default().to_le_bytes());
+
+        finalize(state)
+    }
+
+    pub(super) fn orchard_digest(&self) -> [u8; 32] {
+        let mut state = blake2b_256(b"ZTxIdOrchardHash");
+
+        if self.actions_orchard.is_empty() {
+            return finalize(state);
+        }
+
+        let mut compact = blake2b_256(b"ZTxIdOrcActCHash");
+        let mut memos = blake2b_256(b"ZTxIdOrcActMHash");
+        let mut noncompact = blake2b_256(b"ZTxIdOrcActNHash");
+        for action in &self.actions_orchard {
+            compact.update(&action.nullifier);
+            compact.update(&action.cmx);
+            compact.update(&action.ephemeral_key);
+            compact.update(&action.enc_ciphertext[..COMPACT_NOTE_LEN]);
+            memos.update(&action.enc_ciphertext[COMPACT_NOTE_LEN..MEMO_END]);
+            noncompact.update(&action.cv);
+            noncompact.update(&action.rk);
+            noncompact.update(&action.enc_ciphertext[MEMO_END..]);
+            noncompact.update(&action.out_ciphertext);
+        }
+
+        state.update(&finalize(compact));
+        state.update(&finalize(memos));
+        state.update(&finalize(noncompact));
+        state.update(&[self.flags_orchard.unwrap_or_default()]);
+        state.update(&self.value_balance_orchard.unwrap_or_default().to_le_bytes());
+        state.update(&self.anchor_orchard.unwrap_or_default());
+
+        finalize(state)
+    }
+
+    fn transparent_auth_digest(&self) -> [u8; 32] {
+        let mut state = blake2b_256(b"ZTxAuthTransHash");
+        for input in &self.tx_in {
+            state.update(&encoded(&input.script_len));
+            state.update(&input.script);
+        }
+
+        finalize(state)
+    }
+
+    fn sapling_auth_digest(&self) -> [u8; 32] {
+        let mut state = blake2b_256(b"ZTxAuthSapliHash");
+
+        if self.spends_sapling.is_empty() && self.outputs_sapling.is_empty() {
+            return finalize(state);
+        }
+
+        for proof in &self.spend_proofs_sapling {
+            state.update(proof);
+        }
+        for auth_sig in &self.spend_auth_sigs_sapling {
+            state.update(auth_sig);
+        }
+        for proof in &self.output_proofs_sapling {
+            state.update(proof);
+        }
+        state.update(&self.binding_sig_sapling.unwrap_or([0; 64]));
+
+        finalize(state)
+    }
+
+    fn orchard_auth_digest(&self) -> [u8; 32] {
+        let mut state = blake2b_256(b"ZTxAuthOrchaHash");
+
+        if self.actions_orchard.is_empty() {
+            return finalize(state);
+        }
+
+        state.update(self.proofs_orchard.as_deref().unwrap_or_default());
+        for auth_sig in self.auth_sigs_orchard.iter().flatten() {
+            state.update(auth_sig);
+        }
+        state.update(&self.binding_sig_orchard.unwrap_or([0; 64]));
+
+        finalize(state)
+    }
+}
+
+/// The length of the prefix of a note ciphertext which is kept by compact blocks.
+const COMPACT_NOTE_LEN: usize = 52;
+/// The end of the memo in a note ciphertext, which directly follows the compact prefix.
+const MEMO_END: usize = COMPACT_NOTE_LEN + 512;
+
+/// Returns a BLAKE2b-256 state with the given personalization.
+pub(super) fn blake2b_256(personalization: &[u8]) -> blake2b_simd::State {
+    blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(personalization)
+        .to_state()
+}
+
+pub(super) fn finalize(state: blake2b_simd::State) -> [u8; 32] {
+    state.finalize().as_bytes().try_into().unwrap()
+}
+
+/// Hashes the outpoints spent by the inputs.
+pub(super) fn prevouts_digest(personalization: &[u8], inputs: &[TxIn]) -> [u8; 32] {
+    let mut state = blake2b_256(personalization);
+    for input in inputs {
+        state.update(&input.prev_out_hash.0);
+        state.update(&input.prev_out_index.to_le_bytes());
+    }
+
+    finalize(state)
+}
+
+/// Hashes the sequence numbers of the inputs.
+pub(super) fn sequences_digest(personalization: &[u8], inputs: &[TxIn]) -> [u8; 32] {
+    let mut state = blake2b_256(personalization);
+    for input in inputs {
+        state.update(&input.sequence.to_le_bytes());
+    }
+
+    finalize(state)
+}
+
+/// Hashes the encoded outputs.
+pub(super) fn outputs_digest(personalization: &[u8], outputs: &[TxOut]) -> [u8; 32] {
+    let mut state = blake2b_256(personalization);
+    for output in outputs {
+        state.update(&encoded(output));
+    }
+
+    finalize(state)
+}
+
+fn invalid_for_upgrade(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Encodes a value which can't fail to encode into a new buffer.
+pub(super) fn encoded<T: Codec>(value: &T) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    value.encode(&mut buffer).unwrap();
+
+    buffer
+}
+
+/// A builder for [`TxV5`].
+///
+/// Fields which are only encoded alongside Sapling spends or outputs or Orchard actions are
+/// dropped when there are none, the per-spend, per-output and per-action proofs and signatures
+/// are zero-padded or truncated to match the number of descriptions.
+#[derive(Debug, Clone)]
+pub struct TxV5Builder {
+    group_id: u32,
+    consensus_branch: u32,
+    lock_time: u32,
+    expiry_height: u32,
+    tx_in: Vec<TxIn>,
+    tx_out: Vec<TxOut>,
+    spends_sapling: Vec<SpendDescriptionV5>,
+    outputs_sapling: Vec<OutputDescriptionV5>,
+    value_balance_sapling: i64,
+    anchor_sapling: [u8; 32],
+    spend_proofs_sapling: Vec<[u8; 192]>,
+    spend_auth_sigs_sapling: Vec<[u8; 64]>,
+    output_proofs_sapling: Vec<[u8; 192]>,
+    binding_sig_sapling: [u8; 64],
+    actions_orchard: Vec<ActionDescription>,
+    flags_orchard: u8,
+    value_balance_orchard: i64,
+    anchor_orchard: [u8; 32],
+    proofs_orchard: Vec<u8>,
+    auth_sigs_orchard: Vec<[u8; 64]>,
+    binding_sig_orchard: [u8; 64],
+}
+
+impl Default for TxV5Builder {
+    fn default() -> Self {
+        Self {
+            group_id: NU5_VERSION_GROUP_ID,
+            consensus_branch: NU5_BRANCH_ID,
+            lock_time: 0,
+            expiry_height: 0,
+            tx_in: Vec::new(),
+            tx_out: Vec::new(),
+            spends_sapling: Vec::new(),
+            outputs_sapling: Vec::new(),
+            value_balance_sapling: 0,
+            anchor_sapling: [0; 32],
+            spend_proofs_sapling: Vec::new(),
+            spend_auth_sigs_sapling: Vec::new(),
+            output_proofs_sapling: Vec::new(),
+            binding_sig_sapling: [0; 64],
+            actions_orchard: Vec::new(),
+            flags_orchard: 0,
+            value_balance_orchard: 0,
+            anchor_orchard: [0; 32],
+            proofs_orchard: Vec::new(),
+            auth_sigs_orchard: Vec::new(),
+            binding_sig_orchard: [0; 64],
+        }
+    }
+}
+
+impl TxV5Builder {
+    /// Sets the version group ID, [`NU5_VERSION_GROUP_ID`] by default.
+    pub fn with_group_id(mut self, group_id: u32) -> Self {
+        self.group_id = group_id;
+        self
+    }
+
+    /// Sets the consensus branch ID, [`NU5_BRANCH_ID`] by default.
+    pub fn with_consensus_branch(mut self, consensus_branch: u32) -> Self {
+        self.consensus_branch = consensus_branch;
+        self
+    }
+
+    /// Sets the consensus branch ID to that of the upgrade active at `height`, a
+    /// [`Network`](crate::protocol::network::Network) can be passed for its default
+    /// [`ActivationHeights`].
+    pub fn with_branch_for_height(
+        mut self,
+        heights: impl Into<ActivationHeights>,
+        height: u32,
+    ) -> Self {
+        self.consensus_branch = heights.into().current(height).branch_id();
+        self
+    }
+
+    /// Sets the lock time, `0` by default.
+    pub fn with_lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Sets the expiry height, `0` (no expiry) by default.
+    pub fn with_expiry_height(mut self, expiry_height: u32) -> Self {
+        self.expiry_height = expiry_height;
+        self
+    }
+
+    /// Sets the transparent inputs.
+    pub fn with_tx_in(mut self, tx_in: Vec<TxIn>) -> Self {
+        self.tx_in = tx_in;
+        self
+    }
+
+    /// Sets the transparent outputs.
+    pub fn with_tx_out(mut self, tx_out: Vec<TxOut>) -> Self {
+        self.tx_out = tx_out;
+        self
+    }
+
+    /// Sets the Sapling spend descriptions.
+    pub fn with_spends_sapling(mut self, spends_sapling: Vec<SpendDescriptionV5>) -> Self {
+        self.spends_sapling = spends_sapling;
+        self
+    }
+
+    /// Sets the Sapling output descriptions.
+    pub fn with_outputs_sapling(mut self, outputs_sapling: Vec<OutputDescriptionV5>) -> Self {
+        self.outputs_sapling = outputs_sapling;
+        self
+    }
+
+    /// Sets the net value of the Sapling spends minus outputs, `0` by default.
+    pub fn with_value_balance_sapling(mut self, value_balance_sapling: i64) -> Self {
+        self.value_balance_sapling = value_balance_sapling;
+        self
+    }
+
+    /// Sets the Sapling anchor, zeroed by default.
+    pub fn with_anchor_sapling(mut self, anchor_sapling: [u8; 32]) -> Self {
+        self.anchor_sapling = anchor_sapling;
+        self
+    }
+
+    /// Sets the proofs of the Sapling spends.
+    pub fn with_spend_proofs_sapling(mut self, spend_proofs_sapling: Vec<[u8; 192]>) -> Self {
+        self.spend_proofs_sapling = spend_proofs_sapling;
+        self
+    }
+
+    /// Sets the authorizing signatures of the Sapling spends.
+    pub fn with_spend_auth_sigs_sapling(mut self, spend_auth_sigs_sapling: Vec<[u8; 64]>) -> Self {
+        self.spend_auth_sigs_sapling = spend_auth_sigs_sapling;
+        self
+    }
+
+    /// Sets the proofs of the Sapling outputs.
+    pub fn with_output_proofs_sapling(mut self, output_proofs_sapling: Vec<[u8; 192]>) -> Self {
+        self.output_proofs_sapling = output_proofs_sapling;
+        self
+    }
+
+    /// Sets the Sapling binding signature, zeroed by default.
+    pub fn with_binding_sig_sapling(mut self, binding_sig_sapling: [u8; 64]) -> Self {
+        self.binding_sig_sapling = binding_sig_sapling;
+        self
+    }
+
+    /// Sets the Orchard action descriptions.
+    pub fn with_actions_orchard(mut self, actions_orchard: Vec<ActionDescription>) -> Self {
+        self.actions_orchard = actions_orchard;
+        self
+    }
+
+    /// Sets the Orchard flags, `0` by default.
+    pub fn with_flags_orchard(mut self, flags_orchard: u8) -> Self {
+        self.flags_orchard = flags_orchard;
+        self
+    }
+
+    /// Sets the net value of the Orchard actions, `0` by default.
+    pub fn with_value_balance_orchard(mut self, value_balance_orchard: i64) -> Self {
+        self.value_balance_orchard = value_balance_orchard;
+        self
+    }
+
+    /// Sets the Orchard anchor, zeroed by default.
+    pub fn with_anchor_orchard(mut self, anchor_orchard: [u8; 32]) -> Self {
+        self.anchor_orchard = anchor_orchard;
+        self
+    }
+
+    /// Sets the aggregated Orchard proof, empty by default.
+    pub fn with_proofs_orchard(mut self, proofs_orchard: Vec<u8>) -> Self {
+        self.proofs_orchard = proofs_orchard;
+        self
+    }
+
+    /// Sets the authorizing signatures of the Orchard actions.
+    pub fn with_auth_sigs_orchard(mut self, auth_sigs_orchard: Vec<[u8; 64]>) -> Self {
+        self.auth_sigs_orchard = auth_sigs_orchard;
+        self
+    }
+
+    /// Sets the Orchard binding signature, zeroed by default.
+    pub fn with_binding_sig_orchard(mut self, binding_sig_orchard: [u8; 64]) -> Self {
+        self.binding_sig_orchard = binding_sig_orchard;
+        self
+    }
+
+    /// Creates the [`TxV5`].
+    ///
+    /// Errors with [`ErrorKind::InvalidInput`](io::ErrorKind::InvalidInput) unless there's a Sapling spend proof and
+    /// authorizing signature per spend, an output proof per output and an Orchard authorizing
+    /// signature per action.
+    pub fn build(self) -> io::Result<TxV5> {
+        let n_spends = self.spends_sapling.len();
+        let n_outputs = self.outputs_sapling.len();
+        let n_actions = self.actions_orchard.len();
+
+        for (field, len, expected) in [
+            (
+                "spend_proofs_sapling",
+                self.spend_proofs_sapling.len(),
+                n_spends,
+            ),
+            (
+                "spend_auth_sigs_sapling",
+                self.spend_auth_sigs_sapling.len(),
+                n_spends,
+            ),
+            (
+                "output_proofs_sapling",
+                self.output_proofs_sapling.len(),
+                n_outputs,
+            ),
+            ("auth_sigs_orchard", self.auth_sigs_orchard.len(), n_actions),
+        ] {
+            if len != expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{field} has {len} entries, expected {expected}"),
+                ));
+            }
+        }
+
+        let has_sapling = n_spends + n_outputs > 0;
+        let has_orchard = n_actions > 0;
+
+        Ok(TxV5 {
+            group_id: self.group_id,
+            consensus_branch: self.consensus_branch,
+            lock_time: self.lock_time,
+            expiry_height: self.expiry_height,
+            tx_in: self.tx_in,
+            tx_out: self.tx_out,
+            spends_sapling: self.spends_sapling,
+            outputs_sapling: self.outputs_sapling,
+            value_balance_sapling: has_sapling.then_some(self.value_balance_sapling),
+            anchor_sapling: (n_spends > 0).then_some(self.anchor_sapling),
+            spend_proofs_sapling: self.spend_proofs_sapling,
+            spend_auth_sigs_sapling: self.spend_auth_sigs_sapling,
+            output_proofs_sapling: self.output_proofs_sapling,
+            binding_sig_sapling: has_sapling.then_some(self.binding_sig_sapling),
+            actions_orchard: self.actions_orchard,
+            flags_orchard: has_orchard.then_some(self.flags_orchard),
+            value_balance_orchard: has_orchard.then_some(self.value_balance_orchard),
+            anchor_orchard: has_orchard.then_some(self.anchor_orchard),
+            proofs_orchard: has_orchard.then_some(self.proofs_orchard),
+            auth_sigs_orchard: has_orchard.then_some(self.auth_sigs_orchard),
+            binding_sig_orchard: has_orchard.then_some(self.binding_sig_orchard),
+        })
+    }
+}
+
+/// A transparent transaction input.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TxIn {
     // Outpoint object (previous output transaction reference).
     prev_out_hash: Hash,
     prev_out_index: u32,
 
     script_len: VarInt,
+    #[serde(with = "as_hex")]
     script: Vec<u8>,
 
     // Is currently unused in bitcoin, not sure about Zcash.
     sequence: u32,
 }
 
+impl TxIn {
+    /// Creates an input spending output `prev_out_index` of the transaction `prev_out_hash`.
+    pub fn new(prev_out_hash: Hash, prev_out_index: u32, script: Vec<u8>, sequence: u32) -> Self {
+        Self {
+            prev_out_hash,
+            prev_out_index,
+            script_len: VarInt(script.len()),
+            script,
+            sequence,
+        }
+    }
+
+    /// Returns the hash of the transaction whose output is spent.
+    pub fn prev_out_hash(&self) -> Hash {
+        self.prev_out_hash
+    }
+
+    /// Returns the index of the spent output.
+    pub fn prev_out_index(&self) -> u32 {
+        self.prev_out_index
+    }
+
+    /// Returns the signature script.
+    pub fn script(&self) -> &[u8] {
+        &self.script
+    }
+
+    /// Returns the sequence number.
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+}
+
 impl Codec for TxIn {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         self.prev_out_hash.encode(buffer)?;
@@ -684,20 +2061,21 @@ impl Codec for TxIn {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let prev_out_hash = Hash::decode(bytes)?;
-        let prev_out_index = u32::from_le_bytes(read_n_bytes(bytes)?);
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let prev_out_hash = Hash::decode_with(bytes, options).field("prev_out_hash")?;
+        let prev_out_index = u32::from_le_bytes(read_n_bytes(bytes).field("prev_out_index")?);
 
-        let script_len = VarInt::decode(bytes)?;
+        let script_len = VarInt::decode_with(bytes, options).field("script")?;
 
         if bytes.remaining() < script_len.0 {
-            return Err(io::ErrorKind::InvalidData.into());
+            return Err(ProtocolError::unexpected_eof(script_len.0, bytes.remaining()).into())
+                .field("script");
         }
 
         let mut script = vec![0u8; script_len.0];
         bytes.copy_to_slice(&mut script);
 
-        let sequence = u32::from_le_bytes(read_n_bytes(bytes)?);
+        let sequence = u32::from_le_bytes(read_n_bytes(bytes).field("sequence")?);
 
         Ok(Self {
             prev_out_hash,
@@ -709,13 +2087,36 @@ impl Codec for TxIn {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct TxOut {
+/// A transparent transaction output.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TxOut {
     value: i64,
     pk_script_len: VarInt,
+    #[serde(with = "as_hex")]
     pk_script: Vec<u8>,
 }
 
+impl TxOut {
+    /// Creates an output paying `value` zatoshis to `pk_script`.
+    pub fn new(value: i64, pk_script: Vec<u8>) -> Self {
+        Self {
+            value,
+            pk_script_len: VarInt(pk_script.len()),
+            pk_script,
+        }
+    }
+
+    /// Returns the value in zatoshis.
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// Returns the public key script.
+    pub fn pk_script(&self) -> &[u8] {
+        &self.pk_script
+    }
+}
+
 impl Codec for TxOut {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         buffer.put_i64_le(self.value);
@@ -725,12 +2126,13 @@ impl Codec for TxOut {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let value = i64::from_le_bytes(read_n_bytes(bytes)?);
-        let pk_script_len = VarInt::decode(bytes)?;
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let value = i64::from_le_bytes(read_n_bytes(bytes).field("value")?);
+        let pk_script_len = VarInt::decode_with(bytes, options).field("pk_script")?;
 
         if bytes.remaining() < pk_script_len.0 {
-            return Err(io::ErrorKind::InvalidData.into());
+            return Err(ProtocolError::unexpected_eof(pk_script_len.0, bytes.remaining()).into())
+                .field("pk_script");
         }
 
         let mut pk_script = vec![0u8; pk_script_len.0];
@@ -744,27 +2146,128 @@ impl Codec for TxOut {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct JoinSplit {
+/// A Sprout join split description.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct JoinSplit {
+    /// The value leaving the transparent pool.
     pub_old: u64,
+    /// The value entering the transparent pool.
     pub_new: u64,
+    /// The root of the Sprout note commitment tree.
+    #[serde(with = "as_hex")]
     anchor: [u8; 32],
-    // Two nullifiers are present, each 32 bytes long.
+    /// Two nullifiers, each 32 bytes long.
+    #[serde(with = "as_hex")]
     nullifiers: [u8; 64],
-    // Two commitments are present, each 32 bytes long.
+    /// Two note commitments, each 32 bytes long.
+    #[serde(with = "as_hex")]
     commitments: [u8; 64],
+    #[serde(with = "as_hex")]
     ephemeral_key: [u8; 32],
+    #[serde(with = "as_hex")]
     random_seed: [u8; 32],
-    // Two tags are present, each 32 bytes long.
+    /// Two tags, each 32 bytes long.
+    #[serde(with = "as_hex")]
     vmacs: [u8; 64],
-    // BCTV14 or Groth16, depending on the transaction version.
+    /// BCTV14 or Groth16, depending on the transaction version.
     zkproof: Zkproof,
-    // Two ciphertex components are present, each 601 bytes long.
+    /// Two ciphertext components, each 601 bytes long.
+    #[serde(with = "as_hex")]
     enc_ciphertexts: [u8; 1202],
 }
 
-impl JoinSplit {
-    fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
+impl JoinSplit {
+    /// Returns a join split with a BCTV14 proof (for V2 and V3 transactions) and all its fields
+    /// zeroed.
+    pub fn zeroed_bctv14() -> Self {
+        Self::zeroed(Zkproof::BCTV14([0; 296]))
+    }
+
+    /// Returns a join split with a Groth16 proof (for V4 transactions) and all its fields zeroed.
+    pub fn zeroed_groth16() -> Self {
+        Self::zeroed(Zkproof::Groth16([0; 192]))
+    }
+
+    /// Returns a builder for a join split with a BCTV14 proof (for V2 and V3 transactions).
+    pub fn bctv14_builder() -> JoinSplitBuilder {
+        JoinSplitBuilder {
+            join_split: Self::zeroed_bctv14(),
+        }
+    }
+
+    /// Returns a builder for a join split with a Groth16 proof (for V4 transactions).
+    pub fn groth16_builder() -> JoinSplitBuilder {
+        JoinSplitBuilder {
+            join_split: Self::zeroed_groth16(),
+        }
+    }
+
+    fn zeroed(zkproof: Zkproof) -> Self {
+        Self {
+            pub_old: 0,
+            pub_new: 0,
+            anchor: [0; 32],
+            nullifiers: [0; 64],
+            commitments: [0; 64],
+            ephemeral_key: [0; 32],
+            random_seed: [0; 32],
+            vmacs: [0; 64],
+            zkproof,
+            enc_ciphertexts: [0; 1202],
+        }
+    }
+
+    /// Returns the value leaving the transparent pool.
+    pub fn pub_old(&self) -> u64 {
+        self.pub_old
+    }
+
+    /// Returns the value entering the transparent pool.
+    pub fn pub_new(&self) -> u64 {
+        self.pub_new
+    }
+
+    /// Returns the root of the Sprout note commitment tree.
+    pub fn anchor(&self) -> &[u8; 32] {
+        &self.anchor
+    }
+
+    /// Returns two nullifiers, each 32 bytes long.
+    pub fn nullifiers(&self) -> &[u8; 64] {
+        &self.nullifiers
+    }
+
+    /// Returns two note commitments, each 32 bytes long.
+    pub fn commitments(&self) -> &[u8; 64] {
+        &self.commitments
+    }
+
+    /// Returns the ephemeral public key.
+    pub fn ephemeral_key(&self) -> &[u8; 32] {
+        &self.ephemeral_key
+    }
+
+    /// Returns the random seed.
+    pub fn random_seed(&self) -> &[u8; 32] {
+        &self.random_seed
+    }
+
+    /// Returns two tags, each 32 bytes long.
+    pub fn vmacs(&self) -> &[u8; 64] {
+        &self.vmacs
+    }
+
+    /// Returns the BCTV14 or Groth16 proof, depending on the transaction version.
+    pub fn zkproof(&self) -> &Zkproof {
+        &self.zkproof
+    }
+
+    /// Returns two ciphertext components, each 601 bytes long.
+    pub fn enc_ciphertexts(&self) -> &[u8; 1202] {
+        &self.enc_ciphertexts
+    }
+
+    pub(super) fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         buffer.put_u64_le(self.pub_old);
         buffer.put_u64_le(self.pub_new);
 
@@ -839,11 +2342,85 @@ impl JoinSplit {
     }
 }
 
+/// A builder for [`JoinSplit`], every field but the proof system is zeroed by default.
+#[derive(Debug, Clone)]
+pub struct JoinSplitBuilder {
+    join_split: JoinSplit,
+}
+
+impl JoinSplitBuilder {
+    /// Sets the value leaving the transparent pool, `0` by default.
+    pub fn with_pub_old(mut self, pub_old: u64) -> Self {
+        self.join_split.pub_old = pub_old;
+        self
+    }
+
+    /// Sets the value entering the transparent pool, `0` by default.
+    pub fn with_pub_new(mut self, pub_new: u64) -> Self {
+        self.join_split.pub_new = pub_new;
+        self
+    }
+
+    /// Sets the root of the Sprout note commitment tree, zeroed by default.
+    pub fn with_anchor(mut self, anchor: [u8; 32]) -> Self {
+        self.join_split.anchor = anchor;
+        self
+    }
+
+    /// Sets the two nullifiers, each 32 bytes long, zeroed by default.
+    pub fn with_nullifiers(mut self, nullifiers: [u8; 64]) -> Self {
+        self.join_split.nullifiers = nullifiers;
+        self
+    }
+
+    /// Sets the two note commitments, each 32 bytes long, zeroed by default.
+    pub fn with_commitments(mut self, commitments: [u8; 64]) -> Self {
+        self.join_split.commitments = commitments;
+        self
+    }
+
+    /// Sets the ephemeral public key, zeroed by default.
+    pub fn with_ephemeral_key(mut self, ephemeral_key: [u8; 32]) -> Self {
+        self.join_split.ephemeral_key = ephemeral_key;
+        self
+    }
+
+    /// Sets the random seed, zeroed by default.
+    pub fn with_random_seed(mut self, random_seed: [u8; 32]) -> Self {
+        self.join_split.random_seed = random_seed;
+        self
+    }
+
+    /// Sets the two tags, each 32 bytes long, zeroed by default.
+    pub fn with_vmacs(mut self, vmacs: [u8; 64]) -> Self {
+        self.join_split.vmacs = vmacs;
+        self
+    }
+
+    /// Sets the proof, whose system must match the transaction version, zeroed by default.
+    pub fn with_zkproof(mut self, zkproof: Zkproof) -> Self {
+        self.join_split.zkproof = zkproof;
+        self
+    }
+
+    /// Sets the two ciphertext components, each 601 bytes long, zeroed by default.
+    pub fn with_enc_ciphertexts(mut self, enc_ciphertexts: [u8; 1202]) -> Self {
+        self.join_split.enc_ciphertexts = enc_ciphertexts;
+        self
+    }
+
+    /// Creates the [`JoinSplit`].
+    pub fn build(self) -> JoinSplit {
+        self.join_split
+    }
+}
+
+/// The proof of a [`JoinSplit`].
 // TODO: rethink abstraction.
-#[derive(Debug, PartialEq, Clone)]
-enum Zkproof {
-    BCTV14([u8; 296]),
-    Groth16([u8; 192]),
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Zkproof {
+    BCTV14(#[serde(with = "as_hex")] [u8; 296]),
+    Groth16(#[serde(with = "as_hex")] [u8; 192]),
 }
 
 impl Zkproof {
@@ -857,17 +2434,78 @@ impl Zkproof {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct SpendDescriptionV4 {
+/// A Sapling spend description of a V4 transaction.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SpendDescriptionV4 {
+    /// The value commitment.
+    #[serde(with = "as_hex")]
     cv: [u8; 32],
+    /// The root of the Sapling note commitment tree.
+    #[serde(with = "as_hex")]
     anchor: [u8; 32],
+    #[serde(with = "as_hex")]
     nullifier: [u8; 32],
+    /// The randomized public key.
+    #[serde(with = "as_hex")]
     rk: [u8; 32],
-    // Groth16 only.
+    /// The Groth16 proof.
+    #[serde(with = "as_hex")]
     zkproof: [u8; 192],
+    #[serde(with = "as_hex")]
     spend_auth_sig: [u8; 64],
 }
 
+impl SpendDescriptionV4 {
+    /// Returns a builder for a spend description.
+    pub fn builder() -> SpendDescriptionV4Builder {
+        SpendDescriptionV4Builder {
+            spend_description: Self::zeroed(),
+        }
+    }
+
+    /// Returns a spend description with all its fields zeroed.
+    pub fn zeroed() -> Self {
+        Self {
+            cv: [0; 32],
+            anchor: [0; 32],
+            nullifier: [0; 32],
+            rk: [0; 32],
+            zkproof: [0; 192],
+            spend_auth_sig: [0; 64],
+        }
+    }
+
+    /// Returns the value commitment.
+    pub fn cv(&self) -> &[u8; 32] {
+        &self.cv
+    }
+
+    /// Returns the root of the Sapling note commitment tree.
+    pub fn anchor(&self) -> &[u8; 32] {
+        &self.anchor
+    }
+
+    /// Returns the nullifier.
+    pub fn nullifier(&self) -> &[u8; 32] {
+        &self.nullifier
+    }
+
+    /// Returns the randomized public key.
+    pub fn rk(&self) -> &[u8; 32] {
+        &self.rk
+    }
+
+    /// Returns the Groth16 proof.
+    pub fn zkproof(&self) -> &[u8; 192] {
+        &self.zkproof
+    }
+
+    /// Returns the spend authorizing signature.
+    pub fn spend_auth_sig(&self) -> &[u8; 64] {
+        &self.spend_auth_sig
+    }
+}
+
 impl Codec for SpendDescriptionV4 {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         buffer.put_slice(&self.cv);
@@ -880,7 +2518,7 @@ impl Codec for SpendDescriptionV4 {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
         let cv = read_n_bytes(bytes)?;
         let anchor = read_n_bytes(bytes)?;
         let nullifier = read_n_bytes(bytes)?;
@@ -899,13 +2537,102 @@ impl Codec for SpendDescriptionV4 {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct SpendDescriptionV5 {
+/// A builder for [`SpendDescriptionV4`], every field is zeroed by default.
+#[derive(Debug, Clone)]
+pub struct SpendDescriptionV4Builder {
+    spend_description: SpendDescriptionV4,
+}
+
+impl SpendDescriptionV4Builder {
+    /// Sets the value commitment, zeroed by default.
+    pub fn with_cv(mut self, cv: [u8; 32]) -> Self {
+        self.spend_description.cv = cv;
+        self
+    }
+
+    /// Sets the root of the Sapling note commitment tree, zeroed by default.
+    pub fn with_anchor(mut self, anchor: [u8; 32]) -> Self {
+        self.spend_description.anchor = anchor;
+        self
+    }
+
+    /// Sets the nullifier, zeroed by default.
+    pub fn with_nullifier(mut self, nullifier: [u8; 32]) -> Self {
+        self.spend_description.nullifier = nullifier;
+        self
+    }
+
+    /// Sets the randomized public key, zeroed by default.
+    pub fn with_rk(mut self, rk: [u8; 32]) -> Self {
+        self.spend_description.rk = rk;
+        self
+    }
+
+    /// Sets the Groth16 proof, zeroed by default.
+    pub fn with_zkproof(mut self, zkproof: [u8; 192]) -> Self {
+        self.spend_description.zkproof = zkproof;
+        self
+    }
+
+    /// Sets the spend authorizing signature, zeroed by default.
+    pub fn with_spend_auth_sig(mut self, spend_auth_sig: [u8; 64]) -> Self {
+        self.spend_description.spend_auth_sig = spend_auth_sig;
+        self
+    }
+
+    /// Creates the [`SpendDescriptionV4`].
+    pub fn build(self) -> SpendDescriptionV4 {
+        self.spend_description
+    }
+}
+
+/// A Sapling spend description of a V5 transaction, its anchor, proof and signature are stored
+/// in the transaction.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SpendDescriptionV5 {
+    /// The value commitment.
+    #[serde(with = "as_hex")]
     cv: [u8; 32],
+    #[serde(with = "as_hex")]
     nullifier: [u8; 32],
+    /// The randomized public key.
+    #[serde(with = "as_hex")]
     rk: [u8; 32],
 }
 
+impl SpendDescriptionV5 {
+    /// Returns a builder for a spend description.
+    pub fn builder() -> SpendDescriptionV5Builder {
+        SpendDescriptionV5Builder {
+            spend_description: Self::zeroed(),
+        }
+    }
+
+    /// Returns a spend description with all its fields zeroed.
+    pub fn zeroed() -> Self {
+        Self {
+            cv: [0; 32],
+            nullifier: [0; 32],
+            rk: [0; 32],
+        }
+    }
+
+    /// Returns the value commitment.
+    pub fn cv(&self) -> &[u8; 32] {
+        &self.cv
+    }
+
+    /// Returns the nullifier.
+    pub fn nullifier(&self) -> &[u8; 32] {
+        &self.nullifier
+    }
+
+    /// Returns the randomized public key.
+    pub fn rk(&self) -> &[u8; 32] {
+        &self.rk
+    }
+}
+
 impl Codec for SpendDescriptionV5 {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         buffer.put_slice(&self.cv);
@@ -915,7 +2642,7 @@ impl Codec for SpendDescriptionV5 {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
         let cv = read_n_bytes(bytes)?;
         let nullifier = read_n_bytes(bytes)?;
         let rk = read_n_bytes(bytes)?;
@@ -924,16 +2651,108 @@ impl Codec for SpendDescriptionV5 {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct OutputDescriptionV4 {
+/// A builder for [`SpendDescriptionV5`], every field is zeroed by default.
+#[derive(Debug, Clone)]
+pub struct SpendDescriptionV5Builder {
+    spend_description: SpendDescriptionV5,
+}
+
+impl SpendDescriptionV5Builder {
+    /// Sets the value commitment, zeroed by default.
+    pub fn with_cv(mut self, cv: [u8; 32]) -> Self {
+        self.spend_description.cv = cv;
+        self
+    }
+
+    /// Sets the nullifier, zeroed by default.
+    pub fn with_nullifier(mut self, nullifier: [u8; 32]) -> Self {
+        self.spend_description.nullifier = nullifier;
+        self
+    }
+
+    /// Sets the randomized public key, zeroed by default.
+    pub fn with_rk(mut self, rk: [u8; 32]) -> Self {
+        self.spend_description.rk = rk;
+        self
+    }
+
+    /// Creates the [`SpendDescriptionV5`].
+    pub fn build(self) -> SpendDescriptionV5 {
+        self.spend_description
+    }
+}
+
+/// A Sapling output description of a V4 transaction.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct OutputDescriptionV4 {
+    /// The value commitment.
+    #[serde(with = "as_hex")]
     cv: [u8; 32],
+    /// The note commitment.
+    #[serde(with = "as_hex")]
     cmu: [u8; 32],
+    #[serde(with = "as_hex")]
     ephemeral_key: [u8; 32],
+    #[serde(with = "as_hex")]
     enc_ciphertext: [u8; 580],
+    #[serde(with = "as_hex")]
     out_ciphertext: [u8; 80],
+    /// The Groth16 proof.
+    #[serde(with = "as_hex")]
     zkproof: [u8; 192],
 }
 
+impl OutputDescriptionV4 {
+    /// Returns a builder for an output description.
+    pub fn builder() -> OutputDescriptionV4Builder {
+        OutputDescriptionV4Builder {
+            output_description: Self::zeroed(),
+        }
+    }
+
+    /// Returns an output description with all its fields zeroed.
+    pub fn zeroed() -> Self {
+        Self {
+            cv: [0; 32],
+            cmu: [0; 32],
+            ephemeral_key: [0; 32],
+            enc_ciphertext: [0; 580],
+            out_ciphertext: [0; 80],
+            zkproof: [0; 192],
+        }
+    }
+
+    /// Returns the value commitment.
+    pub fn cv(&self) -> &[u8; 32] {
+        &self.cv
+    }
+
+    /// Returns the note commitment.
+    pub fn cmu(&self) -> &[u8; 32] {
+        &self.cmu
+    }
+
+    /// Returns the ephemeral public key.
+    pub fn ephemeral_key(&self) -> &[u8; 32] {
+        &self.ephemeral_key
+    }
+
+    /// Returns the encrypted note.
+    pub fn enc_ciphertext(&self) -> &[u8; 580] {
+        &self.enc_ciphertext
+    }
+
+    /// Returns the encrypted outgoing cipher key and note.
+    pub fn out_ciphertext(&self) -> &[u8; 80] {
+        &self.out_ciphertext
+    }
+
+    /// Returns the Groth16 proof.
+    pub fn zkproof(&self) -> &[u8; 192] {
+        &self.zkproof
+    }
+}
+
 impl Codec for OutputDescriptionV4 {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         buffer.put_slice(&self.cv);
@@ -946,7 +2765,7 @@ impl Codec for OutputDescriptionV4 {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
         let cv = read_n_bytes(bytes)?;
         let cmu = read_n_bytes(bytes)?;
         let ephemeral_key = read_n_bytes(bytes)?;
@@ -965,15 +2784,117 @@ impl Codec for OutputDescriptionV4 {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct OutputDescriptionV5 {
+/// A builder for [`OutputDescriptionV4`], every field is zeroed by default.
+#[derive(Debug, Clone)]
+pub struct OutputDescriptionV4Builder {
+    output_description: OutputDescriptionV4,
+}
+
+impl OutputDescriptionV4Builder {
+    /// Sets the value commitment, zeroed by default.
+    pub fn with_cv(mut self, cv: [u8; 32]) -> Self {
+        self.output_description.cv = cv;
+        self
+    }
+
+    /// Sets the note commitment, zeroed by default.
+    pub fn with_cmu(mut self, cmu: [u8; 32]) -> Self {
+        self.output_description.cmu = cmu;
+        self
+    }
+
+    /// Sets the ephemeral public key, zeroed by default.
+    pub fn with_ephemeral_key(mut self, ephemeral_key: [u8; 32]) -> Self {
+        self.output_description.ephemeral_key = ephemeral_key;
+        self
+    }
+
+    /// Sets the encrypted note, zeroed by default.
+    pub fn with_enc_ciphertext(mut self, enc_ciphertext: [u8; 580]) -> Self {
+        self.output_description.enc_ciphertext = enc_ciphertext;
+        self
+    }
+
+    /// Sets the encrypted outgoing cipher key and note, zeroed by default.
+    pub fn with_out_ciphertext(mut self, out_ciphertext: [u8; 80]) -> Self {
+        self.output_description.out_ciphertext = out_ciphertext;
+        self
+    }
+
+    /// Sets the Groth16 proof, zeroed by default.
+    pub fn with_zkproof(mut self, zkproof: [u8; 192]) -> Self {
+        self.output_description.zkproof = zkproof;
+        self
+    }
+
+    /// Creates the [`OutputDescriptionV4`].
+    pub fn build(self) -> OutputDescriptionV4 {
+        self.output_description
+    }
+}
+
+/// A Sapling output description of a V5 transaction, its proof is stored in the transaction.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct OutputDescriptionV5 {
+    /// The value commitment.
+    #[serde(with = "as_hex")]
     cv: [u8; 32],
+    /// The note commitment.
+    #[serde(with = "as_hex")]
     cmu: [u8; 32],
+    #[serde(with = "as_hex")]
     ephemeral_key: [u8; 32],
+    #[serde(with = "as_hex")]
     enc_ciphertext: [u8; 580],
+    #[serde(with = "as_hex")]
     out_ciphertext: [u8; 80],
 }
 
+impl OutputDescriptionV5 {
+    /// Returns a builder for an output description.
+    pub fn builder() -> OutputDescriptionV5Builder {
+        OutputDescriptionV5Builder {
+            output_description: Self::zeroed(),
+        }
+    }
+
+    /// Returns an output description with all its fields zeroed.
+    pub fn zeroed() -> Self {
+        Self {
+            cv: [0; 32],
+            cmu: [0; 32],
+            ephemeral_key: [0; 32],
+            enc_ciphertext: [0; 580],
+            out_ciphertext: [0; 80],
+        }
+    }
+
+    /// Returns the value commitment.
+    pub fn cv(&self) -> &[u8; 32] {
+        &self.cv
+    }
+
+    /// Returns the note commitment.
+    pub fn cmu(&self) -> &[u8; 32] {
+        &self.cmu
+    }
+
+    /// Returns the ephemeral public key.
+    pub fn ephemeral_key(&self) -> &[u8; 32] {
+        &self.ephemeral_key
+    }
+
+    /// Returns the encrypted note.
+    pub fn enc_ciphertext(&self) -> &[u8; 580] {
+        &self.enc_ciphertext
+    }
+
+    /// Returns the encrypted outgoing cipher key and note.
+    pub fn out_ciphertext(&self) -> &[u8; 80] {
+        &self.out_ciphertext
+    }
+}
+
 impl Codec for OutputDescriptionV5 {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         buffer.put_slice(&self.cv);
@@ -985,7 +2906,7 @@ impl Codec for OutputDescriptionV5 {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
         let cv = read_n_bytes(bytes)?;
         let cmu = read_n_bytes(bytes)?;
         let ephemeral_key = read_n_bytes(bytes)?;
@@ -1002,17 +2923,128 @@ impl Codec for OutputDescriptionV5 {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-struct ActionDescription {
+/// A builder for [`OutputDescriptionV5`], every field is zeroed by default.
+#[derive(Debug, Clone)]
+pub struct OutputDescriptionV5Builder {
+    output_description: OutputDescriptionV5,
+}
+
+impl OutputDescriptionV5Builder {
+    /// Sets the value commitment, zeroed by default.
+    pub fn with_cv(mut self, cv: [u8; 32]) -> Self {
+        self.output_description.cv = cv;
+        self
+    }
+
+    /// Sets the note commitment, zeroed by default.
+    pub fn with_cmu(mut self, cmu: [u8; 32]) -> Self {
+        self.output_description.cmu = cmu;
+        self
+    }
+
+    /// Sets the ephemeral public key, zeroed by default.
+    pub fn with_ephemeral_key(mut self, ephemeral_key: [u8; 32]) -> Self {
+        self.output_description.ephemeral_key = ephemeral_key;
+        self
+    }
+
+    /// Sets the encrypted note, zeroed by default.
+    pub fn with_enc_ciphertext(mut self, enc_ciphertext: [u8; 580]) -> Self {
+        self.output_description.enc_ciphertext = enc_ciphertext;
+        self
+    }
+
+    /// Sets the encrypted outgoing cipher key and note, zeroed by default.
+    pub fn with_out_ciphertext(mut self, out_ciphertext: [u8; 80]) -> Self {
+        self.output_description.out_ciphertext = out_ciphertext;
+        self
+    }
+
+    /// Creates the [`OutputDescriptionV5`].
+    pub fn build(self) -> OutputDescriptionV5 {
+        self.output_description
+    }
+}
+
+/// An Orchard action description, its proof and signature are stored in the transaction.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ActionDescription {
+    /// The value commitment.
+    #[serde(with = "as_hex")]
     cv: [u8; 32],
+    #[serde(with = "as_hex")]
     nullifier: [u8; 32],
+    /// The randomized public key.
+    #[serde(with = "as_hex")]
     rk: [u8; 32],
+    /// The x-coordinate of the note commitment.
+    #[serde(with = "as_hex")]
     cmx: [u8; 32],
+    #[serde(with = "as_hex")]
     ephemeral_key: [u8; 32],
+    #[serde(with = "as_hex")]
     enc_ciphertext: [u8; 580],
+    #[serde(with = "as_hex")]
     out_ciphertext: [u8; 80],
 }
 
+impl ActionDescription {
+    /// Returns a builder for an action description.
+    pub fn builder() -> ActionDescriptionBuilder {
+        ActionDescriptionBuilder {
+            action_description: Self::zeroed(),
+        }
+    }
+
+    /// Returns an action description with all its fields zeroed.
+    pub fn zeroed() -> Self {
+        Self {
+            cv: [0; 32],
+            nullifier: [0; 32],
+            rk: [0; 32],
+            cmx: [0; 32],
+            ephemeral_key: [0; 32],
+            enc_ciphertext: [0; 580],
+            out_ciphertext: [0; 80],
+        }
+    }
+
+    /// Returns the value commitment.
+    pub fn cv(&self) -> &[u8; 32] {
+        &self.cv
+    }
+
+    /// Returns the nullifier.
+    pub fn nullifier(&self) -> &[u8; 32] {
+        &self.nullifier
+    }
+
+    /// Returns the randomized public key.
+    pub fn rk(&self) -> &[u8; 32] {
+        &self.rk
+    }
+
+    /// Returns the x-coordinate of the note commitment.
+    pub fn cmx(&self) -> &[u8; 32] {
+        &self.cmx
+    }
+
+    /// Returns the ephemeral public key.
+    pub fn ephemeral_key(&self) -> &[u8; 32] {
+        &self.ephemeral_key
+    }
+
+    /// Returns the encrypted note.
+    pub fn enc_ciphertext(&self) -> &[u8; 580] {
+        &self.enc_ciphertext
+    }
+
+    /// Returns the encrypted outgoing cipher key and note.
+    pub fn out_ciphertext(&self) -> &[u8; 80] {
+        &self.out_ciphertext
+    }
+}
+
 impl Codec for ActionDescription {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         buffer.put_slice(&self.cv);
@@ -1026,7 +3058,7 @@ impl Codec for ActionDescription {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
+    fn decode_with<B: Buf>(bytes: &mut B, _options: DecodeOptions) -> io::Result<Self> {
         let cv = read_n_bytes(bytes)?;
         let nullifier = read_n_bytes(bytes)?;
         let rk = read_n_bytes(bytes)?;
@@ -1047,11 +3079,70 @@ impl Codec for ActionDescription {
     }
 }
 
+/// A builder for [`ActionDescription`], every field is zeroed by default.
+#[derive(Debug, Clone)]
+pub struct ActionDescriptionBuilder {
+    action_description: ActionDescription,
+}
+
+impl ActionDescriptionBuilder {
+    /// Sets the value commitment, zeroed by default.
+    pub fn with_cv(mut self, cv: [u8; 32]) -> Self {
+        self.action_description.cv = cv;
+        self
+    }
+
+    /// Sets the nullifier, zeroed by default.
+    pub fn with_nullifier(mut self, nullifier: [u8; 32]) -> Self {
+        self.action_description.nullifier = nullifier;
+        self
+    }
+
+    /// Sets the randomized public key, zeroed by default.
+    pub fn with_rk(mut self, rk: [u8; 32]) -> Self {
+        self.action_description.rk = rk;
+        self
+    }
+
+    /// Sets the x-coordinate of the note commitment, zeroed by default.
+    pub fn with_cmx(mut self, cmx: [u8; 32]) -> Self {
+        self.action_description.cmx = cmx;
+        self
+    }
+
+    /// Sets the ephemeral public key, zeroed by default.
+    pub fn with_ephemeral_key(mut self, ephemeral_key: [u8; 32]) -> Self {
+        self.action_description.ephemeral_key = ephemeral_key;
+        self
+    }
+
+    /// Sets the encrypted note, zeroed by default.
+    pub fn with_enc_ciphertext(mut self, enc_ciphertext: [u8; 580]) -> Self {
+        self.action_description.enc_ciphertext = enc_ciphertext;
+        self
+    }
+
+    /// Sets the encrypted outgoing cipher key and note, zeroed by default.
+    pub fn with_out_ciphertext(mut self, out_ciphertext: [u8; 80]) -> Self {
+        self.action_description.out_ciphertext = out_ciphertext;
+        self
+    }
+
+    /// Creates the [`ActionDescription`].
+    pub fn build(self) -> ActionDescription {
+        self.action_description
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use io::Cursor;
 
     use super::*;
+    use crate::{
+        protocol::{network::Network, payload::block::Block},
+        vectors::*,
+    };
 
     #[test]
     #[ignore]
@@ -1162,4 +3253,417 @@ mod tests {
 
         assert_eq!(tx_v5, Tx::decode(&mut Cursor::new(&bytes)).unwrap());
     }
+
+    #[test]
+    #[ignore]
+    fn network_upgrade_validation() {
+        let network = Network::Testnet;
+        let nu5_height = NetworkUpgrade::Nu5.activation_height(network).unwrap();
+
+        let v5: Tx = TxV5::builder()
+            .with_branch_for_height(network, nu5_height)
+            .build()
+            .unwrap()
+            .into();
+        v5.validate_network_upgrade(network, nu5_height).unwrap();
+        assert!(v5
+            .validate_network_upgrade(network, nu5_height - 1)
+            .is_err());
+
+        let canopy = TxV5::builder()
+            .with_branch_for_height(network, nu5_height - 1)
+            .build()
+            .unwrap();
+        assert_eq!(canopy.consensus_branch, NetworkUpgrade::Canopy.branch_id());
+        assert!(Tx::from(canopy)
+            .validate_network_upgrade(network, nu5_height)
+            .is_err());
+
+        let v4: Tx = TxV4::builder().build().into();
+        v4.validate_network_upgrade(network, nu5_height).unwrap();
+        let wrong_group: Tx = TxV4::builder()
+            .with_group_id(OVERWINTER_VERSION_GROUP_ID)
+            .build()
+            .into();
+        assert!(wrong_group
+            .validate_network_upgrade(network, nu5_height)
+            .is_err());
+
+        // Regtest nodes don't activate any upgrades by default.
+        let v1: Tx = TxV1::builder().build().into();
+        v1.validate_network_upgrade(Network::Regtest, 1_000_000)
+            .unwrap();
+        assert!(v4
+            .validate_network_upgrade(Network::Regtest, 1_000_000)
+            .is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn decoding_with_network_validates_upgrade() {
+        let network = Network::Testnet;
+        let options = DecodeOptions::default().with_network(network);
+        let nu5_height = NetworkUpgrade::Nu5.activation_height(network).unwrap();
+
+        let valid: [Tx; 3] = [
+            TxV1::builder().build().into(),
+            TxV4::builder().build().into(),
+            TxV5::builder()
+                .with_branch_for_height(network, nu5_height)
+                .build()
+                .unwrap()
+                .into(),
+        ];
+        for tx in valid {
+            assert_eq!(
+                Tx::decode_with(&mut &encoded(&tx)[..], options).unwrap(),
+                tx
+            );
+        }
+
+        let invalid: [Tx; 3] = [
+            TxV4::builder()
+                .with_group_id(OVERWINTER_VERSION_GROUP_ID)
+                .build()
+                .into(),
+            TxV5::builder()
+                .with_branch_for_height(network, nu5_height - 1)
+                .build()
+                .unwrap()
+                .into(),
+            TxV5::builder()
+                .with_consensus_branch(0xdead_beef)
+                .build()
+                .unwrap()
+                .into(),
+        ];
+        for tx in invalid {
+            let bytes = encoded(&tx);
+            assert_eq!(Tx::decode(&mut &bytes[..]).unwrap(), tx);
+
+            let err = Tx::decode_with(&mut &bytes[..], options).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+
+        // Regtest nodes don't activate any upgrades by default, only those set with `-nuparams`.
+        let v4: Tx = TxV4::builder().build().into();
+        let options = DecodeOptions::default().with_network(Network::Regtest);
+        assert!(Tx::decode_with(&mut &encoded(&v4)[..], options).is_err());
+
+        let heights = ActivationHeights::new(Network::Regtest)
+            .with_upgrade(NetworkUpgrade::Overwinter, 1)
+            .with_upgrade(NetworkUpgrade::Sapling, 1)
+            .with_upgrade(NetworkUpgrade::Nu5, 1);
+        let options = DecodeOptions::default().with_activation_heights(heights);
+        let v5: Tx = TxV5::builder()
+            .with_branch_for_height(heights, 1)
+            .build()
+            .unwrap()
+            .into();
+        for tx in [v4, v5] {
+            assert_eq!(
+                Tx::decode_with(&mut &encoded(&tx)[..], options).unwrap(),
+                tx
+            );
+        }
+
+        // Transactions of the latest upgrade, NU6, claim its branch ID.
+        let nu6_height = NetworkUpgrade::Nu6.activation_height(network).unwrap();
+        let nu6: Tx = TxV5::builder()
+            .with_branch_for_height(network, nu6_height)
+            .build()
+            .unwrap()
+            .into();
+        let options = DecodeOptions::default().with_network(network);
+        assert_eq!(
+            Tx::decode_with(&mut &encoded(&nu6)[..], options).unwrap(),
+            nu6
+        );
+    }
+
+    fn round_trip(tx: Tx) {
+        let mut bytes = Vec::new();
+        tx.encode(&mut bytes).unwrap();
+
+        assert_eq!(tx, Tx::decode(&mut Cursor::new(&bytes)).unwrap());
+    }
+
+    fn transparent() -> (Vec<TxIn>, Vec<TxOut>) {
+        let tx_in = (0..3)
+            .map(|i| TxIn::new(Hash::new([i as u8; 32]), i, vec![0xab; 300], u32::MAX))
+            .collect();
+        let tx_out = vec![
+            TxOut::new(50_000, vec![0x76, 0xa9]),
+            TxOut::new(0, Vec::new()),
+        ];
+
+        (tx_in, tx_out)
+    }
+
+    #[test]
+    #[ignore]
+    fn built_transactions_round_trip() {
+        let (tx_in, tx_out) = transparent();
+
+        round_trip(
+            TxV1::builder()
+                .with_tx_in(tx_in.clone())
+                .with_tx_out(tx_out.clone())
+                .with_lock_time(1)
+                .build()
+                .into(),
+        );
+        round_trip(
+            TxV2::builder()
+                .with_tx_in(tx_in.clone())
+                .with_join_split(vec![JoinSplit::zeroed_bctv14(); 2])
+                .with_join_split_sig([1; 64])
+                .build()
+                .into(),
+        );
+        round_trip(
+            TxV3::builder()
+                .with_tx_out(tx_out.clone())
+                .with_expiry_height(u32::MAX)
+                .with_join_split(vec![JoinSplit::zeroed_bctv14()])
+                .build()
+                .into(),
+        );
+        round_trip(
+            TxV4::builder()
+                .with_tx_in(tx_in.clone())
+                .with_tx_out(tx_out.clone())
+                .with_value_balance_sapling(-1)
+                .with_spends_sapling(vec![SpendDescriptionV4::zeroed()])
+                .with_outputs_sapling(vec![OutputDescriptionV4::zeroed(); 2])
+                .with_join_split(vec![JoinSplit::zeroed_groth16()])
+                .build()
+                .into(),
+        );
+        round_trip(
+            TxV5::builder()
+                .with_tx_in(tx_in)
+                .with_tx_out(tx_out)
+                .with_spends_sapling(vec![SpendDescriptionV5::zeroed(); 2])
+                .with_spend_proofs_sapling(vec![[0xab; 192]; 2])
+                .with_spend_auth_sigs_sapling(vec![[1; 64]; 2])
+                .with_outputs_sapling(vec![OutputDescriptionV5::zeroed(); 2])
+                .with_output_proofs_sapling(vec![[0xef; 192]; 2])
+                .with_actions_orchard(vec![ActionDescription::zeroed(); 2])
+                .with_proofs_orchard(vec![0xcd; 100])
+                .with_auth_sigs_orchard(vec![[2; 64]; 2])
+                .build()
+                .unwrap()
+                .into(),
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn v5_output_proofs_are_decoded_per_output() {
+        // One output proof per output, regardless of the number of spends.
+        for (n_spends, n_outputs) in [(1, 3), (0, 2), (2, 0)] {
+            let tx = TxV5::builder()
+                .with_spends_sapling(vec![SpendDescriptionV5::zeroed(); n_spends])
+                .with_spend_proofs_sapling(vec![[0xcd; 192]; n_spends])
+                .with_spend_auth_sigs_sapling(vec![[1; 64]; n_spends])
+                .with_outputs_sapling(vec![OutputDescriptionV5::zeroed(); n_outputs])
+                .with_output_proofs_sapling(vec![[0xab; 192]; n_outputs])
+                .build()
+                .unwrap();
+            assert_eq!(tx.output_proofs_sapling().len(), n_outputs);
+
+            round_trip(tx.into());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn builders_keep_optional_fields_consistent() {
+        let tx = TxV4::builder().with_binding_sig_sapling([1; 64]).build();
+        assert_eq!(tx.group_id(), SAPLING_VERSION_GROUP_ID);
+        assert_eq!(tx.binding_sig_sapling(), None);
+        assert_eq!(tx.join_split_pub_key(), None);
+
+        let builder = TxV5::builder()
+            .with_outputs_sapling(vec![OutputDescriptionV5::zeroed(); 2])
+            .with_output_proofs_sapling(vec![[0; 192]; 2])
+            .with_anchor_sapling([2; 32]);
+        let tx = builder.clone().build().unwrap();
+        assert_eq!(tx.consensus_branch(), NU5_BRANCH_ID);
+        assert_eq!(tx.value_balance_sapling(), Some(0));
+        assert_eq!(tx.anchor_sapling(), None);
+        assert_eq!(tx.output_proofs_sapling().len(), 2);
+        assert_eq!(tx.flags_orchard(), None);
+
+        // Proofs and signatures must match their descriptions one to one.
+        for builder in [
+            builder.clone().with_spend_auth_sigs_sapling(vec![[1; 64]]),
+            builder.clone().with_output_proofs_sapling(vec![[0; 192]]),
+            builder.with_actions_orchard(vec![ActionDescription::zeroed()]),
+        ] {
+            assert_eq!(
+                builder.build().unwrap_err().kind(),
+                io::ErrorKind::InvalidInput
+            );
+        }
+
+        let tx_in = TxIn::new(Hash::zeroed(), 7, vec![1, 2, 3], 0);
+        assert_eq!(tx_in.prev_out_index(), 7);
+        assert_eq!(tx_in.script(), &[1, 2, 3]);
+    }
+
+    #[test]
+    #[ignore]
+    fn component_builders_round_trip() {
+        let join_split = JoinSplit::groth16_builder()
+            .with_pub_old(1_000)
+            .with_pub_new(2_000)
+            .with_anchor([1; 32])
+            .with_nullifiers([2; 64])
+            .with_commitments([3; 64])
+            .with_ephemeral_key([4; 32])
+            .with_random_seed([5; 32])
+            .with_vmacs([6; 64])
+            .with_zkproof(Zkproof::Groth16([7; 192]))
+            .with_enc_ciphertexts([8; 1202])
+            .build();
+        assert_eq!(join_split.pub_old(), 1_000);
+        assert_eq!(join_split.pub_new(), 2_000);
+        assert_eq!(join_split.nullifiers(), &[2; 64]);
+        assert_eq!(join_split.zkproof(), &Zkproof::Groth16([7; 192]));
+
+        let spend_v4 = SpendDescriptionV4::builder()
+            .with_cv([1; 32])
+            .with_anchor([2; 32])
+            .with_nullifier([3; 32])
+            .with_rk([4; 32])
+            .with_zkproof([5; 192])
+            .with_spend_auth_sig([6; 64])
+            .build();
+        assert_eq!(spend_v4.nullifier(), &[3; 32]);
+        let output_v4 = OutputDescriptionV4::builder()
+            .with_cv([1; 32])
+            .with_cmu([2; 32])
+            .with_ephemeral_key([3; 32])
+            .with_enc_ciphertext([4; 580])
+            .with_out_ciphertext([5; 80])
+            .with_zkproof([6; 192])
+            .build();
+        assert_eq!(output_v4.cmu(), &[2; 32]);
+
+        // The same nullifier is spent twice, in both pools.
+        round_trip(
+            TxV4::builder()
+                .with_spends_sapling(vec![spend_v4.clone(); 2])
+                .with_outputs_sapling(vec![output_v4])
+                .with_join_split(vec![join_split])
+                .build()
+                .into(),
+        );
+        round_trip(
+            TxV2::builder()
+                .with_join_split(vec![JoinSplit::bctv14_builder()
+                    .with_pub_old(1_000)
+                    .with_zkproof(Zkproof::BCTV14([9; 296]))
+                    .build()])
+                .build()
+                .into(),
+        );
+
+        let spend_v5 = SpendDescriptionV5::builder()
+            .with_cv([1; 32])
+            .with_nullifier([2; 32])
+            .with_rk([3; 32])
+            .build();
+        assert_eq!(spend_v5.rk(), &[3; 32]);
+        let output_v5 = OutputDescriptionV5::builder()
+            .with_cv([1; 32])
+            .with_cmu([2; 32])
+            .with_ephemeral_key([3; 32])
+            .with_enc_ciphertext([4; 580])
+            .with_out_ciphertext([5; 80])
+            .build();
+        assert_eq!(output_v5.out_ciphertext(), &[5; 80]);
+        let action = ActionDescription::builder()
+            .with_cv([1; 32])
+            .with_nullifier([2; 32])
+            .with_rk([3; 32])
+            .with_cmx([4; 32])
+            .with_ephemeral_key([5; 32])
+            .with_enc_ciphertext([6; 580])
+            .with_out_ciphertext([7; 80])
+            .build();
+        assert_eq!(action.cmx(), &[4; 32]);
+
+        let tx = TxV5::builder()
+            .with_spends_sapling(vec![spend_v5])
+            .with_spend_proofs_sapling(vec![[8; 192]])
+            .with_spend_auth_sigs_sapling(vec![[9; 64]])
+            .with_outputs_sapling(vec![output_v5])
+            .with_output_proofs_sapling(vec![[10; 192]])
+            .with_actions_orchard(vec![action; 2])
+            .with_proofs_orchard(vec![11; 64])
+            .with_auth_sigs_orchard(vec![[12; 64]; 2])
+            .build()
+            .unwrap();
+        round_trip(tx.into());
+    }
+
+    #[test]
+    #[ignore]
+    fn txid_commits_to_effecting_data_only() {
+        let (tx_in, tx_out) = transparent();
+        let builder = TxV5::builder()
+            .with_tx_in(tx_in)
+            .with_tx_out(tx_out)
+            .with_spends_sapling(vec![SpendDescriptionV5::zeroed()])
+            .with_spend_proofs_sapling(vec![[0; 192]])
+            .with_spend_auth_sigs_sapling(vec![[0; 64]])
+            .with_actions_orchard(vec![ActionDescription::zeroed(); 2])
+            .with_auth_sigs_orchard(vec![[0; 64]; 2]);
+
+        let tx = Tx::from(builder.clone().build().unwrap());
+        let resigned = Tx::from(
+            builder
+                .clone()
+                .with_binding_sig_sapling([1; 64])
+                .with_binding_sig_orchard([2; 64])
+                .build()
+                .unwrap(),
+        );
+        let changed = Tx::from(builder.with_lock_time(1).build().unwrap());
+
+        assert_eq!(tx.txid().unwrap(), resigned.txid().unwrap());
+        assert_ne!(tx.auth_digest(), resigned.auth_digest());
+        assert_ne!(tx.txid().unwrap(), changed.txid().unwrap());
+        assert_eq!(tx.auth_digest(), changed.auth_digest());
+        assert_ne!(tx.txid().unwrap(), tx.double_sha256().unwrap());
+        assert_eq!(
+            tx.inv_hash(),
+            InvHash::new(ObjectKind::Tx, tx.txid().unwrap())
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn txids_match_block_merkle_roots() {
+        // Single transaction blocks, whose merkle root is the txid of their coinbase.
+        for bytes in [
+            &BLOCK_TESTNET_1_599_199_BYTES[..],
+            &BLOCK_TESTNET_1_599_200_BYTES[..],
+            &BLOCK_TESTNET_1_599_201_BYTES[..],
+        ] {
+            let block = Block::decode(&mut Cursor::new(bytes)).unwrap();
+            assert_eq!(block.txs.len(), 1);
+
+            let tx = &block.txs[0];
+            assert_eq!(tx.txid().unwrap(), block.header.merkle_root);
+
+            match tx {
+                Tx::V5(_) => assert_ne!(tx.auth_digest(), Hash::new([0xff; 32])),
+                _ => assert_eq!(tx.auth_digest(), Hash::new([0xff; 32])),
+            }
+        }
+    }
 }
@@ -3,20 +3,29 @@
 use std::{io, net::SocketAddr};
 
 use bytes::{Buf, BufMut};
+use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
-use crate::protocol::payload::{
-    addr::NetworkAddr, codec::Codec, read_n_bytes, read_timestamp, Nonce, ProtocolVersion, VarStr,
+use crate::protocol::{
+    message::constants::MAX_SUBVERSION_LENGTH,
+    payload::{
+        addr::NetworkAddr,
+        codec::{Codec, DecodeOptions},
+        read_n_bytes, read_timestamp,
+        serde_helpers::as_rfc3339,
+        DecodeContext, Nonce, ProtocolVersion, Services, VarStr,
+    },
 };
 
 /// A version payload.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Version {
     /// The protocol version of the sender.
     pub version: ProtocolVersion,
     /// The services supported by the sender.
-    pub services: u64,
+    pub services: Services,
     /// The timestamp of the message.
+    #[serde(with = "as_rfc3339")]
     pub timestamp: OffsetDateTime,
     /// The receiving address of the message.
     pub addr_recv: NetworkAddr,
@@ -38,16 +47,16 @@ impl Version {
     pub fn new(addr_recv: SocketAddr, addr_from: SocketAddr) -> Self {
         Self {
             version: ProtocolVersion::current(),
-            services: 1,
+            services: Services::NODE_NETWORK,
             timestamp: OffsetDateTime::now_utc(),
             addr_recv: NetworkAddr {
                 last_seen: None,
-                services: 1,
+                services: Services::NODE_NETWORK,
                 addr: addr_recv,
             },
             addr_from: NetworkAddr {
                 last_seen: None,
-                services: 1,
+                services: Services::NODE_NETWORK,
                 addr: addr_from,
             },
             nonce: Nonce::default(),
@@ -62,12 +71,19 @@ impl Version {
         self.version = ProtocolVersion(version);
         self
     }
+
+    /// Sets the services advertised by the sender, in both `services` and `addr_from`.
+    pub fn with_services(mut self, services: Services) -> Self {
+        self.services = services;
+        self.addr_from.services = services;
+        self
+    }
 }
 
 impl Codec for Version {
     fn encode<B: BufMut>(&self, buffer: &mut B) -> io::Result<()> {
         self.version.encode(buffer)?;
-        buffer.put_u64_le(self.services);
+        self.services.encode(buffer)?;
         buffer.put_i64_le(self.timestamp.unix_timestamp());
 
         self.addr_recv.encode_without_timestamp(buffer)?;
@@ -81,19 +97,20 @@ impl Codec for Version {
         Ok(())
     }
 
-    fn decode<B: Buf>(bytes: &mut B) -> io::Result<Self> {
-        let version = ProtocolVersion::decode(bytes)?;
-        let services = u64::from_le_bytes(read_n_bytes(bytes)?);
-        let timestamp = read_timestamp(bytes)?;
+    fn decode_with<B: Buf>(bytes: &mut B, options: DecodeOptions) -> io::Result<Self> {
+        let version = ProtocolVersion::decode_with(bytes, options).field("version")?;
+        let services = Services::decode_with(bytes, options).field("services")?;
+        let timestamp = read_timestamp(bytes).field("timestamp")?;
 
-        let addr_recv = NetworkAddr::decode_without_timestamp(bytes)?;
-        let addr_from = NetworkAddr::decode_without_timestamp(bytes)?;
+        let addr_recv = NetworkAddr::decode_without_timestamp(bytes, options).field("addr_recv")?;
+        let addr_from = NetworkAddr::decode_without_timestamp(bytes, options).field("addr_from")?;
 
-        let nonce = Nonce::decode(bytes)?;
-        let user_agent = VarStr::decode(bytes)?;
+        let nonce = Nonce::decode_with(bytes, options).field("nonce")?;
+        let user_agent =
+            VarStr::decode_bounded(bytes, MAX_SUBVERSION_LENGTH, options).field("user_agent")?;
 
-        let start_height = i32::from_le_bytes(read_n_bytes(bytes)?);
-        let relay = u8::from_le_bytes(read_n_bytes(bytes)?) != 0;
+        let start_height = i32::from_le_bytes(read_n_bytes(bytes).field("start_height")?);
+        let relay = u8::from_le_bytes(read_n_bytes(bytes).field("relay")?) != 0;
 
         Ok(Self {
             version,
@@ -10,12 +10,13 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
-use crate::setup::node::Action;
+use crate::{protocol::network::Network, setup::node::Action};
 
 // The names of the files the node configurations will be written to.
 const ZEBRA_CONFIG: &str = "zebra.toml";
 const ZCASHD_CONFIG: &str = "zcash.conf";
 const ZCASHD_CACHE: &str = "testnet3";
+const ZCASHD_REGTEST_CACHE: &str = "regtest";
 
 // Ziggurat's configuration directory and file. Caches are written to this directory.
 const CONFIG: &str = ".ziggurat";
@@ -46,6 +47,8 @@ pub(super) struct NodeConfig {
     pub(super) initial_peers: HashSet<String>,
     /// The initial max number of peer connections to allow.
     pub(super) max_peers: usize,
+    /// Setting this option to true will run the node on regtest instead of testnet.
+    pub(super) regtest: bool,
     /// Setting this option to true will enable node logging to stdout.
     pub(super) log_to_stdout: bool,
     /// Defines the initial action to take once the node has started.
@@ -65,10 +68,20 @@ impl NodeConfig {
             local_addr,
             initial_peers: HashSet::new(),
             max_peers: 50,
+            regtest: false,
             log_to_stdout: false,
             initial_action: Action::None,
         })
     }
+
+    /// Returns the network the node runs on.
+    pub(super) fn network(&self) -> Network {
+        if self.regtest {
+            Network::Regtest
+        } else {
+            Network::Testnet
+        }
+    }
 }
 
 /// Describes the node kind, currently supports the two known variants.
@@ -88,10 +101,11 @@ impl NodeKind {
         }
     }
 
-    pub(super) fn cache_path(&self, wrapping_dir: &Path) -> Option<PathBuf> {
-        match self {
-            NodeKind::Zebra => None,
-            NodeKind::Zcashd => Some(wrapping_dir.join(ZCASHD_CACHE)),
+    pub(super) fn cache_path(&self, wrapping_dir: &Path, network: Network) -> Option<PathBuf> {
+        match (self, network) {
+            (NodeKind::Zebra, _) => None,
+            (NodeKind::Zcashd, Network::Regtest) => Some(wrapping_dir.join(ZCASHD_REGTEST_CACHE)),
+            (NodeKind::Zcashd, _) => Some(wrapping_dir.join(ZCASHD_CACHE)),
         }
     }
 }
@@ -217,8 +231,9 @@ pub(super) struct ZcashdConfigFile;
 
 impl ZcashdConfigFile {
     pub(super) fn generate(config: &NodeConfig) -> String {
+        let network = if config.regtest { "regtest" } else { "testnet" };
         let mut contents = format!(
-            "testnet=1\nwhitebind={}\nmaxconnections={}\n",
+            "{network}=1\nwhitebind={}\nmaxconnections={}\n",
             config.local_addr, config.max_peers
         );
 
@@ -1,6 +1,7 @@
 //! High level APIs and types for node setup and teardown.
 
 use std::{
+    collections::HashMap,
     fs, io,
     net::SocketAddr,
     process::{Child, Command, ExitStatus, Stdio},
@@ -10,9 +11,14 @@ use std::{
 use tracing::error;
 
 use crate::{
-    protocol::payload::{
-        block::{Block, Headers},
-        Hash, Inv,
+    protocol::{
+        message::{constants::MAX_HEADERS_RESULTS, Message},
+        network::Network,
+        payload::{
+            block::{Block, Headers, LocatorHashes},
+            inv::ObjectKind,
+            Hash, Inv,
+        },
     },
     setup::config::{NodeConfig, NodeKind, NodeMetaData, ZcashdConfigFile, ZebraConfigFile},
     tools::{
@@ -41,6 +47,14 @@ pub enum Action {
         /// from [Block::initial_testnet_blocks].
         usize,
     ),
+    /// Seeds the node with a chain of blocks, in the same way as [`Action::SeedWithTestnetBlocks`].
+    ///
+    /// The chain starts with the genesis block of the node's network, which isn't sent. Mined
+    /// chains (e.g. from [`Chain`](crate::tools::miner::Chain)) lift the limit on the number of
+    /// blocks, as long as the node runs on [regtest](Node::regtest).
+    ///
+    /// **Warning**: this currently only works for zcashd type nodes.
+    SeedWithBlocks(Vec<Block>),
 }
 
 /// Represents an instance of a node, its configuration and setup/teardown intricacies.
@@ -97,6 +111,17 @@ impl Node {
         self
     }
 
+    /// Sets whether the node runs on regtest rather than testnet, `false` by default.
+    pub fn regtest(&mut self, regtest: bool) -> &mut Self {
+        self.config.regtest = regtest;
+        self
+    }
+
+    /// Returns the network the node runs on.
+    pub fn network(&self) -> Network {
+        self.config.network()
+    }
+
     /// Sets whether to log the node's output to Ziggurat's output stream.
     pub fn log_to_stdout(&mut self, log_to_stdout: bool) -> &mut Self {
         self.config.log_to_stdout = log_to_stdout;
@@ -121,9 +146,12 @@ impl Node {
         // Setup the listener if there is some initial action required
         let synthetic_node = match self.config.initial_action {
             Action::None => None,
-            Action::WaitForConnection | Action::SeedWithTestnetBlocks(_) => {
+            Action::WaitForConnection
+            | Action::SeedWithTestnetBlocks(_)
+            | Action::SeedWithBlocks(_) => {
                 // Start a synthetic node to perform the initial actions.
                 let synthetic_node = SyntheticNode::builder()
+                    .with_network(self.config.network())
                     .with_full_handshake()
                     .with_message_filter(
                         MessageFilter::with_all_auto_reply()
@@ -178,72 +206,22 @@ impl Node {
                 // The synthetic node will accept the connection and handshake by itself.
                 wait_until!(LONG_TIMEOUT, synthetic_node.num_connected() == 1);
             }
-            Action::SeedWithTestnetBlocks(_) if self.meta.kind == NodeKind::Zebra => {
+            Action::SeedWithTestnetBlocks(_) | Action::SeedWithBlocks(_)
+                if self.meta.kind == NodeKind::Zebra =>
+            {
                 unimplemented!("zebra doesn't support block seeding");
             }
             Action::SeedWithTestnetBlocks(block_count) => {
-                use crate::protocol::message::Message;
-
-                let genesis_block = Block::testnet_genesis();
-                // initial blocks, skipping genesis as it doesn't get sent
-                let blocks = Block::initial_testnet_blocks()
+                // The genesis block is kept as it anchors the chain, it doesn't get sent.
+                let chain: Vec<_> = Block::initial_testnet_blocks()
                     .into_iter()
-                    .take(block_count)
-                    .skip(1)
-                    .collect::<Vec<_>>();
-
-                // respond to GetHeaders(Block[0])
-                let source = match synthetic_node.recv_message_timeout(LONG_TIMEOUT).await? {
-                    (source, Message::GetHeaders(locations)) => {
-                        // The request should be from the genesis hash onwards,
-                        // i.e. locator_hash = [genesis.hash], stop_hash = [0]
-                        assert_eq!(
-                            locations.block_locator_hashes,
-                            vec![genesis_block.double_sha256().unwrap()]
-                        );
-                        assert_eq!(locations.hash_stop, Hash::zeroed());
-
-                        // Reply with headers for the initial block headers
-                        let headers = blocks.iter().map(|block| block.header.clone()).collect();
-                        synthetic_node.unicast(source, Message::Headers(Headers::new(headers)))?;
-
-                        source
-                    }
+                    .take(block_count.max(1))
+                    .collect();
 
-                    (_, msg) => {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            format!("Expected GetHeaders but got: {msg:?}"),
-                        ));
-                    }
-                };
-
-                // respond to GetData(inv) for the initial blocks
-                match synthetic_node.recv_message_timeout(LONG_TIMEOUT).await? {
-                    (source, Message::GetData(inv)) => {
-                        // The request must be for the initial blocks
-                        let inv_hashes = blocks.iter().map(|block| block.inv_hash()).collect();
-                        let expected = Inv::new(inv_hashes);
-                        assert_eq!(inv, expected);
-
-                        // Send the blocks
-                        for block in blocks {
-                            synthetic_node.unicast(source, Message::Block(Box::new(block)))?;
-                        }
-                    }
-
-                    (_, msg) => {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            format!("Expected GetData but got: {msg:?}"),
-                        ))
-                    }
-                }
-
-                // Check that the node has received and processed all previous messages.
-                synthetic_node
-                    .ping_pong_timeout(source, LONG_TIMEOUT)
-                    .await?;
+                seed_blocks(&mut synthetic_node, &chain).await?;
+            }
+            Action::SeedWithBlocks(ref chain) => {
+                seed_blocks(&mut synthetic_node, chain).await?;
             }
         }
 
@@ -316,6 +294,12 @@ impl Node {
     fn generate_config_file(&self) -> io::Result<()> {
         let config_file_path = self.meta.kind.config_filepath(&self.config.path);
         let content = match self.meta.kind {
+            NodeKind::Zebra if self.config.regtest => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "zebra doesn't support regtest",
+                ));
+            }
             NodeKind::Zebra => ZebraConfigFile::generate(&self.config)
                 .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
             NodeKind::Zcashd => ZcashdConfigFile::generate(&self.config),
@@ -340,7 +324,11 @@ impl Node {
 
     fn cleanup_cache(&self) -> io::Result<()> {
         // Zebra doesn't currently use a cache as it's configured in ephemeral mode.
-        if let Some(path) = self.meta.kind.cache_path(&self.config.path) {
+        if let Some(path) = self
+            .meta
+            .kind
+            .cache_path(&self.config.path, self.config.network())
+        {
             if let Err(e) = fs::remove_dir_all(path) {
                 // Directory may not exist, so we let that error through
                 if e.kind() != std::io::ErrorKind::NotFound {
@@ -361,3 +349,224 @@ impl Drop for Node {
         }
     }
 }
+
+/// Seeds a node with `chain`, by replying to its `GetHeaders` and `GetData` requests until it
+/// has been sent the tip.
+async fn seed_blocks(synthetic_node: &mut SyntheticNode, chain: &[Block]) -> io::Result<()> {
+    let mut seeder = BlockSeeder::new(chain)?;
+    let mut source = None;
+
+    while !seeder.is_done() {
+        let (addr, message) = synthetic_node.recv_message_timeout(LONG_TIMEOUT).await?;
+
+        for response in seeder.respond(&message)? {
+            synthetic_node.unicast(addr, response)?;
+        }
+
+        source = Some(addr);
+    }
+
+    // Check that the node has received and processed all previous messages.
+    if let Some(source) = source {
+        synthetic_node
+            .ping_pong_timeout(source, LONG_TIMEOUT)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Serves a chain of blocks to a node syncing from it.
+///
+/// The node asks for headers in batches of at most [`MAX_HEADERS_RESULTS`], sending a new
+/// `GetHeaders` after each full batch, and for blocks a window at a time (`zcashd` keeps at most
+/// 16 in flight), so requests are answered one by one until the tip has been sent.
+struct BlockSeeder<'a> {
+    chain: &'a [Block],
+    heights: HashMap<Hash, usize>,
+    tip_sent: bool,
+}
+
+impl<'a> BlockSeeder<'a> {
+    /// Creates a seeder for `chain`, which starts with the genesis block of the node's network.
+    fn new(chain: &'a [Block]) -> io::Result<Self> {
+        if chain.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Expected a chain starting with the genesis block",
+            ));
+        }
+
+        let heights = chain
+            .iter()
+            .enumerate()
+            .map(|(height, block)| Ok((block.double_sha256()?, height)))
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            chain,
+            heights,
+            // The genesis block is never sent.
+            tip_sent: chain.len() == 1,
+        })
+    }
+
+    /// Returns `true` once the tip of the chain has been sent.
+    fn is_done(&self) -> bool {
+        self.tip_sent
+    }
+
+    /// Returns the replies to `message`, other messages than `GetHeaders` and `GetData` are
+    /// ignored.
+    ///
+    /// Errors if the node asks for headers from a fork or for blocks outside the chain.
+    fn respond(&mut self, message: &Message) -> io::Result<Vec<Message>> {
+        match message {
+            Message::GetHeaders(locator) => Ok(vec![Message::Headers(self.headers(locator)?)]),
+            Message::GetData(inv) => self.blocks(inv),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the headers following the first locator hash in the chain, up to the stop hash.
+    fn headers(&self, locator: &LocatorHashes) -> io::Result<Headers> {
+        let start = locator
+            .block_locator_hashes
+            .iter()
+            .find_map(|hash| self.heights.get(hash))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Expected a GetHeaders locator in the chain, got: {:?}",
+                        locator.block_locator_hashes
+                    ),
+                )
+            })?;
+
+        let mut headers = Vec::new();
+        for block in self.chain[start + 1..].iter().take(MAX_HEADERS_RESULTS) {
+            headers.push(block.header.clone());
+
+            if locator.hash_stop != Hash::zeroed() && block.double_sha256()? == locator.hash_stop {
+                break;
+            }
+        }
+
+        Ok(Headers::new(headers))
+    }
+
+    /// Returns the requested blocks.
+    fn blocks(&mut self, inv: &Inv) -> io::Result<Vec<Message>> {
+        let tip = self.chain.len() - 1;
+
+        inv.inventory
+            .iter()
+            .map(|inv_hash| {
+                let height = match inv_hash.kind() {
+                    ObjectKind::Block => self.heights.get(&inv_hash.hash()),
+                    _ => None,
+                };
+
+                match height {
+                    Some(&height) => {
+                        self.tip_sent |= height == tip;
+                        Ok(Message::Block(Box::new(self.chain[height].clone())))
+                    }
+                    None => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Expected GetData for blocks in the chain, got: {inv_hash:?}"),
+                    )),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::protocol::payload::inv::InvHash;
+
+    /// Returns a chain of `len` blocks after the testnet genesis block, linked but not mined.
+    fn linked_chain(len: usize) -> Vec<Block> {
+        let mut chain = vec![Block::testnet_genesis()];
+        for _ in 0..len {
+            let mut block = Block::testnet_1();
+            block.header.prev_block = chain.last().unwrap().double_sha256().unwrap();
+            chain.push(block);
+        }
+
+        chain
+    }
+
+    #[test]
+    #[ignore]
+    fn seeds_chains_longer_than_a_block_window() {
+        let chain = linked_chain(2 * MAX_HEADERS_RESULTS + 20);
+        let mut seeder = BlockSeeder::new(&chain).unwrap();
+
+        // Headers come in batches, the node asks for more after each full one.
+        let mut headers = Vec::new();
+        loop {
+            let tip = chain[headers.len()].double_sha256().unwrap();
+            let request = Message::GetHeaders(LocatorHashes::new(vec![tip], Hash::zeroed()));
+            let [Message::Headers(batch)] = &seeder.respond(&request).unwrap()[..] else {
+                panic!("expected a single Headers reply");
+            };
+
+            assert!(batch.headers.len() <= MAX_HEADERS_RESULTS);
+            headers.extend(batch.headers.iter().cloned());
+            if batch.headers.len() < MAX_HEADERS_RESULTS {
+                break;
+            }
+        }
+        assert_eq!(headers.len(), chain.len() - 1);
+
+        // Blocks are requested 16 at a time, the seeder is only done once the tip has been sent.
+        for window in chain[1..].chunks(16) {
+            assert!(!seeder.is_done());
+
+            let inv = Inv::new(window.iter().map(Block::inv_hash).collect());
+            let blocks = seeder.respond(&Message::GetData(inv)).unwrap();
+            let expected: Vec<_> = window
+                .iter()
+                .map(|block| Message::Block(Box::new(block.clone())))
+                .collect();
+            assert_eq!(blocks, expected);
+        }
+        assert!(seeder.is_done());
+    }
+
+    #[test]
+    #[ignore]
+    fn seeding_rejects_requests_outside_the_chain() {
+        let chain = linked_chain(3);
+        let mut seeder = BlockSeeder::new(&chain).unwrap();
+
+        // A node on another network has another genesis block.
+        let locator = LocatorHashes::new(
+            vec![Block::regtest_genesis().double_sha256().unwrap()],
+            Hash::zeroed(),
+        );
+        assert!(seeder.respond(&Message::GetHeaders(locator)).is_err());
+
+        let inv = Inv::new(vec![InvHash::new(ObjectKind::Tx, Hash::zeroed())]);
+        assert!(seeder.respond(&Message::GetData(inv)).is_err());
+
+        // The stop hash ends the batch early.
+        let locator = LocatorHashes::new(
+            vec![chain[0].double_sha256().unwrap()],
+            chain[2].double_sha256().unwrap(),
+        );
+        assert_matches!(
+            &seeder.respond(&Message::GetHeaders(locator)).unwrap()[..],
+            [Message::Headers(headers)] if headers.headers.len() == 2
+        );
+        assert!(!seeder.is_done());
+
+        assert!(BlockSeeder::new(&[]).is_err());
+    }
+}
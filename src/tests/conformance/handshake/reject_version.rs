@@ -95,7 +95,8 @@ async fn c008_VERSION_with_obsolete_number() {
             .recv_message_timeout(LONG_TIMEOUT)
             .await
             .unwrap();
-        assert_matches!(reject, Message::Reject(reject) if reject.ccode == CCode::Obsolete);
+        let reject = assert_matches!(reject, Message::Reject(reject) => reject);
+        reject.check_ccode(CCode::Obsolete).unwrap();
 
         // Expect the connection to be dropped.
         wait_until!(LONG_TIMEOUT, synthetic_node.num_connected() == 0);
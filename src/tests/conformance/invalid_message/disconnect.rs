@@ -26,6 +26,7 @@ use crate::{
             constants::{ADDR_COMMAND, HEADER_LEN},
             Message, MessageHeader,
         },
+        network::Network,
         payload::{addr::NetworkAddr, block::Block, codec::Codec, Addr, Inv, Nonce, VarInt},
     },
     setup::node::{Action, Node},
@@ -140,7 +141,7 @@ async fn c012_t4_ADDR_without_timestamp() {
     // Create a Addr message and encode it. This encoding includes the timestamp.
     let message = Message::Addr(Addr::new(net_addrs));
     let mut payload = Default::default();
-    message.encode(&mut payload).unwrap();
+    message.encode(Network::default(), &mut payload).unwrap();
     let mut payload = payload.to_vec();
 
     // Remove the timestamp bytes. The length of the timestamp field is four 4 bytes (u32).p
@@ -151,7 +152,7 @@ async fn c012_t4_ADDR_without_timestamp() {
     // Note that we cannot use the header from `message.encode()` as it would be generated
     // from the incorrect payload (pre-timestamp removal). Specifically the check-sum would
     // be incorrect.
-    let header = MessageHeader::new(ADDR_COMMAND, &payload);
+    let header = MessageHeader::new(Network::default(), ADDR_COMMAND, &payload);
     let mut buffer = Vec::with_capacity(HEADER_LEN + payload.len());
     header.encode(&mut buffer).unwrap();
     buffer.append(&mut payload);
@@ -161,7 +162,7 @@ async fn c012_t4_ADDR_without_timestamp() {
 
 async fn run_test_case_message(message: Message) -> io::Result<()> {
     let mut buffer = Default::default();
-    message.encode(&mut buffer)?;
+    message.encode(Network::default(), &mut buffer)?;
     run_test_case_bytes(buffer.to_vec()).await
 }
 
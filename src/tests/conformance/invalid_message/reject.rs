@@ -131,16 +131,7 @@ async fn run_test_case(message: Message, expected_code: CCode) -> io::Result<()>
     {
         Ok(_) => Err(io::Error::new(io::ErrorKind::Other, "Message was ignored")),
         Err(PingPongError::Unexpected(msg)) => match *msg {
-            Message::Reject(reject) if reject.ccode == expected_code => Ok(()),
-            Message::Reject(reject) => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!(
-                        "Incorrect rejection ccode: {:?} instead of {:?}",
-                        reject.ccode, expected_code
-                    ),
-                ))
-            }
+            Message::Reject(reject) => reject.check_ccode(expected_code),
             unexpected => {
                 return Err(io::Error::new(
                     io::ErrorKind::Other,
@@ -0,0 +1,59 @@
+//! Seeds a regtest node with a mined chain ([`Action::SeedWithBlocks`]) and queries it back.
+//!
+//! Note: Zebra does not support seeding with chain data nor regtest, and as such cannot run this test successfully.
+
+use crate::{
+    protocol::{
+        message::Message,
+        network::Network,
+        payload::{
+            block::{Headers, LocatorHashes},
+            Hash, Inv,
+        },
+    },
+    setup::node::{Action, Node},
+    tools::{miner::Chain, synthetic_node::SyntheticNode, RECV_TIMEOUT},
+};
+
+#[tokio::test]
+async fn seeded_regtest_chain_is_served() {
+    // zcashd: pass
+    let mut chain = Chain::new();
+    chain.mine(20);
+
+    let mut node = Node::new().unwrap();
+    node.regtest(true)
+        .initial_action(Action::SeedWithBlocks(chain.blocks().to_vec()))
+        .start()
+        .await
+        .unwrap();
+
+    let mut synthetic_node = SyntheticNode::builder()
+        .with_network(Network::Regtest)
+        .with_full_handshake()
+        .with_all_auto_reply()
+        .build()
+        .await
+        .unwrap();
+    synthetic_node.connect(node.addr()).await.unwrap();
+
+    // The node accepted every block, its chain matches ours.
+    let genesis_hash = chain.blocks()[0].double_sha256().unwrap();
+    let query = Message::GetHeaders(LocatorHashes::new(vec![genesis_hash], Hash::zeroed()));
+    let response = synthetic_node
+        .request_timeout(node.addr(), query, RECV_TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(response, Message::Headers(Headers::new(chain.headers())));
+
+    let tip = chain.tip().clone();
+    let query = Message::GetData(Inv::new(vec![tip.inv_hash()]));
+    let response = synthetic_node
+        .request_timeout(node.addr(), query, RECV_TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(response, Message::Block(tip.into()));
+
+    synthetic_node.shut_down().await;
+    node.stop().unwrap();
+}
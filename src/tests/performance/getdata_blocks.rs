@@ -9,7 +9,7 @@ use ziggurat_core_metrics::{
 
 use crate::{
     protocol::{
-        message::Message,
+        message::{constants::BLOCK_COMMAND, Message},
         payload::{block::Block, Inv},
     },
     setup::node::{Action, Node},
@@ -110,13 +110,13 @@ async fn p001_t2_GET_DATA_BLOCKS_throughput() {
             // We want different blocks for consecutive requests, in order to determine if the node
             // has skipped a request or to tell if the reply is in response to a timed out request.
             //
-            // We also store the Block, in order to compare to the reply.
+            // We also store the Block's hash, in order to compare to the reply.
             let requests = Block::initial_testnet_blocks()
                 .into_iter()
                 .map(|block| {
                     (
                         Message::GetData(Inv::new(vec![block.inv_hash()])),
-                        Box::new(block),
+                        block.double_sha256().unwrap(),
                     )
                 })
                 .collect::<VecDeque<_>>();
@@ -125,6 +125,8 @@ async fn p001_t2_GET_DATA_BLOCKS_throughput() {
                 let mut synth_node = SyntheticNode::builder()
                     .with_full_handshake()
                     .with_all_auto_reply()
+                    // Measure the node's throughput rather than our own block decoding.
+                    .with_lazy_decoding()
                     .build()
                     .await
                     .unwrap();
@@ -135,13 +137,16 @@ async fn p001_t2_GET_DATA_BLOCKS_throughput() {
                     let (request, expected) = &requests[i % requests.len()];
                     synth_node.unicast(node_addr, request.clone()).unwrap();
                     let now = tokio::time::Instant::now();
-                    match synth_node.recv_message_timeout(REQUEST_TIMEOUT).await {
+                    match synth_node.recv_lazy_message_timeout(REQUEST_TIMEOUT).await {
                         Err(_timeout) => break,
-                        Ok((_, Message::Block(block))) if &block == expected => {
+                        Ok((_, reply))
+                            if reply.command() == BLOCK_COMMAND
+                                && reply.view().block_hash().ok().as_ref() == Some(expected) =>
+                        {
                             metrics::histogram!(METRIC_LATENCY, duration_as_ms(now.elapsed()));
                         }
                         Ok((_, bad_reply)) => {
-                            panic!("Failed to receive Block, got {bad_reply:?}");
+                            panic!("Failed to receive Block, got {:?}", bad_reply.decode());
                         }
                     }
                 }
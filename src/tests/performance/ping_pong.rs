@@ -218,18 +218,14 @@ async fn simulate_peer(node_addr: SocketAddr) {
 
     for _ in 0..PINGS {
         let nonce = Nonce::default();
-        let expected = Message::Pong(nonce);
-
-        // send Ping(nonce)
-        synth_node.unicast(node_addr, Message::Ping(nonce)).unwrap();
 
+        // send Ping(nonce) and wait for the matching Pong(nonce)
         let now = tokio::time::Instant::now();
         match synth_node
-            .recv_message_timeout(Duration::from_secs(5))
+            .request_timeout(node_addr, Message::Ping(nonce), Duration::from_secs(5))
             .await
         {
-            Ok((_, reply)) => {
-                assert_eq!(reply, expected);
+            Ok(_pong) => {
                 metrics::histogram!(METRIC_LATENCY, duration_as_ms(now.elapsed()));
             }
             Err(_timeout) => break,
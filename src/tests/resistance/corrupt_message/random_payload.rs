@@ -5,7 +5,7 @@ use crate::{
     setup::node::{Action, Node},
     tests::resistance::{DISCONNECT_TIMEOUT, ITERATIONS},
     tools::{
-        fuzzing::{metadata_compliant_random_bytes, seeded_rng, COMMANDS_WITH_PAYLOADS},
+        fuzzing::{commands_with_payloads, metadata_compliant_random_bytes, seeded_rng},
         synthetic_node::SyntheticNode,
     },
 };
@@ -20,7 +20,7 @@ async fn r001_t3_instead_of_version_when_node_receives_connection() {
 
     // Payloadless messages are omitted.
     let mut rng = seeded_rng();
-    let payloads = metadata_compliant_random_bytes(&mut rng, ITERATIONS, &COMMANDS_WITH_PAYLOADS);
+    let payloads = metadata_compliant_random_bytes(&mut rng, ITERATIONS, &commands_with_payloads());
 
     let mut node = Node::new().unwrap();
     node.initial_action(Action::WaitForConnection)
@@ -56,7 +56,7 @@ async fn r002_t3_instead_of_verack_when_node_receives_connection() {
 
     // Payloadless messages are omitted.
     let mut rng = seeded_rng();
-    let payloads = metadata_compliant_random_bytes(&mut rng, ITERATIONS, &COMMANDS_WITH_PAYLOADS);
+    let payloads = metadata_compliant_random_bytes(&mut rng, ITERATIONS, &commands_with_payloads());
 
     let mut node = Node::new().unwrap();
     node.initial_action(Action::WaitForConnection)
@@ -96,7 +96,7 @@ async fn r003_t3_instead_of_version_when_node_initiates_connection() {
     // Payloadless messages are omitted.
     let mut rng = seeded_rng();
     let mut payloads =
-        metadata_compliant_random_bytes(&mut rng, ITERATIONS, &COMMANDS_WITH_PAYLOADS);
+        metadata_compliant_random_bytes(&mut rng, ITERATIONS, &commands_with_payloads());
 
     // create peers (we need their ports to give to the node)
     let (synth_nodes, synth_addrs) = SyntheticNode::builder()
@@ -157,7 +157,7 @@ async fn r004_t3_instead_of_verack_when_node_initiates_connection() {
     // Payloadless messages are omitted.
     let mut rng = seeded_rng();
     let mut payloads =
-        metadata_compliant_random_bytes(&mut rng, ITERATIONS, &COMMANDS_WITH_PAYLOADS);
+        metadata_compliant_random_bytes(&mut rng, ITERATIONS, &commands_with_payloads());
 
     // create peers (we need their ports to give to the node)
     let (synth_nodes, synth_addrs) = SyntheticNode::builder()
@@ -215,7 +215,7 @@ async fn r005_t3_post_handshake() {
 
     // Payloadless messages are omitted.
     let mut rng = seeded_rng();
-    let payloads = metadata_compliant_random_bytes(&mut rng, ITERATIONS, &COMMANDS_WITH_PAYLOADS);
+    let payloads = metadata_compliant_random_bytes(&mut rng, ITERATIONS, &commands_with_payloads());
 
     let mut node = Node::new().unwrap();
     node.initial_action(Action::WaitForConnection)
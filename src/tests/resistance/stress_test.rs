@@ -11,7 +11,8 @@ use ziggurat_core_metrics::{
 
 use crate::{
     protocol::{
-        message::{constants::MAGIC, Message, MessageHeader},
+        message::{Message, MessageHeader, MessageKind},
+        network::Network,
         payload::{
             block::{Block, Headers, LocatorHashes},
             codec::Codec,
@@ -21,10 +22,10 @@ use crate::{
     setup::node::{Action, Node},
     tools::{
         fuzzing::{
-            default_fuzz_messages, encode_messages_with_corrupt_body_length,
-            encode_messages_with_corrupt_checksum, encode_slightly_corrupted_messages,
-            metadata_compliant_random_bytes, random_bytes, seeded_rng, zeroes,
-            COMMANDS_WITH_PAYLOADS,
+            commands_with_payloads, default_fuzz_messages,
+            encode_messages_with_corrupt_body_length, encode_messages_with_corrupt_checksum,
+            encode_slightly_corrupted_messages, metadata_compliant_random_bytes, random_bytes,
+            seeded_rng, zeroes,
         },
         synthetic_node::SyntheticNode,
     },
@@ -409,7 +410,7 @@ fn generate_corrupt_messages(rng: &mut ChaCha8Rng, n: usize) -> Vec<Vec<u8>> {
     possible_payloads.append(&mut metadata_compliant_random_bytes(
         rng,
         n,
-        &COMMANDS_WITH_PAYLOADS,
+        &commands_with_payloads(),
     ));
 
     // remove payloads that ended up being valid
@@ -425,11 +426,15 @@ fn is_valid_message_bytes(bytes: &mut std::io::Cursor<&[u8]>) -> bool {
     };
 
     // check magic
-    if header.magic != MAGIC {
+    if header.magic != Network::default().magic() {
         return false;
     }
 
-    Message::decode(header.command, bytes).is_ok()
+    // Unknown commands decode losslessly, but aren't valid messages as far as the node is concerned.
+    matches!(
+        Message::decode(header.command, bytes),
+        Ok(message) if !matches!(message, Message::Unknown { .. })
+    )
 }
 
 async fn simulate_peer(
@@ -457,6 +462,10 @@ async fn simulate_peer(
 
     // send the valid query messages and validate the responses
     for (query, expected) in message_pairs {
+        let expected_kinds = query
+            .kind()
+            .map_or(&[][..], MessageKind::expected_responses);
+
         if synth_node.unicast(node_addr, query).is_err() {
             metrics::counter!(CONNECTION_TERMINATED, 1);
             return;
@@ -465,6 +474,12 @@ async fn simulate_peer(
         let timer = tokio::time::Instant::now();
         let (_, reply) = synth_node.recv_message().await;
         metrics::histogram!(REQUEST_LATENCY, duration_as_ms(timer.elapsed()));
+
+        // A reply of the wrong kind means the request/response pairing is lost for this peer.
+        if !matches!(reply.kind(), Some(kind) if expected_kinds.contains(&kind)) {
+            metrics::counter!(BAD_REPLY, 1);
+            return;
+        }
         assert_eq!(reply, expected);
     }
 
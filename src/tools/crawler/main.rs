@@ -15,7 +15,10 @@ use rand::prelude::IteratorRandom;
 use tokio::{signal, time::sleep};
 use tracing::{debug, error, info, warn};
 use tracing_subscriber::filter::{EnvFilter, LevelFilter};
-use ziggurat::{protocol::message::Message, wait_until};
+use ziggurat::{
+    protocol::{message::Message, network::Network},
+    wait_until,
+};
 use ziggurat_core_crawler::summary::NetworkSummary;
 
 use crate::{
@@ -49,9 +52,10 @@ struct Args {
     /// If present, start an RPC server at the specified address
     #[clap(short, long, value_parser)]
     rpc_addr: Option<SocketAddr>,
-    // TODO
-    // #[clap(short, long, value_parser, default_value = "testnet")]
-    // network: String,
+
+    /// The network to crawl: mainnet, testnet, regtest or hex-encoded custom magic bytes
+    #[clap(short, long, value_parser, default_value_t = Network::Mainnet)]
+    network: Network,
 }
 
 fn start_logger(default_level: LevelFilter) {
@@ -77,7 +81,7 @@ async fn main() {
     let args = Args::parse();
 
     // Create the crawler with the given listener address.
-    let crawler = Crawler::new().await;
+    let crawler = Crawler::new(args.network).await;
 
     let mut network_metrics = NetworkMetrics::default();
     let summary_snapshot = Arc::new(Mutex::new(NetworkSummary::default()));
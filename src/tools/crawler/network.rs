@@ -5,7 +5,7 @@ use std::{
 };
 
 use parking_lot::RwLock;
-use ziggurat::protocol::payload::{ProtocolVersion, VarStr};
+use ziggurat::protocol::payload::{ProtocolVersion, Services, VarStr};
 use ziggurat_core_crawler::connection::KnownConnection;
 
 /// The elapsed time before a connection should be regarded as inactive.
@@ -25,8 +25,8 @@ pub struct KnownNode {
     pub user_agent: Option<VarStr>,
     /// The node's block height.
     pub start_height: Option<i32>,
-    /// The number of services supported by the node.
-    pub services: Option<u64>,
+    /// The services supported by the node.
+    pub services: Option<Services>,
     /// The number of subsequent connection errors.
     pub connection_failures: u8,
 }
@@ -14,31 +14,43 @@ use rand::{
 use rand_chacha::ChaCha8Rng;
 
 use crate::protocol::{
-    message::{constants::*, Message, MessageHeader},
+    message::{constants::*, Message, MessageHeader, MessageKind},
+    network::Network,
     payload::{
         block::{Headers, LocatorHashes},
-        codec::Codec,
-        Addr, Inv, Nonce, Version,
+        codec::{Codec, DecodeOptions},
+        read_compact_size, Addr, Inv, Nonce, Version,
     },
 };
 
-/// A list of message commands which contain payload bytes.
-pub const COMMANDS_WITH_PAYLOADS: [[u8; 12]; 13] = [
-    VERSION_COMMAND,
-    PING_COMMAND,
-    PONG_COMMAND,
-    ADDR_COMMAND,
-    GETHEADERS_COMMAND,
-    HEADERS_COMMAND,
-    GETBLOCKS_COMMAND,
-    BLOCK_COMMAND,
-    GETDATA_COMMAND,
-    INV_COMMAND,
-    NOTFOUND_COMMAND,
-    TX_COMMAND,
-    REJECT_COMMAND,
+/// The message kinds with payloads fuzzed by the resistance tests.
+///
+/// Listed rather than derived from [`MessageKind::ALL`], so that registering new kinds doesn't
+/// change the seeded corpus.
+const FUZZED_KINDS_WITH_PAYLOADS: [MessageKind; 13] = [
+    MessageKind::Version,
+    MessageKind::Ping,
+    MessageKind::Pong,
+    MessageKind::Addr,
+    MessageKind::GetHeaders,
+    MessageKind::Headers,
+    MessageKind::GetBlocks,
+    MessageKind::Block,
+    MessageKind::GetData,
+    MessageKind::Inv,
+    MessageKind::NotFound,
+    MessageKind::Tx,
+    MessageKind::Reject,
 ];
 
+/// Returns the commands of the fuzzed message kinds which contain payload bytes.
+pub fn commands_with_payloads() -> Vec<[u8; COMMAND_LEN]> {
+    FUZZED_KINDS_WITH_PAYLOADS
+        .into_iter()
+        .map(MessageKind::command)
+        .collect()
+}
+
 const CORRUPTION_PROBABILITY: f64 = 0.5;
 
 /// Returns a randomly seeded `ChaCha8Rng` instance, useful for making tests reproducible.
@@ -111,7 +123,7 @@ pub fn metadata_compliant_random_bytes(
             let mut random_payload: Vec<u8> = rng.sample_iter(Standard).take(random_len).collect();
 
             let command = commands.choose(rng).unwrap();
-            let header = MessageHeader::new(*command, &random_payload);
+            let header = MessageHeader::new(Network::default(), *command, &random_payload);
 
             let mut buffer = Vec::with_capacity(HEADER_LEN + random_payload.len());
             header.encode(&mut buffer).unwrap();
@@ -138,7 +150,7 @@ pub fn encode_slightly_corrupted_messages(
 
 fn corrupt_message(rng: &mut ChaCha8Rng, message: &Message) -> Vec<u8> {
     let mut bytes = Default::default();
-    message.encode(&mut bytes).unwrap();
+    message.encode(Network::default(), &mut bytes).unwrap();
     let vec: Vec<_> = bytes.to_vec();
     let (valid_header, valid_message) = vec.split_at(HEADER_LEN);
 
@@ -167,7 +179,7 @@ fn corrupt_bytes(rng: &mut ChaCha8Rng, serialized: &[u8]) -> Vec<u8> {
 /// Encodes a message and corrupts the body length bytes.
 pub fn encode_message_with_corrupt_body_length(rng: &mut ChaCha8Rng, message: &Message) -> Vec<u8> {
     let mut bytes = Default::default();
-    message.encode(&mut bytes).unwrap();
+    message.encode(Network::default(), &mut bytes).unwrap();
     let mut vec: Vec<_> = bytes.to_vec();
 
     let invalid_body_length = random_non_valid_u32(rng, (vec.len() - HEADER_LEN) as u32);
@@ -179,7 +191,7 @@ pub fn encode_message_with_corrupt_body_length(rng: &mut ChaCha8Rng, message: &M
 /// Encodes a message and corrupts the checksum bytes.
 pub fn encode_message_with_corrupt_checksum(rng: &mut ChaCha8Rng, message: &Message) -> Vec<u8> {
     let mut bytes = Default::default();
-    message.encode(&mut bytes).unwrap();
+    message.encode(Network::default(), &mut bytes).unwrap();
     let mut vec: Vec<_> = bytes.to_vec();
 
     let offset = MAGIC_LEN + COMMAND_LEN + 4; // 4 = sizeof MessageHeader.body_length
@@ -230,3 +242,63 @@ pub fn encode_messages_with_corrupt_checksum(
         })
         .collect()
 }
+
+/// Returns the encodings of `value` as a `CompactSize` which are longer than necessary, e.g.
+/// `fd 01 00` for `1`.
+pub fn non_canonical_compact_sizes(value: u64) -> Vec<Vec<u8>> {
+    let mut encodings = Vec::new();
+
+    if value < 0xfd {
+        let mut bytes = vec![0xfd];
+        bytes.put_u16_le(value as u16);
+        encodings.push(bytes);
+    }
+    if value <= 0xffff {
+        let mut bytes = vec![0xfe];
+        bytes.put_u32_le(value as u32);
+        encodings.push(bytes);
+    }
+    if value <= 0xffff_ffff {
+        let mut bytes = vec![0xff];
+        bytes.put_u64_le(value);
+        encodings.push(bytes);
+    }
+
+    encodings
+}
+
+/// Encodes a message once per non-canonical encoding of the `CompactSize` its payload starts
+/// with (a count or a length), with a valid header.
+///
+/// Returns no encodings for messages whose payload doesn't start with one, e.g. [`Message::Ping`].
+pub fn encode_with_non_canonical_compact_size(message: &Message) -> Vec<Vec<u8>> {
+    match message {
+        Message::Addr(_)
+        | Message::AddrV2(_)
+        | Message::Headers(_)
+        | Message::GetData(_)
+        | Message::Inv(_)
+        | Message::NotFound(_)
+        | Message::Reject(_) => {}
+        _ => return Vec::new(),
+    }
+
+    let mut bytes = Default::default();
+    message.encode(Network::default(), &mut bytes).unwrap();
+    let mut body = &bytes[HEADER_LEN..];
+    let value = read_compact_size(&mut body, DecodeOptions::default()).unwrap();
+
+    non_canonical_compact_sizes(value)
+        .into_iter()
+        .map(|mut payload| {
+            payload.extend_from_slice(body);
+
+            let header = MessageHeader::new(Network::default(), message.command(), &payload);
+            let mut buffer = Vec::with_capacity(HEADER_LEN + payload.len());
+            header.encode(&mut buffer).unwrap();
+            buffer.append(&mut payload);
+
+            buffer
+        })
+        .collect()
+}
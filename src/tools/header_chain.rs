@@ -0,0 +1,306 @@
+//! Tracks the chain of block headers announced by a peer.
+//!
+//! Heights are assigned by linking each header to its parent, starting at a known genesis
+//! header. Headers whose parent is unknown are reported as gaps, headers extending a block other
+//! than the tip of the best chain as forks.
+
+use std::collections::HashMap;
+
+use crate::protocol::{
+    message::Message,
+    payload::{block::Header, Hash},
+};
+
+/// What happened to the chain when a header or block was observed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ChainEvent {
+    /// The header extended the best chain to `height`.
+    Extended { height: u32, hash: Hash },
+    /// The header was already known.
+    Known { height: u32, hash: Hash },
+    /// The header extended a block other than the tip of the best chain, `reorg` is set if the
+    /// fork is now longer and became the best chain.
+    Fork {
+        height: u32,
+        hash: Hash,
+        reorg: bool,
+    },
+    /// The header's parent is unknown, the header wasn't added to the chain.
+    Gap { hash: Hash, prev_block: Hash },
+    /// The block's coinbase encodes a different height than its position in the chain, `None`
+    /// if it doesn't encode one.
+    HeightMismatch {
+        height: u32,
+        hash: Hash,
+        coinbase_height: Option<u32>,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    height: u32,
+    prev_block: Hash,
+}
+
+/// A tree of block headers rooted at a genesis header, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct HeaderChain {
+    entries: HashMap<Hash, Entry>,
+    tip: Hash,
+    events: Vec<ChainEvent>,
+}
+
+impl HeaderChain {
+    /// Creates a chain holding only `genesis`, at height 0.
+    pub fn new(genesis: &Header) -> Self {
+        let hash = genesis.double_sha256().unwrap();
+        let entry = Entry {
+            height: 0,
+            prev_block: genesis.prev_block,
+        };
+
+        Self {
+            entries: HashMap::from([(hash, entry)]),
+            tip: hash,
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns the hash of the tip of the best chain.
+    pub fn tip(&self) -> Hash {
+        self.tip
+    }
+
+    /// Returns the height of the tip of the best chain.
+    pub fn height(&self) -> u32 {
+        self.entries[&self.tip].height
+    }
+
+    /// Returns the height of the header with the given hash, if known.
+    pub fn height_of(&self, hash: &Hash) -> Option<u32> {
+        self.entries.get(hash).map(|entry| entry.height)
+    }
+
+    /// Returns the hash of the header at `height` in the best chain.
+    pub fn hash_at(&self, height: u32) -> Option<Hash> {
+        if height > self.height() {
+            return None;
+        }
+
+        let mut hash = self.tip;
+        for _ in height..self.height() {
+            hash = self.entries[&hash].prev_block;
+        }
+
+        Some(hash)
+    }
+
+    /// Returns `true` if the header with the given hash is part of the best chain.
+    pub fn is_in_best_chain(&self, hash: &Hash) -> bool {
+        self.height_of(hash)
+            .is_some_and(|height| self.hash_at(height) == Some(*hash))
+    }
+
+    /// Returns the events recorded so far, oldest first.
+    pub fn events(&self) -> &[ChainEvent] {
+        &self.events
+    }
+
+    /// Adds `header` to the chain, returning and recording what happened.
+    pub fn insert(&mut self, header: &Header) -> ChainEvent {
+        let hash = header.double_sha256().unwrap();
+
+        let event = if let Some(height) = self.height_of(&hash) {
+            ChainEvent::Known { height, hash }
+        } else if let Some(prev_height) = self.height_of(&header.prev_block) {
+            let height = prev_height + 1;
+            self.entries.insert(
+                hash,
+                Entry {
+                    height,
+                    prev_block: header.prev_block,
+                },
+            );
+
+            if header.prev_block == self.tip {
+                self.tip = hash;
+                ChainEvent::Extended { height, hash }
+            } else {
+                let reorg = height > self.height();
+                if reorg {
+                    self.tip = hash;
+                }
+                ChainEvent::Fork {
+                    height,
+                    hash,
+                    reorg,
+                }
+            }
+        } else {
+            ChainEvent::Gap {
+                hash,
+                prev_block: header.prev_block,
+            }
+        };
+
+        self.events.push(event);
+        event
+    }
+
+    /// Updates the chain with the headers of `Headers` and `Block` messages, ignoring other
+    /// messages.
+    ///
+    /// Blocks are also checked against the height in their coinbase, see
+    /// [`Block::coinbase_height`](crate::protocol::payload::block::Block::coinbase_height).
+    pub fn observe(&mut self, message: &Message) -> Vec<ChainEvent> {
+        match message {
+            Message::Headers(headers) => headers
+                .headers
+                .iter()
+                .map(|header| self.insert(header))
+                .collect(),
+            Message::Block(block) => {
+                let mut events = vec![self.insert(&block.header)];
+
+                let hash = block.header.double_sha256().unwrap();
+                if let Some(height) = self.height_of(&hash) {
+                    let coinbase_height = block.coinbase_height().ok();
+                    if coinbase_height != Some(height) {
+                        let event = ChainEvent::HeightMismatch {
+                            height,
+                            hash,
+                            coinbase_height,
+                        };
+                        self.events.push(event);
+                        events.push(event);
+                    }
+                }
+
+                events
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::{
+        protocol::payload::block::{Block, Headers},
+        tools::miner::{coinbase, mine_block, Chain},
+    };
+
+    #[test]
+    #[ignore]
+    fn testnet_blocks_are_tracked() {
+        let blocks = Block::initial_testnet_blocks();
+        let mut chain = HeaderChain::new(&blocks[0].header);
+
+        let headers = blocks[1..6]
+            .iter()
+            .map(|block| block.header.clone())
+            .collect();
+        let events = chain.observe(&Message::Headers(Headers::new(headers)));
+        assert_matches!(events[..], [.., ChainEvent::Extended { height: 5, .. }]);
+
+        // Blocks 7 onwards don't link up until 6 arrives.
+        let event = chain.insert(&blocks[7].header);
+        assert_matches!(event, ChainEvent::Gap { .. });
+
+        for block in &blocks[5..] {
+            chain.observe(&Message::Block(Box::new(block.clone())));
+        }
+        assert_eq!(chain.height(), 10);
+        assert_matches!(
+            chain.events()[5..],
+            [
+                ChainEvent::Gap { .. },
+                ChainEvent::Known { height: 5, .. },
+                ChainEvent::Extended { height: 6, .. },
+                ..
+            ]
+        );
+        assert!(!chain
+            .events()
+            .iter()
+            .any(|event| matches!(event, ChainEvent::HeightMismatch { .. })));
+
+        for (height, block) in blocks.iter().enumerate() {
+            let hash = block.double_sha256().unwrap();
+            assert_eq!(chain.height_of(&hash), Some(height as u32));
+            assert_eq!(chain.hash_at(height as u32), Some(hash));
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn forks_and_reorgs_are_detected() {
+        let mut main = Chain::new();
+        main.mine(3);
+        let mut fork = main.fork_at(1);
+        fork.mine(3);
+
+        let mut chain = HeaderChain::new(&main.blocks()[0].header);
+        for header in main.headers() {
+            chain.insert(&header);
+        }
+        assert_eq!(chain.tip(), main.tip().double_sha256().unwrap());
+
+        let events: Vec<_> = fork.headers()[1..]
+            .iter()
+            .map(|header| chain.insert(header))
+            .collect();
+        assert_matches!(
+            events[..],
+            [
+                ChainEvent::Fork {
+                    height: 2,
+                    reorg: false,
+                    ..
+                },
+                ChainEvent::Fork {
+                    height: 3,
+                    reorg: false,
+                    ..
+                },
+                ChainEvent::Fork {
+                    height: 4,
+                    reorg: true,
+                    ..
+                },
+            ]
+        );
+
+        assert_eq!(chain.height(), 4);
+        assert!(chain.is_in_best_chain(&fork.tip().double_sha256().unwrap()));
+        assert!(!chain.is_in_best_chain(&main.tip().double_sha256().unwrap()));
+        assert!(chain.is_in_best_chain(&main.blocks()[1].double_sha256().unwrap()));
+    }
+
+    #[test]
+    #[ignore]
+    fn coinbase_height_mismatches_are_detected() {
+        let mut main = Chain::new();
+        main.mine(1);
+
+        // A block at height 1 claiming to be at height 2.
+        let block = mine_block(&main.blocks()[0].header, vec![coinbase(2, Vec::new())]);
+
+        let mut chain = HeaderChain::new(&main.blocks()[0].header);
+        let events = chain.observe(&Message::Block(Box::new(block)));
+        assert_matches!(
+            events[..],
+            [
+                ChainEvent::Extended { height: 1, .. },
+                ChainEvent::HeightMismatch {
+                    height: 1,
+                    coinbase_height: Some(2),
+                    ..
+                }
+            ]
+        );
+    }
+}
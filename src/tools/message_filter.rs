@@ -1,7 +1,7 @@
 //! Message filtering types and utilities.
 
 use crate::protocol::{
-    message::Message,
+    message::{constants::COMMAND_LEN, Message, MessageKind},
     payload::{block::Headers, Addr},
 };
 
@@ -111,17 +111,31 @@ impl MessageFilter {
         self
     }
 
-    /// Returns the set [`Filter`] for the message type.
-    pub fn message_filter_type(&self, message: &Message) -> Filter {
-        match message {
-            Message::Ping(_) => self.ping,
-            Message::GetAddr => self.getaddr,
-            Message::GetHeaders(_) => self.getheaders,
-            Message::GetData(_) => self.getdata,
+    /// Returns the set [`Filter`] for the message kind.
+    pub fn kind_filter_type(&self, kind: MessageKind) -> Filter {
+        match kind {
+            MessageKind::Ping => self.ping,
+            MessageKind::GetAddr => self.getaddr,
+            MessageKind::GetHeaders => self.getheaders,
+            MessageKind::GetData => self.getdata,
             _ => Filter::Disabled,
         }
     }
 
+    /// Returns the set [`Filter`] for the message type.
+    pub fn message_filter_type(&self, message: &Message) -> Filter {
+        message
+            .kind()
+            .map_or(Filter::Disabled, |kind| self.kind_filter_type(kind))
+    }
+
+    /// Returns the set [`Filter`] for messages with the command, e.g. to check whether a message
+    /// needs decoding at all.
+    pub fn command_filter_type(&self, command: [u8; COMMAND_LEN]) -> Filter {
+        MessageKind::from_command(command)
+            .map_or(Filter::Disabled, |kind| self.kind_filter_type(kind))
+    }
+
     /// Returns the appropriate reply for the message.
     pub fn reply_message(&self, message: &Message) -> Message {
         match message {
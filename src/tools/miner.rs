@@ -0,0 +1,319 @@
+//! A CPU miner for synthetic regtest chains.
+//!
+//! Regtest uses Equihash (48, 5) and a trivial difficulty, which makes mining valid blocks cheap
+//! enough to do in tests. Blocks only carry a coinbase transaction by default and follow the
+//! pre-Overwinter rules of a regtest node without any network upgrades activated, which includes
+//! paying the founders' reward.
+
+use std::cmp;
+
+use time::OffsetDateTime;
+
+use crate::protocol::payload::{
+    block::{Block, Header, Target},
+    equihash::EquihashParams,
+    tx::{TxIn, TxOut, TxV1},
+    Hash, Tx, VarInt,
+};
+
+/// The compact target of regtest blocks, the network's proof-of-work limit.
+pub const REGTEST_BITS: u32 = 0x200f_0f0f;
+
+/// The block subsidy before the first halving, in zatoshis.
+const INITIAL_SUBSIDY: i64 = 1_250_000_000;
+/// The number of blocks between halvings on regtest (before Blossom).
+const HALVING_INTERVAL: u32 = 150;
+
+/// The `OP_TRUE` script, spendable by anyone.
+const OP_TRUE: u8 = 0x51;
+
+/// The P2SH script of the regtest founders' reward address, `t2FwcEhFdNXuFMv1tcYwaBJtYVtMj8b1uTg`.
+pub const REGTEST_FOUNDERS_REWARD_SCRIPT: [u8; 23] = [
+    0xa9, 0x14, 0x67, 0x08, 0xe6, 0x67, 0x0d, 0xb0, 0xb9, 0x50, 0xda, 0xc6, 0x80, 0x31, 0x02, 0x5c,
+    0xc5, 0xb6, 0x32, 0x13, 0xa4, 0x91, 0x87,
+];
+
+/// A chain of regtest blocks mined on a CPU, starting at [`Block::regtest_genesis`].
+///
+/// Forks are created with [`Chain::fork_at`], mining past the tip of the original chain on a
+/// fork sets up a reorg.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    blocks: Vec<Block>,
+    pk_script: Vec<u8>,
+    founders_reward_script: Option<Vec<u8>>,
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        Self {
+            blocks: vec![Block::regtest_genesis()],
+            pk_script: vec![OP_TRUE],
+            founders_reward_script: Some(REGTEST_FOUNDERS_REWARD_SCRIPT.to_vec()),
+        }
+    }
+}
+
+impl Chain {
+    /// Creates a chain holding only the regtest genesis block, whose coinbases pay to `OP_TRUE`
+    /// and the founders' reward to [`REGTEST_FOUNDERS_REWARD_SCRIPT`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the script the coinbase transactions pay the block subsidy to.
+    pub fn with_pk_script(mut self, pk_script: Vec<u8>) -> Self {
+        self.pk_script = pk_script;
+        self
+    }
+
+    /// Sets the script the coinbase transactions pay the founders' reward to, a fifth of the
+    /// subsidy until the first halving, [`REGTEST_FOUNDERS_REWARD_SCRIPT`] by default.
+    pub fn with_founders_reward_script(mut self, founders_reward_script: Vec<u8>) -> Self {
+        self.founders_reward_script = Some(founders_reward_script);
+        self
+    }
+
+    /// Pays the whole subsidy to the miner. Nodes reject such blocks unless Canopy is active.
+    pub fn without_founders_reward(mut self) -> Self {
+        self.founders_reward_script = None;
+        self
+    }
+
+    /// Returns the blocks of the chain, starting with the genesis block.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Returns the last block of the chain.
+    pub fn tip(&self) -> &Block {
+        self.blocks.last().unwrap()
+    }
+
+    /// Returns the height of the tip.
+    pub fn height(&self) -> u32 {
+        self.blocks.len() as u32 - 1
+    }
+
+    /// Returns the headers of the blocks after the genesis block.
+    pub fn headers(&self) -> Vec<Header> {
+        self.blocks[1..]
+            .iter()
+            .map(|block| block.header.clone())
+            .collect()
+    }
+
+    /// Mines `count` blocks on top of the tip.
+    pub fn mine(&mut self, count: usize) -> &mut Self {
+        for _ in 0..count {
+            self.mine_block(Vec::new());
+        }
+
+        self
+    }
+
+    /// Mines a block holding the transactions after its coinbase on top of the tip.
+    pub fn mine_block(&mut self, txs: Vec<Tx>) -> &Block {
+        let height = self.height() + 1;
+        let subsidy = INITIAL_SUBSIDY
+            .checked_shr(height / HALVING_INTERVAL)
+            .unwrap_or(0);
+
+        let mut outputs = Vec::new();
+        let mut miner_reward = subsidy;
+        if let Some(script) = &self.founders_reward_script {
+            if height < HALVING_INTERVAL {
+                outputs.push(TxOut::new(subsidy / 5, script.clone()));
+                miner_reward -= subsidy / 5;
+            }
+        }
+        outputs.insert(0, TxOut::new(miner_reward, self.pk_script.clone()));
+
+        let mut block_txs = vec![coinbase(height, outputs)];
+        block_txs.extend(txs);
+
+        let block = mine_block(&self.tip().header, block_txs);
+        self.blocks.push(block);
+        self.tip()
+    }
+
+    /// Returns a copy of the chain truncated to `height`, to be mined on independently.
+    ///
+    /// Coinbase transactions carry a random tag, blocks mined on the fork differ from those of
+    /// the original chain at the same height.
+    pub fn fork_at(&self, height: u32) -> Self {
+        let mut fork = self.clone();
+        fork.blocks.truncate(height as usize + 1);
+
+        fork
+    }
+
+    /// Returns the blocks of the chain, starting with the genesis block.
+    pub fn into_blocks(self) -> Vec<Block> {
+        self.blocks
+    }
+}
+
+/// Builds a coinbase transaction for the block at `height`.
+///
+/// The signature script starts with the height as required by BIP 34, followed by a random tag
+/// which keeps coinbases (and so blocks) at the same height distinct.
+pub fn coinbase(height: u32, outputs: Vec<TxOut>) -> Tx {
+    let mut script = Vec::new();
+    push_number(&mut script, height);
+    script.push(8);
+    script.extend_from_slice(&rand::random::<[u8; 8]>());
+
+    let input = TxIn::new(Hash::zeroed(), u32::MAX, script, u32::MAX);
+
+    TxV1::builder()
+        .with_tx_in(vec![input])
+        .with_tx_out(outputs)
+        .build()
+        .into()
+}
+
+/// Mines a regtest block holding `txs` on top of the block with the header `prev`.
+///
+/// The block's timestamp is the current time, or a second past `prev`'s if that's later.
+pub fn mine_block(prev: &Header, txs: Vec<Tx>) -> Block {
+    let now = OffsetDateTime::now_utc().unix_timestamp() as u32;
+
+    let mut block = Block {
+        header: Header {
+            version: prev.version,
+            prev_block: prev.double_sha256().unwrap(),
+            merkle_root: Hash::zeroed(),
+            light_client_root: Hash::zeroed(),
+            timestamp: cmp::max(now, prev.timestamp + 1),
+            bits: REGTEST_BITS,
+            nonce: [0; 32],
+            solution_size: VarInt::new(0),
+            solution: Vec::new(),
+        },
+        txs,
+    };
+    block.header.merkle_root = block.compute_merkle_root().unwrap();
+
+    solve(&mut block.header);
+
+    block
+}
+
+/// Searches for a nonce and Equihash solution which meet the header's target.
+fn solve(header: &mut Header) {
+    let params = EquihashParams::REGTEST;
+    let target = Target::from_bits(header.bits).unwrap();
+
+    for nonce in 0u64.. {
+        header.nonce[..8].copy_from_slice(&nonce.to_le_bytes());
+
+        let mut input = Vec::new();
+        header.encode_equihash_input(&mut input).unwrap();
+
+        for solution in params.solve(&input) {
+            header.solution_size = VarInt::new(solution.len());
+            header.solution = solution;
+
+            if target.is_met_by(&header.double_sha256().unwrap()) {
+                return;
+            }
+        }
+    }
+}
+
+/// Pushes a number onto a script, as `CScript() << number` does in `zcashd`.
+fn push_number(script: &mut Vec<u8>, number: u32) {
+    match number {
+        0 => script.push(0x00),
+        // OP_1 to OP_16.
+        1..=16 => script.push(0x50 + number as u8),
+        _ => {
+            let mut bytes = number.to_le_bytes().to_vec();
+            while bytes.last() == Some(&0) {
+                bytes.pop();
+            }
+            // The top bit is the sign.
+            if bytes.last().unwrap() & 0x80 != 0 {
+                bytes.push(0);
+            }
+
+            script.push(bytes.len() as u8);
+            script.extend(bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::network::Network;
+
+    fn assert_valid(chain: &Chain) {
+        for (prev, block) in chain.blocks().iter().zip(&chain.blocks()[1..]) {
+            assert_eq!(block.header.prev_block, prev.double_sha256().unwrap());
+            assert!(block.header.timestamp > prev.header.timestamp);
+            block.header.validate_pow(Network::Regtest).unwrap();
+            block.validate_merkle_root().unwrap();
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn mined_chains_are_valid() {
+        let mut chain = Chain::new();
+        chain.mine(5);
+        assert_eq!(chain.height(), 5);
+        assert_eq!(chain.headers().len(), 5);
+        assert_valid(&chain);
+
+        let Tx::V1(coinbase) = &chain.tip().txs[0] else {
+            panic!("expected a V1 coinbase");
+        };
+        assert_eq!(coinbase.tx_in()[0].script()[0], 0x55);
+        assert_eq!(coinbase.tx_out()[0].value(), 1_000_000_000);
+        assert_eq!(coinbase.tx_out()[1].value(), 250_000_000);
+        assert_eq!(
+            coinbase.tx_out()[1].pk_script(),
+            REGTEST_FOUNDERS_REWARD_SCRIPT
+        );
+
+        let mut chain = Chain::new().without_founders_reward();
+        let Tx::V1(coinbase) = &chain.mine(1).tip().txs[0] else {
+            panic!("expected a V1 coinbase");
+        };
+        assert_eq!(coinbase.tx_out().len(), 1);
+        assert_eq!(coinbase.tx_out()[0].value(), 1_250_000_000);
+    }
+
+    #[test]
+    #[ignore]
+    fn forks_diverge() {
+        let mut chain = Chain::new();
+        chain.mine(3);
+
+        let mut fork = chain.fork_at(1);
+        fork.mine(4);
+        assert_valid(&fork);
+
+        assert_eq!(fork.blocks()[..2], chain.blocks()[..2]);
+        assert_ne!(fork.blocks()[2], chain.blocks()[2]);
+        assert_eq!(fork.height(), 5);
+    }
+
+    #[test]
+    #[ignore]
+    fn heights_are_pushed_minimally() {
+        for (height, expected) in [
+            (0, &[0x00][..]),
+            (16, &[0x60]),
+            (17, &[0x01, 0x11]),
+            (128, &[0x02, 0x80, 0x00]),
+            (1_599_200, &[0x03, 0xe0, 0x66, 0x18]),
+        ] {
+            let mut script = Vec::new();
+            push_number(&mut script, height);
+            assert_eq!(script, expected);
+        }
+    }
+}
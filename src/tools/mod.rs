@@ -1,7 +1,9 @@
 //! Utilities for network testing.
 
 pub mod fuzzing;
+pub mod header_chain;
 pub mod message_filter;
+pub mod miner;
 pub mod synthetic_node;
 
 use std::time::Duration;
@@ -1,14 +1,17 @@
 //! A lightweight node implementation to be used as peers in tests.
 
 use std::{
+    collections::VecDeque,
     io::{self, Error, ErrorKind},
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
     time::Duration,
 };
 
 use assert_matches::assert_matches;
 use bytes::{BufMut, BytesMut};
 use futures_util::{sink::SinkExt, TryStreamExt};
+use parking_lot::Mutex;
 use pea2pea::{
     protocols::{Handshake, Reading, Writing},
     Config as NodeConfig, Connection, ConnectionSide, Node, Pea2Pea,
@@ -22,10 +25,21 @@ use tracing::*;
 
 use crate::{
     protocol::{
-        message::{Message, MessageHeader},
-        payload::{codec::Codec, Nonce, Version},
+        message::{
+            checksum,
+            constants::{BLOCK_COMMAND, COMMAND_LEN, HEADERS_COMMAND, HEADER_LEN, MAGIC_LEN},
+            LazyMessage, Message, MessageHeader, MessageKind,
+        },
+        network::Network,
+        payload::{
+            codec::{Codec, DecodeOptions},
+            Nonce, ProtocolVersion, Services, VarStr, Version,
+        },
+    },
+    tools::{
+        header_chain::HeaderChain,
+        message_filter::{Filter, MessageFilter},
     },
-    tools::message_filter::{Filter, MessageFilter},
 };
 
 /// An [`Error`](std::error::Error) type for [`SyntheticNode::ping_pong_timeout`]
@@ -85,6 +99,111 @@ impl From<PingPongError> for io::Error {
     }
 }
 
+/// An [`Error`](std::error::Error) type for frames rejected by [`MessageCodec`], naming the check
+/// which failed.
+///
+/// The codec surfaces it wrapped in an [`io::Error`] of kind [`ErrorKind::InvalidData`], use
+/// [`DecodeError::from_io_error`] to get it back.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The frame's magic doesn't match the codec's [`Network`].
+    Magic {
+        /// The magic of the codec's network.
+        expected: [u8; MAGIC_LEN],
+        /// The magic from the frame's header.
+        actual: [u8; MAGIC_LEN],
+    },
+    /// The frame's checksum doesn't match its body.
+    Checksum {
+        /// The command from the frame's header.
+        command: [u8; COMMAND_LEN],
+        /// The checksum computed from the frame's body.
+        expected: u32,
+        /// The checksum from the frame's header.
+        actual: u32,
+    },
+    /// The frame's body is longer than the codec accepts for its command.
+    FrameTooLarge {
+        /// The command from the frame's header.
+        command: [u8; COMMAND_LEN],
+        /// The body length from the frame's header.
+        body_length: usize,
+        /// The maximum body length accepted for the command.
+        max_body_length: usize,
+    },
+    /// The frame's body couldn't be decoded as a message of its command.
+    Payload {
+        /// The command from the frame's header.
+        command: [u8; COMMAND_LEN],
+        /// The error returned by [`Message::decode`].
+        source: io::Error,
+    },
+}
+
+impl DecodeError {
+    /// Returns the [`DecodeError`] wrapped by `err`, if any.
+    pub fn from_io_error(err: &io::Error) -> Option<&Self> {
+        err.get_ref().and_then(|inner| inner.downcast_ref())
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let command_str = |command: &[u8]| {
+            String::from_utf8_lossy(command)
+                .trim_end_matches('\0')
+                .to_owned()
+        };
+
+        match self {
+            DecodeError::Magic { expected, actual } => write!(
+                f,
+                "bad magic: expected {}, got {}",
+                hex::encode(expected),
+                hex::encode(actual)
+            ),
+            DecodeError::Checksum {
+                command,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "bad checksum for {:?}: expected {expected:#010x}, got {actual:#010x}",
+                command_str(command)
+            ),
+            DecodeError::FrameTooLarge {
+                command,
+                body_length,
+                max_body_length,
+            } => write!(
+                f,
+                "{:?} frame body of {body_length} bytes exceeds the maximum of {max_body_length} bytes",
+                command_str(command)
+            ),
+            DecodeError::Payload { command, source } => write!(
+                f,
+                "bad {:?} payload: {source}",
+                command_str(command)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::Payload { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for io::Error {
+    fn from(original: DecodeError) -> Self {
+        Error::new(ErrorKind::InvalidData, original)
+    }
+}
+
 /// Enables tracing for all [`SyntheticNode`] instances (usually scoped by test).
 pub fn enable_tracing() {
     use tracing_subscriber::{fmt, EnvFilter};
@@ -109,12 +228,132 @@ pub enum HandshakeKind {
     VersionOnly,
 }
 
+/// How a [`SyntheticNode`] picks the nonce of the [`Version`] messages it sends.
+///
+/// [`Version`]: enum@crate::protocol::message::Message::Version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoncePolicy {
+    /// A new random nonce per handshake.
+    Random,
+    /// The same nonce for every handshake.
+    Fixed(Nonce),
+    /// The nonce of the peer's version on inbound connections, which makes the peer see a
+    /// connection to itself. Falls back to a random nonce on outbound connections, as the peer's
+    /// version isn't known yet.
+    EchoPeer,
+}
+
+/// The [`Version`] message a [`SyntheticNode`] sends during handshakes, the addresses are filled
+/// in per connection.
+///
+/// [`Version`]: enum@crate::protocol::message::Message::Version
+#[derive(Debug, Clone)]
+pub struct VersionTemplate {
+    version: ProtocolVersion,
+    services: Services,
+    user_agent: String,
+    start_height: i32,
+    relay: bool,
+    nonce: NoncePolicy,
+    timestamp_skew: time::Duration,
+}
+
+impl Default for VersionTemplate {
+    fn default() -> Self {
+        Self {
+            version: ProtocolVersion::current(),
+            services: Services::NODE_NETWORK,
+            user_agent: String::new(),
+            start_height: 0,
+            relay: false,
+            nonce: NoncePolicy::Random,
+            timestamp_skew: time::Duration::ZERO,
+        }
+    }
+}
+
+impl VersionTemplate {
+    /// Sets the protocol version.
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = ProtocolVersion(version);
+        self
+    }
+
+    /// Sets the advertised services.
+    pub fn with_services(mut self, services: Services) -> Self {
+        self.services = services;
+        self
+    }
+
+    /// Sets the user agent.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets the height of the last block received.
+    pub fn with_start_height(mut self, start_height: i32) -> Self {
+        self.start_height = start_height;
+        self
+    }
+
+    /// Sets whether the peer should relay transactions.
+    pub fn with_relay(mut self, relay: bool) -> Self {
+        self.relay = relay;
+        self
+    }
+
+    /// Sets the [`NoncePolicy`].
+    pub fn with_nonce(mut self, nonce: NoncePolicy) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    /// Offsets the timestamp from the current time, e.g. to send timestamps in the future.
+    pub fn with_timestamp_skew(mut self, skew: time::Duration) -> Self {
+        self.timestamp_skew = skew;
+        self
+    }
+
+    /// Builds the version sent to `addr_recv`, `peer_nonce` being the nonce of the peer's version
+    /// if it was already received.
+    pub fn build(
+        &self,
+        addr_recv: SocketAddr,
+        addr_from: SocketAddr,
+        peer_nonce: Option<Nonce>,
+    ) -> Version {
+        let mut version = Version::new(addr_recv, addr_from).with_services(self.services);
+        version.version = self.version;
+        version.timestamp += self.timestamp_skew;
+        version.user_agent = VarStr(self.user_agent.clone());
+        version.start_height = self.start_height;
+        version.relay = self.relay;
+        match (self.nonce, peer_nonce) {
+            (NoncePolicy::Fixed(nonce), _) | (NoncePolicy::EchoPeer, Some(nonce)) => {
+                version.nonce = nonce;
+            }
+            _ => {}
+        }
+
+        version
+    }
+}
+
 /// A builder for [`SyntheticNode`].
 #[derive(Debug, Clone)]
 pub struct SyntheticNodeBuilder {
     network_config: NodeConfig,
+    network: Network,
+    max_body_length: Option<usize>,
+    raw_decoding: bool,
+    decode_options: DecodeOptions,
+    lazy_decoding: bool,
     handshake: Option<HandshakeKind>,
     message_filter: MessageFilter,
+    header_chain: Option<HeaderChain>,
+    version: VersionTemplate,
+    services: Option<Services>,
 }
 
 impl Default for SyntheticNodeBuilder {
@@ -125,8 +364,16 @@ impl Default for SyntheticNodeBuilder {
                 listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
                 ..Default::default()
             },
+            network: Network::default(),
+            max_body_length: None,
+            raw_decoding: false,
+            decode_options: DecodeOptions::default(),
+            lazy_decoding: false,
             handshake: None,
             message_filter: MessageFilter::with_all_disabled(),
+            header_chain: None,
+            version: VersionTemplate::default(),
+            services: None,
         }
     }
 }
@@ -139,8 +386,7 @@ impl SyntheticNodeBuilder {
 
         // Inbound channel size of 100 messages.
         let (tx, rx) = mpsc::channel(100);
-        let inner_node =
-            InnerNode::new(node, tx, self.message_filter.clone(), self.handshake).await;
+        let inner_node = InnerNode::new(node, tx, self).await;
 
         // Enable the read and write protocols
         inner_node.enable_reading().await;
@@ -152,6 +398,7 @@ impl SyntheticNodeBuilder {
         Ok(SyntheticNode {
             inner_node,
             inbound_rx: rx,
+            pending: VecDeque::new(),
         })
     }
 
@@ -191,12 +438,84 @@ impl SyntheticNodeBuilder {
         self.message_filter = filter;
         self
     }
+
+    /// Sets the [`Network`] the node encodes its messages for and expects its peers' messages
+    /// to be from, defaults to [`Network::Testnet`].
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Caps the body length of every inbound frame at `max_body_length` bytes, replacing the
+    /// protocol's per-command limits (see [`MessageHeader::max_body_length`]).
+    pub fn with_max_body_length(mut self, max_body_length: usize) -> Self {
+        self.max_body_length = Some(max_body_length);
+        self
+    }
+
+    /// Disables magic and checksum validation of inbound frames, see [`MessageCodec::raw`].
+    pub fn with_raw_decoding(mut self) -> Self {
+        self.raw_decoding = true;
+        self
+    }
+
+    /// Sets the options inbound messages are decoded with, e.g. [`DecodeOptions::strict`] to
+    /// reject non-canonical encodings or oversized fields, see [`MessageCodec::with_decode_options`].
+    pub fn with_decode_options(mut self, options: DecodeOptions) -> Self {
+        self.decode_options = options;
+        self
+    }
+
+    /// Queues inbound messages undecoded, as [`LazyMessage`]s, so that high-throughput tests
+    /// measure the peer rather than our own parsing. Messages the [`MessageFilter`] acts on are
+    /// still decoded.
+    ///
+    /// Use [`SyntheticNode::recv_lazy_message`] to read them without decoding.
+    pub fn with_lazy_decoding(mut self) -> Self {
+        self.lazy_decoding = true;
+        self
+    }
+
+    /// Tracks the headers of inbound `Headers` and `Block` messages in a copy of `chain`, before
+    /// they're filtered. Use [`SyntheticNode::header_chain`] to inspect it.
+    pub fn with_header_chain(mut self, chain: HeaderChain) -> Self {
+        self.header_chain = Some(chain);
+        self
+    }
+
+    /// Sets the services the node advertises in its [`Version`] messages, `NODE_NETWORK` by
+    /// default. Takes precedence over the [template](Self::with_version_template)'s services,
+    /// whichever is set first.
+    ///
+    /// [`Version`]: enum@crate::protocol::message::Message::Version
+    pub fn with_services(mut self, services: Services) -> Self {
+        self.services = Some(services);
+        self
+    }
+
+    /// Sets the template of the [`Version`] messages the node sends during handshakes.
+    ///
+    /// [`Version`]: enum@crate::protocol::message::Message::Version
+    pub fn with_version_template(mut self, template: VersionTemplate) -> Self {
+        self.version = template;
+        self
+    }
+
+    /// Returns the version template, with the services set on the builder applied.
+    fn version_template(&self) -> VersionTemplate {
+        match self.services {
+            Some(services) => self.version.clone().with_services(services),
+            None => self.version.clone(),
+        }
+    }
 }
 
 /// Convenient abstraction over a `pea2pea` node.
 pub struct SyntheticNode {
     inner_node: InnerNode,
-    inbound_rx: Receiver<(SocketAddr, Message)>,
+    inbound_rx: Receiver<(SocketAddr, InboundMessage)>,
+    // Messages set aside by `request` while waiting for a response, read before the inbound queue.
+    pending: VecDeque<(SocketAddr, InboundMessage)>,
 }
 
 impl SyntheticNode {
@@ -205,6 +524,20 @@ impl SyntheticNode {
         SyntheticNodeBuilder::default()
     }
 
+    /// Returns the [`Network`] the node is configured for.
+    pub fn network(&self) -> Network {
+        self.inner_node.network
+    }
+
+    /// Returns a snapshot of the chain of headers received so far, if tracking was enabled with
+    /// [`SyntheticNodeBuilder::with_header_chain`].
+    pub fn header_chain(&self) -> Option<HeaderChain> {
+        self.inner_node
+            .header_chain
+            .as_ref()
+            .map(|chain| chain.lock().clone())
+    }
+
     /// Returns the listening address of the node.
     pub fn listening_addr(&self) -> SocketAddr {
         self.inner_node.node().listening_addr().unwrap()
@@ -265,14 +598,44 @@ impl SyntheticNode {
 
     /// Reads a message from the inbound (internal) queue of the node.
     ///
-    /// Messages are sent to the queue when unfiltered by the message filter.
+    /// Messages are sent to the queue when unfiltered by the message filter. With
+    /// [lazy decoding](SyntheticNodeBuilder::with_lazy_decoding) they're decoded here, which panics
+    /// if the message is malformed.
     pub async fn recv_message(&mut self) -> (SocketAddr, Message) {
-        match self.inbound_rx.recv().await {
-            Some(message) => message,
-            None => panic!("all senders dropped!"),
+        match self.recv_inbound().await {
+            (source, InboundMessage::Decoded(message)) => (source, message),
+            (source, InboundMessage::Lazy(message)) => (
+                source,
+                message.decode().expect("couldn't decode lazy message"),
+            ),
         }
     }
 
+    /// Reads a message from the inbound (internal) queue of the node without decoding it.
+    ///
+    /// Only avoids decoding with [lazy decoding](SyntheticNodeBuilder::with_lazy_decoding)
+    /// enabled, decoded messages are re-encoded.
+    pub async fn recv_lazy_message(&mut self) -> (SocketAddr, LazyMessage) {
+        match self.recv_inbound().await {
+            (source, InboundMessage::Lazy(message)) => (source, message),
+            (source, InboundMessage::Decoded(message)) => (
+                source,
+                LazyMessage::from_message(self.network(), &message)
+                    .expect("couldn't encode message"),
+            ),
+        }
+    }
+
+    /// Reads the next message set aside by [`SyntheticNode::request`], or else the next message
+    /// from the inbound queue.
+    async fn recv_inbound(&mut self) -> (SocketAddr, InboundMessage) {
+        if let Some(inbound) = self.pending.pop_front() {
+            return inbound;
+        }
+
+        self.inbound_rx.recv().await.expect("all senders dropped!")
+    }
+
     // Attempts to read a message from the inbound (internal) queue of the node before the timeout
     // duration has elapsed (seconds).
     // FIXME: logging?
@@ -282,10 +645,78 @@ impl SyntheticNode {
     ) -> io::Result<(SocketAddr, Message)> {
         match timeout(duration, self.recv_message()).await {
             Ok(message) => Ok(message),
+            Err(_e) => Err(recv_timeout_error(duration)),
+        }
+    }
+
+    /// Attempts to read a message from the inbound (internal) queue of the node without decoding
+    /// it, before the timeout duration has elapsed, see [`SyntheticNode::recv_lazy_message`].
+    pub async fn recv_lazy_message_timeout(
+        &mut self,
+        duration: Duration,
+    ) -> io::Result<(SocketAddr, LazyMessage)> {
+        match timeout(duration, self.recv_lazy_message()).await {
+            Ok(message) => Ok(message),
+            Err(_e) => Err(recv_timeout_error(duration)),
+        }
+    }
+
+    /// Sends `request` to the target and waits for the response to it.
+    ///
+    /// The response is the first message from the target which
+    /// [`is_response_to`](Message::is_response_to) the request, e.g. a [`Pong`](Message::Pong) with
+    /// the [`Ping`](Message::Ping)'s nonce or a [`Headers`](Message::Headers) for a
+    /// [`GetHeaders`](Message::GetHeaders). Unrelated messages received in the meantime are left in
+    /// the inbound queue, in order, for the `recv_*` methods to read.
+    ///
+    /// Errors with [`ErrorKind::InvalidInput`] if the request's kind doesn't call for a response.
+    pub async fn request(&mut self, target: SocketAddr, request: Message) -> io::Result<Message> {
+        let expected_kinds = match request.kind() {
+            Some(kind) if kind.is_request() => kind.expected_responses(),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{request} doesn't call for a response"),
+                ))
+            }
+        };
+
+        self.unicast(target, request.clone())?;
+
+        // The response may already have been set aside while waiting on an earlier request.
+        if let Some(response) =
+            take_pending_response(&mut self.pending, target, &request, expected_kinds)
+        {
+            return Ok(response);
+        }
+
+        loop {
+            let inbound = self.inbound_rx.recv().await.expect("all senders dropped!");
+
+            if let Some(response) = response_to(&inbound, target, &request, expected_kinds) {
+                return Ok(response);
+            }
+
+            self.pending.push_back(inbound);
+        }
+    }
+
+    /// Sends `request` to the target and waits for the response to it before the timeout duration
+    /// has elapsed, see [`SyntheticNode::request`].
+    pub async fn request_timeout(
+        &mut self,
+        target: SocketAddr,
+        request: Message,
+        duration: Duration,
+    ) -> io::Result<Message> {
+        let kind = request.kind();
+        match timeout(duration, self.request(target, request)).await {
+            Ok(result) => result,
             Err(_e) => Err(Error::new(
                 ErrorKind::TimedOut,
                 format!(
-                    "could not read message after {0:.3}s",
+                    "no response to {} after {:.3}s",
+                    kind.map_or("Unknown", |kind| kind.name()),
                     duration.as_secs_f64()
                 ),
             )),
@@ -368,34 +799,136 @@ impl SyntheticNode {
     }
 }
 
+fn recv_timeout_error(duration: Duration) -> io::Error {
+    Error::new(
+        ErrorKind::TimedOut,
+        format!(
+            "could not read message after {0:.3}s",
+            duration.as_secs_f64()
+        ),
+    )
+}
+
+/// Returns the inbound message as a response to `request` if it's one from `target`.
+fn response_to(
+    (source, inbound): &(SocketAddr, InboundMessage),
+    target: SocketAddr,
+    request: &Message,
+    expected_kinds: &[MessageKind],
+) -> Option<Message> {
+    if *source != target {
+        return None;
+    }
+
+    match inbound {
+        InboundMessage::Decoded(message) if message.is_response_to(request) => {
+            Some(message.clone())
+        }
+        // Only messages of the expected kinds are worth decoding.
+        InboundMessage::Lazy(message) => MessageKind::from_command(message.command())
+            .filter(|kind| expected_kinds.contains(kind))
+            .and_then(|_| message.decode().ok())
+            .filter(|response| response.is_response_to(request)),
+        _ => None,
+    }
+}
+
+/// Removes the first response to `request` from the set aside messages, keeping the others in
+/// order.
+fn take_pending_response(
+    pending: &mut VecDeque<(SocketAddr, InboundMessage)>,
+    target: SocketAddr,
+    request: &Message,
+    expected_kinds: &[MessageKind],
+) -> Option<Message> {
+    let (index, response) = pending.iter().enumerate().find_map(|(index, inbound)| {
+        response_to(inbound, target, request, expected_kinds).map(|response| (index, response))
+    })?;
+    pending.remove(index);
+
+    Some(response)
+}
+
+/// A message in the node's inbound queue.
+#[allow(clippy::large_enum_variant)]
+enum InboundMessage {
+    Decoded(Message),
+    Lazy(LazyMessage),
+}
+
 #[derive(Clone)]
 struct InnerNode {
     node: Node,
+    network: Network,
+    max_body_length: Option<usize>,
+    raw_decoding: bool,
+    decode_options: DecodeOptions,
+    lazy_decoding: bool,
     handshake: Option<HandshakeKind>,
-    inbound_tx: Sender<(SocketAddr, Message)>,
+    inbound_tx: Sender<(SocketAddr, InboundMessage)>,
     message_filter: MessageFilter,
+    header_chain: Option<Arc<Mutex<HeaderChain>>>,
+    version: VersionTemplate,
 }
 
 impl InnerNode {
     async fn new(
         node: Node,
-        tx: Sender<(SocketAddr, Message)>,
-        message_filter: MessageFilter,
-        handshake: Option<HandshakeKind>,
+        tx: Sender<(SocketAddr, InboundMessage)>,
+        builder: &SyntheticNodeBuilder,
     ) -> Self {
         let node = Self {
             node,
+            network: builder.network,
+            max_body_length: builder.max_body_length,
+            raw_decoding: builder.raw_decoding,
+            decode_options: builder.decode_options,
+            lazy_decoding: builder.lazy_decoding,
             inbound_tx: tx,
-            message_filter,
-            handshake,
+            message_filter: builder.message_filter.clone(),
+            handshake: builder.handshake,
+            header_chain: builder
+                .header_chain
+                .clone()
+                .map(|chain| Arc::new(Mutex::new(chain))),
+            version: builder.version_template(),
         };
 
-        if handshake.is_some() {
+        if node.handshake.is_some() {
             node.enable_handshake().await;
         }
 
         node
     }
+
+    /// Returns the [`Version`] message sent to `addr_recv` during handshakes.
+    fn own_version(&self, addr_recv: SocketAddr, peer_nonce: Option<Nonce>) -> Message {
+        let own_listening_addr = self.node().listening_addr().unwrap();
+
+        Message::Version(
+            self.version
+                .build(addr_recv, own_listening_addr, peer_nonce),
+        )
+    }
+
+    fn message_codec(&self) -> MessageCodec {
+        let mut codec = MessageCodec::new(self.network);
+
+        if let Some(max_body_length) = self.max_body_length {
+            codec = codec.with_max_body_length(max_body_length);
+        }
+
+        if self.raw_decoding {
+            codec = codec.raw();
+        }
+
+        codec.with_decode_options(self.decode_options)
+    }
+
+    /// Returns `true` if messages with this command have to be decoded to track their headers.
+    fn tracks_command(&self, command: [u8; COMMAND_LEN]) -> bool {
+        self.header_chain.is_some() && (command == HEADERS_COMMAND || command == BLOCK_COMMAND)
+    }
 }
 
 impl Pea2Pea for InnerNode {
@@ -407,20 +940,113 @@ impl Pea2Pea for InnerNode {
 // TODO: move to protocol
 pub struct MessageCodec {
     codec: LengthDelimitedCodec,
+    network: Network,
+    max_body_length: Option<usize>,
+    raw: bool,
+    decode_options: DecodeOptions,
 }
 
 impl Default for MessageCodec {
     fn default() -> Self {
+        Self::new(Network::default())
+    }
+}
+
+impl MessageCodec {
+    /// Creates a codec which encodes messages for the `network`.
+    ///
+    /// Inbound frames are rejected with a [`DecodeError`] if their magic isn't the `network`'s,
+    /// their checksum doesn't match their body or their body exceeds the protocol's maximum length
+    /// for their command (see [`MessageHeader::max_body_length`]).
+    pub fn new(network: Network) -> Self {
         Self {
             codec: LengthDelimitedCodec::builder()
-                .length_adjustment(24)
+                .length_adjustment(HEADER_LEN as isize)
                 .length_field_offset(16)
                 .little_endian()
                 .num_skip(0)
-                .max_frame_length(65536) // FIXME
+                // Body lengths are checked against `max_body_length` before framing.
+                .max_frame_length(u32::MAX as usize)
                 .new_codec(),
+            network,
+            max_body_length: None,
+            raw: false,
+            decode_options: DecodeOptions::default(),
         }
     }
+
+    /// Applies `max_body_length` to every inbound frame instead of the per-command limits.
+    pub fn with_max_body_length(mut self, max_body_length: usize) -> Self {
+        self.max_body_length = Some(max_body_length);
+        self
+    }
+
+    /// Disables magic and checksum validation of inbound frames, so that messages from misbehaving
+    /// peers can still be decoded (useful for resistance tests). Length limits still apply.
+    pub fn raw(mut self) -> Self {
+        self.raw = true;
+        self
+    }
+
+    /// Sets the options inbound messages are decoded with, lazily decoded ones included.
+    pub fn with_decode_options(mut self, options: DecodeOptions) -> Self {
+        self.decode_options = options;
+        self
+    }
+
+    /// Decodes the next frame into a [`LazyMessage`], validating its header but leaving its body
+    /// undecoded.
+    pub fn decode_lazy(&mut self, src: &mut BytesMut) -> io::Result<Option<LazyMessage>> {
+        // Validate the header as soon as it arrives, rather than buffering a frame we'd reject.
+        if src.len() >= HEADER_LEN {
+            let header = MessageHeader::decode(&mut &src[..HEADER_LEN])?;
+
+            let expected_magic = self.network.magic();
+            if !self.raw && header.magic != expected_magic {
+                return Err(DecodeError::Magic {
+                    expected: expected_magic,
+                    actual: header.magic,
+                }
+                .into());
+            }
+
+            let body_length = header.body_length as usize;
+            let max_body_length = self
+                .max_body_length
+                .unwrap_or_else(|| header.max_body_length());
+
+            if body_length > max_body_length {
+                return Err(DecodeError::FrameTooLarge {
+                    command: header.command,
+                    body_length,
+                    max_body_length,
+                }
+                .into());
+            }
+        }
+
+        let mut bytes = if let Some(bytes) = self.codec.decode(src)? {
+            bytes
+        } else {
+            return Ok(None);
+        };
+
+        let header = MessageHeader::decode(&mut bytes)?;
+
+        let expected_checksum = checksum(&bytes);
+        if !self.raw && header.checksum != expected_checksum {
+            return Err(DecodeError::Checksum {
+                command: header.command,
+                expected: expected_checksum,
+                actual: header.checksum,
+            }
+            .into());
+        }
+
+        Ok(Some(
+            LazyMessage::new(header, bytes.freeze()).with_decode_options(self.decode_options),
+        ))
+    }
 }
 
 impl Decoder for MessageCodec {
@@ -428,19 +1054,42 @@ impl Decoder for MessageCodec {
     type Error = io::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let mut bytes = if let Some(bytes) = self.codec.decode(src)? {
-            bytes
+        let lazy_message = if let Some(lazy_message) = self.decode_lazy(src)? {
+            lazy_message
         } else {
             return Ok(None);
         };
 
-        let header = MessageHeader::decode(&mut bytes)?;
-        let message = Message::decode(header.command, &mut bytes)?;
+        let message = lazy_message
+            .decode()
+            .map_err(|source| DecodeError::Payload {
+                command: lazy_message.command(),
+                source,
+            })?;
 
         Ok(Some(message))
     }
 }
 
+/// Decodes inbound messages eagerly or lazily, depending on the node's configuration.
+struct InboundCodec {
+    codec: MessageCodec,
+    lazy: bool,
+}
+
+impl Decoder for InboundCodec {
+    type Item = InboundMessage;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if self.lazy {
+            Ok(self.codec.decode_lazy(src)?.map(InboundMessage::Lazy))
+        } else {
+            Ok(self.codec.decode(src)?.map(InboundMessage::Decoded))
+        }
+    }
+}
+
 impl Encoder<Vec<u8>> for MessageCodec {
     type Error = io::Error;
 
@@ -455,7 +1104,7 @@ impl Encoder<Message> for MessageCodec {
     type Error = io::Error;
 
     fn encode(&mut self, message: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        message.encode(dst)
+        message.encode(self.network, dst)
     }
 }
 
@@ -478,17 +1127,55 @@ enum MessageOrBytes {
 
 #[async_trait::async_trait]
 impl Reading for InnerNode {
-    type Message = Message;
-    type Codec = MessageCodec;
+    type Message = InboundMessage;
+    type Codec = InboundCodec;
 
     fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+        InboundCodec {
+            codec: self.message_codec(),
+            lazy: self.lazy_decoding,
+        }
     }
 
     async fn process_message(&self, source: SocketAddr, message: Self::Message) -> io::Result<()> {
         let span = self.node().span().clone();
 
+        let (message, lazy) = match message {
+            InboundMessage::Decoded(message) => (message, None),
+            // Only decode lazy messages the filter acts on or whose headers are tracked.
+            InboundMessage::Lazy(message)
+                if self.message_filter.command_filter_type(message.command())
+                    == Filter::Disabled
+                    && !self.tracks_command(message.command()) =>
+            {
+                debug!(
+                    parent: span,
+                    "sending the lazy message to the node's inbound queue"
+                );
+                self.inbound_tx
+                    .send((source, InboundMessage::Lazy(message)))
+                    .await
+                    .expect("receiver dropped!");
+
+                return Ok(());
+            }
+            InboundMessage::Lazy(message) => {
+                let decoded = message.decode().map_err(|source| DecodeError::Payload {
+                    command: message.command(),
+                    source,
+                })?;
+
+                (decoded, Some(message))
+            }
+        };
+
         debug!(parent: span.clone(), "processing {:?}", message);
+        if let Some(chain) = &self.header_chain {
+            for event in chain.lock().observe(&message) {
+                debug!(parent: span.clone(), "header chain: {:?}", event);
+            }
+        }
+
         match self.message_filter.message_filter_type(&message) {
             Filter::AutoReply => {
                 // Autoreply with the appropriate response.
@@ -504,6 +1191,11 @@ impl Reading for InnerNode {
                     parent: span,
                     "sending the message to the node's inbound queue"
                 );
+                // Messages only decoded for header tracking are still queued lazily.
+                let message = match lazy {
+                    Some(lazy) => InboundMessage::Lazy(lazy),
+                    None => InboundMessage::Decoded(message),
+                };
                 self.inbound_tx
                     .send((source, message))
                     .await
@@ -525,7 +1217,7 @@ impl Writing for InnerNode {
     type Codec = MessageCodec;
 
     fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+        self.message_codec()
     }
 }
 
@@ -534,13 +1226,12 @@ impl Handshake for InnerNode {
     async fn perform_handshake(&self, mut conn: Connection) -> io::Result<Connection> {
         let node_conn_side = !conn.side();
         let conn_addr = conn.addr();
-        let own_listening_addr = self.node().listening_addr().unwrap();
-        let mut framed_stream = Framed::new(self.borrow_stream(&mut conn), MessageCodec::default());
+        let mut framed_stream = Framed::new(self.borrow_stream(&mut conn), self.message_codec());
 
         match (self.handshake, node_conn_side) {
             (Some(HandshakeKind::Full), ConnectionSide::Initiator) => {
                 // Send and receive Version.
-                let own_version = Message::Version(Version::new(conn_addr, own_listening_addr));
+                let own_version = self.own_version(conn_addr, None);
                 framed_stream.send(own_version).await?;
 
                 let peer_version = framed_stream.try_next().await?;
@@ -569,8 +1260,8 @@ impl Handshake for InnerNode {
             (Some(HandshakeKind::Full), ConnectionSide::Responder) => {
                 // Receive and send Version.
                 let peer_version = framed_stream.try_next().await?;
-                let node_addr = match peer_version {
-                    Some(Message::Version(version)) => version.addr_from.addr,
+                let (node_addr, peer_nonce) = match peer_version {
+                    Some(Message::Version(version)) => (version.addr_from.addr, version.nonce),
                     Some(other) => {
                         let span = self.node().span().clone();
                         error!(
@@ -582,7 +1273,7 @@ impl Handshake for InnerNode {
                     None => return Err(io::ErrorKind::InvalidData.into()),
                 };
 
-                let own_version = Message::Version(Version::new(node_addr, own_listening_addr));
+                let own_version = self.own_version(node_addr, Some(peer_nonce));
                 framed_stream.send(own_version).await?;
 
                 // Receive and send Verack.
@@ -592,7 +1283,7 @@ impl Handshake for InnerNode {
                 framed_stream.send(Message::Verack).await?;
             }
             (Some(HandshakeKind::VersionOnly), ConnectionSide::Initiator) => {
-                let own_version = Message::Version(Version::new(conn_addr, own_listening_addr));
+                let own_version = self.own_version(conn_addr, None);
                 framed_stream.send(own_version).await?;
 
                 let peer_version = framed_stream.try_next().await?;
@@ -601,8 +1292,8 @@ impl Handshake for InnerNode {
             (Some(HandshakeKind::VersionOnly), ConnectionSide::Responder) => {
                 // Receive and send Version.
                 let peer_version = framed_stream.try_next().await?;
-                let node_addr = match peer_version {
-                    Some(Message::Version(version)) => version.addr_from.addr,
+                let (node_addr, peer_nonce) = match peer_version {
+                    Some(Message::Version(version)) => (version.addr_from.addr, version.nonce),
                     Some(other) => {
                         let span = self.node().span().clone();
                         error!(
@@ -614,7 +1305,7 @@ impl Handshake for InnerNode {
                     None => return Err(io::ErrorKind::InvalidData.into()),
                 };
 
-                let own_version = Message::Version(Version::new(node_addr, own_listening_addr));
+                let own_version = self.own_version(node_addr, Some(peer_nonce));
                 framed_stream.send(own_version).await?;
             }
             (None, _) => {}
@@ -623,3 +1314,267 @@ impl Handshake for InnerNode {
         Ok(conn)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{message::constants::*, payload::block::Block};
+
+    #[test]
+    #[ignore]
+    fn builder_services_survive_version_template() {
+        let addr: SocketAddr = "127.0.0.1:8233".parse().unwrap();
+        let template = VersionTemplate::default().with_user_agent("/synthetic:0.1/");
+
+        for builder in [
+            SyntheticNode::builder()
+                .with_services(Services::NODE_BLOOM)
+                .with_version_template(template.clone()),
+            SyntheticNode::builder()
+                .with_version_template(template.clone())
+                .with_services(Services::NODE_BLOOM),
+        ] {
+            let version = builder.version_template().build(addr, addr, None);
+            assert_eq!(version.services, Services::NODE_BLOOM);
+            assert_eq!(version.user_agent, VarStr("/synthetic:0.1/".to_owned()));
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn version_template_fills_in_version() {
+        let addr: SocketAddr = "127.0.0.1:8233".parse().unwrap();
+        let peer_nonce = Nonce::new(42);
+
+        let template = VersionTemplate::default()
+            .with_version(170_002)
+            .with_services(Services::NODE_NETWORK | Services::NODE_BLOOM)
+            .with_user_agent("/synthetic:0.1/")
+            .with_start_height(1_000)
+            .with_relay(true)
+            .with_nonce(NoncePolicy::EchoPeer)
+            .with_timestamp_skew(time::Duration::hours(3));
+        let version = template.build(addr, addr, Some(peer_nonce));
+
+        assert_eq!(version.version, ProtocolVersion(170_002));
+        assert_eq!(version.addr_from.services, version.services);
+        assert!(version.services.contains(Services::NODE_BLOOM));
+        assert_eq!(version.user_agent, VarStr("/synthetic:0.1/".to_owned()));
+        assert_eq!(version.start_height, 1_000);
+        assert!(version.relay);
+        assert_eq!(version.nonce, peer_nonce);
+        assert!(version.timestamp > time::OffsetDateTime::now_utc() + time::Duration::hours(2));
+
+        // Without the peer's version there's nothing to echo.
+        assert_ne!(template.build(addr, addr, None).nonce, peer_nonce);
+
+        let template = template.with_nonce(NoncePolicy::Fixed(Nonce::new(7)));
+        assert_eq!(
+            template.build(addr, addr, Some(peer_nonce)).nonce,
+            Nonce::new(7)
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn takes_set_aside_response_in_place() {
+        let target: SocketAddr = "127.0.0.1:8233".parse().unwrap();
+        let other: SocketAddr = "127.0.0.1:18233".parse().unwrap();
+        let request = Message::Ping(Nonce::new(1));
+        let expected_kinds = MessageKind::Ping.expected_responses();
+
+        // The response was set aside while waiting on an earlier request, ahead of an unrelated
+        // message from the target and a matching one from another peer.
+        let mut pending = VecDeque::from([
+            (other, InboundMessage::Decoded(Message::Pong(Nonce::new(1)))),
+            (
+                target,
+                InboundMessage::Decoded(Message::Pong(Nonce::new(2))),
+            ),
+            (
+                target,
+                InboundMessage::Decoded(Message::Pong(Nonce::new(1))),
+            ),
+            (target, InboundMessage::Decoded(Message::GetAddr)),
+        ]);
+
+        assert_eq!(
+            take_pending_response(&mut pending, target, &request, expected_kinds),
+            Some(Message::Pong(Nonce::new(1)))
+        );
+        assert_eq!(
+            take_pending_response(&mut pending, target, &request, expected_kinds),
+            None
+        );
+
+        let remaining: Vec<_> = pending
+            .into_iter()
+            .map(|(source, inbound)| match inbound {
+                InboundMessage::Decoded(message) => (source, message),
+                InboundMessage::Lazy(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            remaining,
+            [
+                (other, Message::Pong(Nonce::new(1))),
+                (target, Message::Pong(Nonce::new(2))),
+                (target, Message::GetAddr),
+            ]
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn decodes_frames_larger_than_64_kib() {
+        let mut block = Block::testnet_2();
+        block.txs = vec![block.txs[0].clone(); 1000];
+        let message = Message::Block(Box::new(block));
+
+        let mut codec = MessageCodec::default();
+        let mut buffer = BytesMut::new();
+        codec.encode(message.clone(), &mut buffer).unwrap();
+        assert!(buffer.len() > 64 * 1024);
+
+        assert_eq!(codec.decode(&mut buffer).unwrap(), Some(message));
+    }
+
+    #[test]
+    #[ignore]
+    fn rejects_frame_exceeding_command_limit() {
+        let mut header = MessageHeader::new(Network::default(), BLOCK_COMMAND, &[]);
+        header.body_length = (MAX_BLOCK_SIZE + 1) as u32;
+
+        let mut buffer = BytesMut::new();
+        header.encode(&mut buffer).unwrap();
+
+        let err = MessageCodec::default().decode(&mut buffer).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_matches!(
+            DecodeError::from_io_error(&err),
+            Some(DecodeError::FrameTooLarge {
+                command: BLOCK_COMMAND,
+                body_length,
+                max_body_length: MAX_BLOCK_SIZE,
+            }) if *body_length == MAX_BLOCK_SIZE + 1
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn rejects_frame_exceeding_configured_limit() {
+        let mut codec = MessageCodec::default().with_max_body_length(4);
+        let mut buffer = BytesMut::new();
+        codec
+            .encode(Message::Ping(Nonce::default()), &mut buffer)
+            .unwrap();
+
+        let err = codec.decode(&mut buffer).unwrap_err();
+        assert_matches!(
+            DecodeError::from_io_error(&err),
+            Some(DecodeError::FrameTooLarge {
+                body_length: 8,
+                max_body_length: 4,
+                ..
+            })
+        );
+    }
+
+    fn encode_ping(network: Network) -> BytesMut {
+        let mut buffer = BytesMut::new();
+        Message::Ping(Nonce::default())
+            .encode(network, &mut buffer)
+            .unwrap();
+
+        buffer
+    }
+
+    #[test]
+    #[ignore]
+    fn rejects_mismatched_magic() {
+        let mut buffer = encode_ping(Network::Mainnet);
+
+        let err = MessageCodec::new(Network::Testnet)
+            .decode(&mut buffer)
+            .unwrap_err();
+        assert_matches!(
+            DecodeError::from_io_error(&err),
+            Some(DecodeError::Magic {
+                expected: MAGIC_TESTNET,
+                actual: MAGIC_MAINNET,
+            })
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn rejects_bad_checksum() {
+        let mut buffer = encode_ping(Network::default());
+        // Corrupt the last byte of the nonce.
+        *buffer.last_mut().unwrap() ^= 0xff;
+
+        let err = MessageCodec::default().decode(&mut buffer).unwrap_err();
+        assert_matches!(
+            DecodeError::from_io_error(&err),
+            Some(DecodeError::Checksum {
+                command: PING_COMMAND,
+                ..
+            })
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn rejects_bad_payload() {
+        let body = [0u8; 4];
+        let mut buffer = BytesMut::new();
+        MessageHeader::new(Network::default(), VERSION_COMMAND, &body)
+            .encode(&mut buffer)
+            .unwrap();
+        buffer.put_slice(&body);
+
+        let err = MessageCodec::default().decode(&mut buffer).unwrap_err();
+        assert_matches!(
+            DecodeError::from_io_error(&err),
+            Some(DecodeError::Payload {
+                command: VERSION_COMMAND,
+                ..
+            })
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn raw_codec_skips_magic_and_checksum_validation() {
+        let mut buffer = encode_ping(Network::Mainnet);
+        // Corrupt the checksum.
+        buffer[HEADER_LEN - 1] ^= 0xff;
+
+        let message = MessageCodec::new(Network::Testnet)
+            .raw()
+            .decode(&mut buffer)
+            .unwrap();
+        assert_matches!(message, Some(Message::Ping(_)));
+    }
+
+    #[test]
+    #[ignore]
+    fn decode_lazy_leaves_body_undecoded() {
+        let block = Block::testnet_2();
+        let mut buffer = BytesMut::new();
+        Message::Block(Box::new(block.clone()))
+            .encode(Network::default(), &mut buffer)
+            .unwrap();
+
+        let lazy_message = MessageCodec::default()
+            .decode_lazy(&mut buffer)
+            .unwrap()
+            .unwrap();
+        assert!(buffer.is_empty());
+        assert_eq!(lazy_message.command(), BLOCK_COMMAND);
+        assert_eq!(
+            lazy_message.view().block_hash().unwrap(),
+            block.double_sha256().unwrap()
+        );
+    }
+}